@@ -0,0 +1,667 @@
+use crate::models::Coord;
+use rand::Rng;
+
+/// The playfield that pieces are dropped onto. Cells are addressed by
+/// `Coord`, with row 0 at the top of the board and column 0 on the left.
+/// Above the board is a vanish zone of `VANISH_ROWS` hidden rows that the
+/// active piece may move into, but where nothing can be locked.
+pub struct TetrisBoard {
+    pub board: Vec<Vec<bool>>,
+    pub clear_style: ClearStyle,
+    pub garbage_rules: GarbageRules,
+    /// Hole column of each garbage row waiting to rise, oldest first, with
+    /// the number of pieces still to lock before it can rise.
+    garbage_queue: Vec<(usize, u64)>,
+    /// Hole column of the last garbage row received.
+    last_hole: Option<usize>,
+    /// Number of rows at the bottom of the stack that rose as garbage and
+    /// have not been cleared.
+    garbage_rows: usize,
+    /// Number of garbage rows cleared by filling them.
+    garbage_cleared: usize,
+    /// Number of full rows banked at the very bottom of the board by the
+    /// zone, waiting to be cleared together.
+    banked_rows: usize,
+}
+impl Default for TetrisBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl TetrisBoard {
+    pub const NUM_ROWS: usize = 20;
+    pub const NUM_COLS: usize = 10;
+    pub const VANISH_ROWS: usize = 2;
+
+    pub fn new() -> Self {
+        Self::with_clear_style(ClearStyle::Naive)
+    }
+    pub fn with_clear_style(clear_style: ClearStyle) -> Self {
+        let row = vec![false; Self::NUM_COLS];
+        Self {
+            board: vec![row; Self::NUM_ROWS],
+            clear_style,
+            garbage_rules: GarbageRules::default(),
+            garbage_queue: Vec::new(),
+            last_hole: None,
+            garbage_rows: 0,
+            garbage_cleared: 0,
+            banked_rows: 0,
+        }
+    }
+    pub fn is_on_board(coord: &Coord) -> bool {
+        coord.row >= 0
+            && coord.row < Self::NUM_ROWS as i16
+            && coord.col >= 0
+            && coord.col < Self::NUM_COLS as i16
+    }
+    /// Returns whether a piece may occupy the cell, which includes the
+    /// vanish zone above the board.
+    fn is_in_play_area(coord: &Coord) -> bool {
+        coord.row >= -(Self::VANISH_ROWS as i16)
+            && coord.row < Self::NUM_ROWS as i16
+            && coord.col >= 0
+            && coord.col < Self::NUM_COLS as i16
+    }
+    fn check_coordinates_on_board(&self, coordinates: &[Coord]) -> bool {
+        coordinates.iter().all(Self::is_in_play_area)
+    }
+    /// Returns whether the cell at `coord` is filled. Cells off the board
+    /// are never occupied.
+    pub fn is_occupied(&self, coord: &Coord) -> bool {
+        Self::is_on_board(coord) && self.board[coord.row as usize][coord.col as usize]
+    }
+    pub fn check_is_valid_position(&self, coordinates: &[Coord]) -> PiecePositionValidity {
+        if !self.check_coordinates_on_board(coordinates) {
+            return PiecePositionValidity::OffOfBoard;
+        }
+        for coord in coordinates {
+            if self.is_occupied(coord) {
+                return PiecePositionValidity::PieceCollision;
+            }
+        }
+        return PiecePositionValidity::Valid;
+    }
+    /// Fills each of the given cells. Coordinates off the board are ignored.
+    /// Fills the cell if it is empty, or empties it if it is filled.
+    pub fn toggle_cell(&mut self, coord: &Coord) {
+        if Self::is_on_board(coord) {
+            let cell = &mut self.board[coord.row as usize][coord.col as usize];
+            *cell = !*cell;
+        }
+    }
+    pub fn place_cells(&mut self, coordinates: &[Coord]) {
+        for coord in coordinates {
+            if Self::is_on_board(coord) {
+                self.board[coord.row as usize][coord.col as usize] = true;
+            }
+        }
+    }
+    /// Indices of every row containing at least one filled cell, top to bottom.
+    pub fn occupied_rows(&self) -> Vec<usize> {
+        (0..Self::NUM_ROWS)
+            .filter(|&row| self.board[row].iter().any(|x| *x))
+            .collect()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.board.iter().all(|row| row.iter().all(|x| !*x))
+    }
+    /// Indices of every completely filled row, top to bottom.
+    pub fn full_rows(&self) -> Vec<usize> {
+        (0..Self::NUM_ROWS)
+            .filter(|&row| self.board[row].iter().all(|x| *x))
+            .collect()
+    }
+    pub fn clear_rows(&mut self) -> u16 {
+        self.forget_cleared_garbage();
+        let mut board_without_row: Vec<Vec<bool>> = self
+            .board
+            .clone()
+            .into_iter()
+            .rev()
+            .filter(|row| !row.iter().all(|x| *x))
+            .collect();
+        let num_cleared_rows = Self::NUM_ROWS - board_without_row.len();
+        for _ in 0..num_cleared_rows {
+            board_without_row.push(vec![false; Self::NUM_COLS]);
+        }
+        self.board = board_without_row.into_iter().rev().collect();
+        return num_cleared_rows as u16;
+    }
+    /// Removes a row, moving every row above it down by one.
+    pub fn remove_row(&mut self, row: usize) {
+        let first_banked_row = Self::NUM_ROWS - self.banked_rows;
+        if row >= first_banked_row {
+            self.banked_rows -= 1;
+        } else if row >= first_banked_row - self.garbage_rows {
+            self.garbage_rows -= 1;
+        }
+        self.board.remove(row);
+        self.board.insert(0, vec![false; Self::NUM_COLS]);
+    }
+    /// Clears full rows following the board's clear style. Returns the
+    /// number of rows cleared by each step of the chain, which is a single
+    /// step unless cascades clear further rows. Empty if nothing cleared.
+    pub fn clear_lines(&mut self) -> Vec<u16> {
+        let mut chain = Vec::new();
+        match self.clear_style {
+            ClearStyle::Naive => {
+                let rows = self.clear_rows();
+                if rows > 0 {
+                    chain.push(rows);
+                }
+            }
+            ClearStyle::Cascade => loop {
+                let full_rows = self.full_rows();
+                if full_rows.is_empty() {
+                    break;
+                }
+                self.forget_cleared_garbage();
+                for &row in &full_rows {
+                    self.board[row] = vec![false; Self::NUM_COLS];
+                }
+                self.drop_floating_groups();
+                chain.push(full_rows.len() as u16);
+            },
+        }
+        return chain;
+    }
+    /// Drops every group of connected cells until it rests on the floor or
+    /// on another group, lowest groups first.
+    fn drop_floating_groups(&mut self) {
+        loop {
+            let mut groups = self.connected_groups();
+            groups
+                .sort_by_key(|group| std::cmp::Reverse(group.iter().map(|coord| coord.row).max()));
+            let mut any_dropped = false;
+            for group in groups {
+                for coord in &group {
+                    self.board[coord.row as usize][coord.col as usize] = false;
+                }
+                let mut distance = 0;
+                while group.iter().all(|coord| {
+                    let below = Coord {
+                        col: coord.col,
+                        row: coord.row + distance + 1,
+                    };
+                    Self::is_on_board(&below) && !self.is_occupied(&below)
+                }) {
+                    distance += 1;
+                }
+                any_dropped |= distance > 0;
+                for coord in &group {
+                    self.board[(coord.row + distance) as usize][coord.col as usize] = true;
+                }
+            }
+            if !any_dropped {
+                return;
+            }
+        }
+    }
+    /// Every group of filled cells joined by their edges.
+    fn connected_groups(&self) -> Vec<Vec<Coord>> {
+        let mut seen = vec![vec![false; Self::NUM_COLS]; Self::NUM_ROWS];
+        let mut groups = Vec::new();
+        for row in 0..Self::NUM_ROWS {
+            for col in 0..Self::NUM_COLS {
+                if !self.board[row][col] || seen[row][col] {
+                    continue;
+                }
+                seen[row][col] = true;
+                let mut group = Vec::new();
+                let mut to_visit = vec![Coord {
+                    col: col as i16,
+                    row: row as i16,
+                }];
+                while let Some(coord) = to_visit.pop() {
+                    for (col_offset, row_offset) in [(0, 1), (0, -1), (1, 0), (-1, 0)] {
+                        let neighbour = Coord {
+                            col: coord.col + col_offset,
+                            row: coord.row + row_offset,
+                        };
+                        if self.is_occupied(&neighbour)
+                            && !seen[neighbour.row as usize][neighbour.col as usize]
+                        {
+                            seen[neighbour.row as usize][neighbour.col as usize] = true;
+                            to_visit.push(neighbour);
+                        }
+                    }
+                    group.push(coord);
+                }
+                groups.push(group);
+            }
+        }
+        return groups;
+    }
+    /// Queues `num_rows` garbage rows, each with a single hole. Each hole
+    /// moves to another random column from the row before with the garbage
+    /// rules' messiness. The rows rise from the bottom when `raise_garbage`
+    /// is called, once their entry delay has passed.
+    pub fn receive_garbage(&mut self, num_rows: usize) {
+        for _ in 0..num_rows {
+            let hole = self.next_hole();
+            self.garbage_queue
+                .push((hole, self.garbage_rules.entry_delay));
+        }
+    }
+    /// Raises `num_rows` garbage rows straight away, skipping the queue.
+    /// Returns whether any blocks were pushed off the top of the board.
+    pub fn raise_garbage_now(&mut self, num_rows: usize) -> bool {
+        let mut pushed_off = false;
+        for _ in 0..num_rows {
+            let hole = self.next_hole();
+            pushed_off |= self.push_garbage_row(hole);
+        }
+        return pushed_off;
+    }
+    /// Picks the hole of the next garbage row, which moves to another
+    /// random column from the last hole with the garbage rules' messiness.
+    fn next_hole(&mut self) -> usize {
+        let mut rng = rand::thread_rng();
+        let change_chance = (self.garbage_rules.messiness as f64 / 100.0).min(1.0);
+        let hole = match self.last_hole {
+            Some(last_hole) if !rng.gen_bool(change_chance) => last_hole,
+            Some(last_hole) => {
+                let hole = rng.gen_range(0..Self::NUM_COLS - 1);
+                if hole >= last_hole {
+                    hole + 1
+                } else {
+                    hole
+                }
+            }
+            None => rng.gen_range(0..Self::NUM_COLS),
+        };
+        self.last_hole = Some(hole);
+        return hole;
+    }
+    /// Counts a locked piece off the entry delay of every queued garbage
+    /// row.
+    pub fn count_down_garbage(&mut self) {
+        for (_, pieces_left) in self.garbage_queue.iter_mut() {
+            *pieces_left = pieces_left.saturating_sub(1);
+        }
+    }
+    /// Takes full garbage rows, about to be cleared, off the garbage count.
+    fn forget_cleared_garbage(&mut self) {
+        let first_garbage_row = Self::NUM_ROWS - self.garbage_rows;
+        let cleared_garbage = self
+            .full_rows()
+            .into_iter()
+            .filter(|&row| row >= first_garbage_row)
+            .count();
+        self.garbage_rows -= cleared_garbage;
+        self.garbage_cleared += cleared_garbage;
+    }
+    /// Moves full rows to the bottom of the board, below the rest of the
+    /// stack and above any rows banked before them, instead of clearing
+    /// them. Returns the number of rows banked.
+    pub fn bank_full_rows(&mut self) -> u16 {
+        let first_banked_row = Self::NUM_ROWS - self.banked_rows;
+        let first_garbage_row = first_banked_row - self.garbage_rows;
+        let full_rows: Vec<usize> = self
+            .full_rows()
+            .into_iter()
+            .filter(|&row| row < first_banked_row)
+            .collect();
+        let banked_garbage = full_rows
+            .iter()
+            .filter(|&&row| row >= first_garbage_row)
+            .count();
+        self.garbage_rows -= banked_garbage;
+        self.garbage_cleared += banked_garbage;
+        let banked = self.board.split_off(first_banked_row);
+        let (newly_banked, rest): (Vec<Vec<bool>>, Vec<Vec<bool>>) =
+            self.board.drain(..).partition(|row| row.iter().all(|x| *x));
+        self.board = rest;
+        self.board.extend(newly_banked);
+        self.board.extend(banked);
+        self.banked_rows += full_rows.len();
+        return full_rows.len() as u16;
+    }
+    /// Clears every banked row at once. Returns the number cleared.
+    pub fn clear_banked_rows(&mut self) -> u16 {
+        let banked_rows = self.banked_rows;
+        self.board.truncate(Self::NUM_ROWS - banked_rows);
+        for _ in 0..banked_rows {
+            self.board.insert(0, vec![false; Self::NUM_COLS]);
+        }
+        self.banked_rows = 0;
+        return banked_rows as u16;
+    }
+    /// Number of rows banked at the bottom of the board by the zone.
+    pub fn banked_rows(&self) -> usize {
+        self.banked_rows
+    }
+    /// Number of risen garbage rows still on the board.
+    pub fn garbage_rows(&self) -> usize {
+        self.garbage_rows
+    }
+    /// Number of garbage rows cleared or banked so far.
+    pub fn garbage_cleared(&self) -> usize {
+        self.garbage_cleared
+    }
+    /// Number of garbage rows waiting to rise.
+    pub fn pending_garbage(&self) -> usize {
+        self.garbage_queue.len()
+    }
+    /// Cancels up to `num_rows` queued garbage rows, newest first, and
+    /// returns how many were cancelled.
+    pub fn cancel_garbage(&mut self, num_rows: usize) -> usize {
+        let cancelled = num_rows.min(self.garbage_queue.len());
+        self.garbage_queue
+            .truncate(self.garbage_queue.len() - cancelled);
+        return cancelled;
+    }
+    /// Pushes every queued garbage row whose entry delay has passed in from
+    /// the bottom of the board, shifting the stack up. Returns whether any
+    /// filled cells were pushed off the top of the board.
+    pub fn raise_garbage(&mut self) -> bool {
+        let mut pushed_off = false;
+        let ready = self
+            .garbage_queue
+            .iter()
+            .take_while(|(_, pieces_left)| *pieces_left == 0)
+            .count();
+        let ready_holes: Vec<usize> = self
+            .garbage_queue
+            .drain(..ready)
+            .map(|(hole, _)| hole)
+            .collect();
+        for hole in ready_holes {
+            pushed_off |= self.push_garbage_row(hole);
+        }
+        return pushed_off;
+    }
+    /// Pushes a garbage row with a hole at `hole` onto the bottom of the
+    /// board, shifting everything up a row. Returns whether the top row
+    /// had any blocks in it.
+    fn push_garbage_row(&mut self, hole: usize) -> bool {
+        let top_row = self.board.remove(0);
+        let mut garbage_row = vec![true; Self::NUM_COLS];
+        garbage_row[hole] = false;
+        self.board.push(garbage_row);
+        self.garbage_rows = (self.garbage_rows + 1).min(Self::NUM_ROWS);
+        return top_row.iter().any(|x| *x);
+    }
+}
+/// How incoming garbage is laid out and when it rises.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GarbageRules {
+    /// Percentage chance of each garbage row's hole moving to a different
+    /// column from the row before. 0 keeps a single clean well.
+    pub messiness: u64,
+    /// Whether clearing lines cancels queued garbage, a row for each line
+    /// cleared, before it can rise.
+    pub cancel: bool,
+    /// Pieces that must lock after garbage is received before it rises.
+    pub entry_delay: u64,
+}
+impl Default for GarbageRules {
+    fn default() -> Self {
+        Self {
+            messiness: 100,
+            cancel: true,
+            entry_delay: 0,
+        }
+    }
+}
+/// How the stack falls after rows are cleared.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClearStyle {
+    /// Everything above a cleared row moves down by one row, leaving any
+    /// overhangs floating.
+    Naive,
+    /// Each group of connected cells falls until it lands, which can
+    /// complete more rows and clear them as a chain.
+    Cascade,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PiecePositionValidity {
+    Valid,
+    OffOfBoard,
+    PieceCollision,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_board_is_ten_by_twenty() {
+        let tetris_board = TetrisBoard::new();
+        assert_eq!(tetris_board.board.len(), 20);
+        for row in &tetris_board.board {
+            assert_eq!(row.len(), 10);
+        }
+    }
+
+    #[test]
+    fn test_piece_position_validity_returns_off_board() {
+        let tetris_board = TetrisBoard::new();
+        let off_board_coords = vec![
+            vec![Coord { row: 0, col: -1 }],
+            vec![Coord { row: -3, col: 0 }],
+            vec![Coord { row: 0, col: 10 }],
+            vec![Coord { row: 20, col: 0 }],
+        ];
+        for coord in off_board_coords {
+            assert_eq!(
+                tetris_board.check_is_valid_position(&coord),
+                PiecePositionValidity::OffOfBoard
+            );
+        }
+    }
+
+    #[test]
+    fn test_piece_position_validity_returns_valid() {
+        let tetris_board = TetrisBoard::new();
+        assert_eq!(
+            tetris_board.check_is_valid_position(&[Coord { row: 0, col: 0 }]),
+            PiecePositionValidity::Valid
+        );
+    }
+    #[test]
+    fn test_vanish_zone_is_valid_but_not_on_board() {
+        let tetris_board = TetrisBoard::new();
+        let coords = [Coord { row: -2, col: 0 }];
+        assert_eq!(
+            tetris_board.check_is_valid_position(&coords),
+            PiecePositionValidity::Valid
+        );
+        assert!(!TetrisBoard::is_on_board(&coords[0]));
+    }
+    #[test]
+    fn test_piece_position_validity_returns_collision() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.board[0][0] = true;
+        assert_eq!(
+            tetris_board.check_is_valid_position(&[Coord { row: 0, col: 0 }]),
+            PiecePositionValidity::PieceCollision
+        );
+    }
+
+    #[test]
+    fn test_place_cells_fills_board() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.place_cells(&[Coord { row: 19, col: 0 }, Coord { row: 25, col: 0 }]);
+        assert!(tetris_board.is_occupied(&Coord { row: 19, col: 0 }));
+        assert!(!tetris_board.is_occupied(&Coord { row: 18, col: 0 }));
+        assert!(!tetris_board.is_occupied(&Coord { row: 25, col: 0 }));
+    }
+
+    #[test]
+    fn test_occupied_and_full_rows() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.board[19] = vec![true; TetrisBoard::NUM_COLS];
+        tetris_board.board[17][3] = true;
+        assert_eq!(tetris_board.occupied_rows(), vec![17, 19]);
+        assert_eq!(tetris_board.full_rows(), vec![19]);
+    }
+
+    #[test]
+    fn test_clear_rows_returns_num_full_rows() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.board[10] = vec![true; TetrisBoard::NUM_COLS];
+        tetris_board.board[11] = vec![true; TetrisBoard::NUM_COLS];
+        tetris_board.board[12] = vec![true; TetrisBoard::NUM_COLS];
+        assert_eq!(3, tetris_board.clear_rows());
+    }
+
+    #[test]
+    fn test_clear_rows_shifts_rows() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.board[TetrisBoard::NUM_ROWS - 1] = vec![true; TetrisBoard::NUM_COLS];
+        tetris_board.board[TetrisBoard::NUM_ROWS - 2][0] = true;
+        tetris_board.clear_rows();
+        assert!(tetris_board.board[TetrisBoard::NUM_ROWS - 1][0]);
+    }
+
+    #[test]
+    fn test_garbage_rises_with_one_hole_per_row() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.board[TetrisBoard::NUM_ROWS - 1][0] = true;
+        tetris_board.receive_garbage(3);
+        assert_eq!(tetris_board.pending_garbage(), 3);
+        assert!(!tetris_board.raise_garbage());
+        assert_eq!(tetris_board.pending_garbage(), 0);
+        assert!(tetris_board.board[TetrisBoard::NUM_ROWS - 4][0]);
+        for row in TetrisBoard::NUM_ROWS - 3..TetrisBoard::NUM_ROWS {
+            let holes = tetris_board.board[row].iter().filter(|x| !**x).count();
+            assert_eq!(holes, 1);
+        }
+    }
+
+    #[test]
+    fn test_cleared_garbage_rows_are_counted_off() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.receive_garbage(3);
+        tetris_board.raise_garbage();
+        assert_eq!(tetris_board.garbage_rows(), 3);
+        tetris_board.board[18] = vec![true; TetrisBoard::NUM_COLS];
+        tetris_board.board[16] = vec![true; TetrisBoard::NUM_COLS];
+        tetris_board.clear_rows();
+        assert_eq!(tetris_board.garbage_rows(), 2);
+        assert_eq!(tetris_board.garbage_cleared(), 1);
+    }
+
+    #[test]
+    fn test_garbage_pushing_stack_off_top_is_reported() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.board[0][5] = true;
+        tetris_board.receive_garbage(1);
+        assert!(tetris_board.raise_garbage());
+    }
+
+    #[test]
+    fn test_garbage_raised_now_skips_the_queue() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.garbage_rules.entry_delay = 3;
+        tetris_board.receive_garbage(1);
+        assert!(!tetris_board.raise_garbage_now(2));
+        assert_eq!(tetris_board.garbage_rows(), 2);
+        assert_eq!(tetris_board.pending_garbage(), 1);
+    }
+
+    #[test]
+    fn test_cancel_garbage() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.receive_garbage(2);
+        assert_eq!(tetris_board.cancel_garbage(5), 2);
+        assert!(!tetris_board.raise_garbage());
+        assert!(tetris_board.is_empty());
+    }
+
+    #[test]
+    fn test_garbage_messiness_moves_the_hole() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.garbage_rules.messiness = 0;
+        tetris_board.receive_garbage(5);
+        tetris_board.raise_garbage();
+        let hole = tetris_board.board[19].iter().position(|x| !*x);
+        for row in 15..TetrisBoard::NUM_ROWS {
+            assert_eq!(tetris_board.board[row].iter().position(|x| !*x), hole);
+        }
+        tetris_board.garbage_rules.messiness = 100;
+        tetris_board.receive_garbage(5);
+        tetris_board.raise_garbage();
+        for row in 15..TetrisBoard::NUM_ROWS {
+            let hole = tetris_board.board[row].iter().position(|x| !*x);
+            let hole_above = tetris_board.board[row - 1].iter().position(|x| !*x);
+            assert_ne!(hole, hole_above);
+        }
+    }
+
+    #[test]
+    fn test_garbage_waits_out_its_entry_delay() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.garbage_rules.entry_delay = 2;
+        tetris_board.receive_garbage(1);
+        tetris_board.count_down_garbage();
+        assert!(!tetris_board.raise_garbage());
+        assert!(tetris_board.is_empty());
+        tetris_board.count_down_garbage();
+        tetris_board.raise_garbage();
+        assert_eq!(tetris_board.garbage_rows(), 1);
+        assert_eq!(tetris_board.pending_garbage(), 0);
+    }
+
+    #[test]
+    fn test_naive_clear_leaves_overhangs_floating() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.board[19] = vec![true; TetrisBoard::NUM_COLS];
+        tetris_board.board[18] = vec![true; TetrisBoard::NUM_COLS];
+        tetris_board.board[18][0] = false;
+        tetris_board.board[17][1] = true;
+        assert_eq!(tetris_board.clear_lines(), vec![1]);
+        assert!(tetris_board.board[18][1]);
+        assert!(!tetris_board.board[19][0]);
+    }
+
+    #[test]
+    fn test_cascade_clear_drops_groups_and_chains() {
+        let mut tetris_board = TetrisBoard::with_clear_style(ClearStyle::Cascade);
+        tetris_board.board[19] = vec![true; TetrisBoard::NUM_COLS];
+        tetris_board.board[19][0] = false;
+        tetris_board.board[18] = vec![true; TetrisBoard::NUM_COLS];
+        // A separate cell resting on the full row falls into the hole below
+        // it, completing the bottom row.
+        tetris_board.board[17][0] = true;
+        assert_eq!(tetris_board.clear_lines(), vec![1, 1]);
+        assert!(tetris_board.is_empty());
+    }
+
+    #[test]
+    fn test_cascade_groups_fall_as_one() {
+        let mut tetris_board = TetrisBoard::with_clear_style(ClearStyle::Cascade);
+        tetris_board.board[19][5] = true;
+        tetris_board.board[18] = vec![true; TetrisBoard::NUM_COLS];
+        tetris_board.board[17][0] = true;
+        tetris_board.board[17][1] = true;
+        tetris_board.board[16][1] = true;
+        assert_eq!(tetris_board.clear_lines(), vec![1]);
+        assert!(tetris_board.board[19][0]);
+        assert!(tetris_board.board[19][1]);
+        assert!(tetris_board.board[18][1]);
+        assert!(tetris_board.board[19][5]);
+    }
+
+    #[test]
+    fn test_banked_rows_sink_to_the_bottom_until_cleared() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.board[19][0] = true;
+        tetris_board.board[18] = vec![true; TetrisBoard::NUM_COLS];
+        tetris_board.board[17][3] = true;
+        assert_eq!(tetris_board.bank_full_rows(), 1);
+        assert!(tetris_board.board[19].iter().all(|x| *x));
+        assert!(tetris_board.board[18][0]);
+        assert!(tetris_board.board[17][3]);
+        tetris_board.board[17] = vec![true; TetrisBoard::NUM_COLS];
+        assert_eq!(tetris_board.bank_full_rows(), 1);
+        assert_eq!(tetris_board.banked_rows(), 2);
+        assert_eq!(tetris_board.occupied_rows(), vec![17, 18, 19]);
+        assert_eq!(tetris_board.clear_banked_rows(), 2);
+        assert_eq!(tetris_board.occupied_rows(), vec![19]);
+        assert!(tetris_board.board[19][0]);
+    }
+}
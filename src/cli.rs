@@ -0,0 +1,180 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::keymap::KeymapPreset;
+use crate::mode::GameMode;
+use crate::piece::PieceSet;
+
+/// Options given on the command line.
+#[derive(Debug, Default, PartialEq)]
+pub struct CliArgs {
+    /// Mode to play, skipping the start menu.
+    pub mode: Option<GameMode>,
+    /// Piece set to play with, overriding the config.
+    pub piece_set: Option<PieceSet>,
+    /// Puzzle file to play, in puzzle mode.
+    pub puzzle: Option<PathBuf>,
+    /// Keys to play with, overriding the config.
+    pub keymap: Option<KeymapPreset>,
+    /// Whether to steer with the mouse, as well as the keys.
+    pub mouse: bool,
+    /// Whether to draw with plain ASCII and no colour.
+    pub ascii: bool,
+    /// File to record the game's inputs to.
+    pub record: Option<PathBuf>,
+    /// Recording to play back instead of playing.
+    pub replay: Option<PathBuf>,
+}
+impl CliArgs {
+    pub const USAGE: &'static str =
+        "Usage: tetris [--mode <endless|marathon|invisible|big|party|cheese|puzzle|sprint|zen|master|survival|dig-race|practice|daily>] [--pieces <tetrominoes|pentominoes>] [--puzzle <file>] [--keymap <default|vim|wasd|left-hand|right-hand>] [--mouse] [--ascii] [--record <file>] [--replay <file>]";
+
+    /// Parses the arguments after the program name.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, CliError> {
+        let mut cli_args = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            match flag.as_str() {
+                "--mode" => {
+                    let name = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| CliError(String::from("--mode needs a mode name")))?;
+                    let mode = GameMode::from_name(&name)
+                        .ok_or_else(|| CliError(format!("unknown mode `{}`", name)))?;
+                    cli_args.mode = Some(mode);
+                }
+                "--pieces" => {
+                    let name = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| CliError(String::from("--pieces needs a piece set")))?;
+                    let piece_set = PieceSet::from_name(&name)
+                        .ok_or_else(|| CliError(format!("unknown piece set `{}`", name)))?;
+                    cli_args.piece_set = Some(piece_set);
+                }
+                "--puzzle" => {
+                    let path = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| CliError(String::from("--puzzle needs a file")))?;
+                    cli_args.puzzle = Some(PathBuf::from(path));
+                }
+                "--mouse" => cli_args.mouse = true,
+                "--ascii" => cli_args.ascii = true,
+                "--record" => {
+                    let path = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| CliError(String::from("--record needs a file")))?;
+                    cli_args.record = Some(PathBuf::from(path));
+                }
+                "--replay" => {
+                    let path = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| CliError(String::from("--replay needs a file")))?;
+                    cli_args.replay = Some(PathBuf::from(path));
+                }
+                "--keymap" => {
+                    let name = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| CliError(String::from("--keymap needs a preset")))?;
+                    let preset = KeymapPreset::from_name(&name)
+                        .ok_or_else(|| CliError(format!("unknown keymap `{}`", name)))?;
+                    cli_args.keymap = Some(preset);
+                }
+                other => return Err(CliError(format!("unknown argument `{}`", other))),
+            }
+        }
+        Ok(cli_args)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CliError(String);
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\n{}", self.0, CliArgs::USAGE)
+    }
+}
+impl std::error::Error for CliError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliArgs, CliError> {
+        CliArgs::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_mode_flag() {
+        assert_eq!(parse(&[]).unwrap().mode, None);
+        assert_eq!(
+            parse(&["--mode", "invisible"]).unwrap().mode,
+            Some(GameMode::Invisible)
+        );
+        assert_eq!(
+            parse(&["--mode=marathon"]).unwrap().mode,
+            Some(GameMode::Marathon)
+        );
+    }
+
+    #[test]
+    fn test_pieces_flag() {
+        let cli_args = parse(&["--pieces", "pentominoes", "--mode=big"]).unwrap();
+        assert_eq!(cli_args.piece_set, Some(PieceSet::Pentominoes));
+        assert_eq!(cli_args.mode, Some(GameMode::Big));
+    }
+
+    #[test]
+    fn test_puzzle_flag() {
+        assert_eq!(
+            parse(&["--puzzle", "pc.txt"]).unwrap().puzzle,
+            Some(PathBuf::from("pc.txt"))
+        );
+        assert!(parse(&["--puzzle"]).is_err());
+    }
+
+    #[test]
+    fn test_keymap_flag() {
+        assert_eq!(
+            parse(&["--keymap", "vim"]).unwrap().keymap,
+            Some(KeymapPreset::VIM)
+        );
+        assert!(parse(&["--keymap=dvorak"]).is_err());
+    }
+
+    #[test]
+    fn test_mouse_flag() {
+        assert!(!parse(&[]).unwrap().mouse);
+        assert!(parse(&["--mouse", "--mode", "zen"]).unwrap().mouse);
+    }
+
+    #[test]
+    fn test_ascii_flag() {
+        assert!(!parse(&[]).unwrap().ascii);
+        assert!(parse(&["--ascii"]).unwrap().ascii);
+    }
+
+    #[test]
+    fn test_record_and_replay_flags() {
+        assert_eq!(
+            parse(&["--record=game.txt"]).unwrap().record,
+            Some(PathBuf::from("game.txt"))
+        );
+        assert!(parse(&["--record"]).is_err());
+        assert_eq!(
+            parse(&["--replay", "game.txt"]).unwrap().replay,
+            Some(PathBuf::from("game.txt"))
+        );
+    }
+
+    #[test]
+    fn test_bad_arguments_are_errors() {
+        assert!(parse(&["--mode"]).is_err());
+        assert!(parse(&["--pieces", "trominoes"]).is_err());
+        assert!(parse(&["--mode", "versus"]).is_err());
+        assert!(parse(&["--fast"]).is_err());
+    }
+}
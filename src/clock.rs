@@ -0,0 +1,128 @@
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Where timers and the frame loop get the time from, so that their timing
+/// can be tested on a `MockClock` without waiting.
+pub trait Clock: Clone + Send + 'static {
+    fn now(&self) -> Instant;
+    fn sleep_until(&self, deadline: Instant);
+    /// Waits for a message on `receiver` until `deadline`.
+    fn recv_until<T>(
+        &self,
+        receiver: &mpsc::Receiver<T>,
+        deadline: Instant,
+    ) -> Result<T, mpsc::RecvTimeoutError> {
+        receiver.recv_timeout(deadline.saturating_duration_since(self.now()))
+    }
+}
+
+/// The time as it passes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SystemClock;
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+    fn sleep_until(&self, deadline: Instant) {
+        thread::sleep(deadline.saturating_duration_since(Instant::now()));
+    }
+}
+
+/// A clock that only moves when it is advanced, for tests. Sleeping or
+/// waiting on it lasts until it has been advanced to the deadline, however
+/// long that takes. Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    time: Arc<(Mutex<Instant>, Condvar)>,
+}
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl MockClock {
+    /// How often a wait for a message checks for one, as sending does not
+    /// wake the clock.
+    const POLL: Duration = Duration::from_millis(1);
+
+    pub fn new() -> Self {
+        Self {
+            time: Arc::new((Mutex::new(Instant::now()), Condvar::new())),
+        }
+    }
+    pub fn advance(&self, by: Duration) {
+        let (time, advanced) = &*self.time;
+        *time.lock().expect("Mock clock poisoned.") += by;
+        advanced.notify_all();
+    }
+}
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.time.0.lock().expect("Mock clock poisoned.")
+    }
+    fn sleep_until(&self, deadline: Instant) {
+        let (time, advanced) = &*self.time;
+        let mut now = time.lock().expect("Mock clock poisoned.");
+        while *now < deadline {
+            now = advanced.wait(now).expect("Mock clock poisoned.");
+        }
+    }
+    fn recv_until<T>(
+        &self,
+        receiver: &mpsc::Receiver<T>,
+        deadline: Instant,
+    ) -> Result<T, mpsc::RecvTimeoutError> {
+        let (time, advanced) = &*self.time;
+        loop {
+            // The time is read before the channel, so that anything sent
+            // before the clock reached the deadline is received first.
+            let now = time.lock().expect("Mock clock poisoned.");
+            let reached = *now >= deadline;
+            match receiver.try_recv() {
+                Ok(message) => return Ok(message),
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    return Err(mpsc::RecvTimeoutError::Disconnected)
+                }
+                Err(mpsc::TryRecvError::Empty) if reached => {
+                    return Err(mpsc::RecvTimeoutError::Timeout)
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+            }
+            let _waited = advanced
+                .wait_timeout(now, Self::POLL)
+                .expect("Mock clock poisoned.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_only_moves_when_advanced() {
+        let clock = MockClock::new();
+        let start = clock.now();
+        assert_eq!(clock.now(), start);
+        let sleeper = clock.clone();
+        let sleep = thread::spawn(move || sleeper.sleep_until(start + Duration::from_secs(60)));
+        clock.advance(Duration::from_secs(60));
+        sleep.join().unwrap();
+        assert_eq!(clock.now(), start + Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_mock_clock_waits_for_messages_until_the_deadline() {
+        let clock = MockClock::new();
+        let (sender, receiver) = mpsc::channel();
+        sender.send(1).unwrap();
+        let deadline = clock.now() + Duration::from_secs(1);
+        assert_eq!(clock.recv_until(&receiver, deadline), Ok(1));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(
+            clock.recv_until(&receiver, deadline),
+            Err(mpsc::RecvTimeoutError::Timeout)
+        );
+    }
+}
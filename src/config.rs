@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Values read from a config file. The file uses a small subset of TOML:
+/// `[section]` headers followed by `key = value` lines, where a value is a
+/// whole number, `true` or `false`, or a double quoted string. Keys may be
+/// quoted. Anything after a `#` is a comment. Keys are looked up as `section.key`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Config {
+    /// Raw value and line number of each key.
+    values: HashMap<String, (String, usize)>,
+}
+impl Config {
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let mut values = HashMap::new();
+        let mut section = String::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[') {
+                section = match name.strip_suffix(']') {
+                    Some(name) => name.trim().to_string(),
+                    None => {
+                        return Err(ConfigError::at_line(line_number, "unclosed section header"))
+                    }
+                };
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim().trim_matches('"'), value.trim()),
+                None => return Err(ConfigError::at_line(line_number, "expected `key = value`")),
+            };
+            if key.is_empty() || value.is_empty() {
+                return Err(ConfigError::at_line(line_number, "expected `key = value`"));
+            }
+            let full_key = match section.as_str() {
+                "" => key.to_string(),
+                section => format!("{}.{}", section, key),
+            };
+            values.insert(full_key, (value.to_string(), line_number));
+        }
+        Ok(Self { values })
+    }
+    /// Reads and parses the config file at `path`. A missing file is an
+    /// empty config, so every setting keeps its default.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(ConfigError::new(format!(
+                "could not read {}: {}",
+                path.display(),
+                error
+            ))),
+        }
+    }
+    /// Sets `key` to the raw value `raw` in the config file at `path`,
+    /// creating the file if needed and leaving the rest of it as it was.
+    pub fn write_value(path: &Path, key: &str, raw: &str) -> Result<(), ConfigError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => {
+                return Err(ConfigError::new(format!(
+                    "could not read {}: {}",
+                    path.display(),
+                    error
+                )))
+            }
+        };
+        let written = match path.parent() {
+            Some(dir) => fs::create_dir_all(dir),
+            None => Ok(()),
+        }
+        .and_then(|()| fs::write(path, set_value(&text, key, raw)));
+        written.map_err(|error| {
+            ConfigError::new(format!("could not write {}: {}", path.display(), error))
+        })
+    }
+    /// `tetris-cli/config.toml` in the user's config directory, following
+    /// `XDG_CONFIG_HOME` and falling back to `~/.config`.
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _other => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(config_dir.join("tetris-cli").join("config.toml"))
+    }
+    /// Returns whether `key` is set.
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+    /// Returns whether any key is set in the section or its subsections.
+    pub fn has_section(&self, section: &str) -> bool {
+        let prefix = format!("{}.", section);
+        self.values.keys().any(|key| key.starts_with(&prefix))
+    }
+    /// An error for the line that sets `key`.
+    pub fn error_at(&self, key: &str, message: &str) -> ConfigError {
+        match self.values.get(key) {
+            Some((_, line_number)) => ConfigError::at_line(*line_number, message),
+            None => ConfigError::new(message.to_string()),
+        }
+    }
+    /// Overwrites `target` with the value of `key`, if it is set.
+    pub fn read<T: ConfigValue>(&self, key: &str, target: &mut T) -> Result<(), ConfigError> {
+        if let Some((raw, line_number)) = self.values.get(key) {
+            *target = T::parse(raw).ok_or_else(|| {
+                ConfigError::at_line(*line_number, &format!("invalid value for `{}`", key))
+            })?;
+        }
+        Ok(())
+    }
+    /// Overwrites `target` with the choice named by the string value of
+    /// `key`, if it is set.
+    pub fn read_choice<T: Copy>(
+        &self,
+        key: &str,
+        choices: &[(&str, T)],
+        target: &mut T,
+    ) -> Result<(), ConfigError> {
+        if !self.contains(key) {
+            return Ok(());
+        }
+        let mut name = String::new();
+        self.read(key, &mut name)?;
+        match choices.iter().find(|(choice, _)| *choice == name) {
+            Some((_, value)) => {
+                *target = *value;
+                Ok(())
+            }
+            None => {
+                let names: Vec<&str> = choices.iter().map(|(choice, _)| *choice).collect();
+                Err(self.error_at(
+                    key,
+                    &format!("`{}` must be one of: {}", key, names.join(", ")),
+                ))
+            }
+        }
+    }
+}
+
+/// Config text with `key` set to the raw value `raw`. The line already
+/// setting the key is replaced, keeping any comment after it. Otherwise the
+/// line is added at the start of the key's section, and the section is
+/// added at the end if it is missing.
+fn set_value(text: &str, key: &str, raw: &str) -> String {
+    let (section, name) = key.rsplit_once('.').unwrap_or(("", key));
+    let new_line = format!("{} = {}", name, raw);
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    let mut current_section = String::new();
+    let mut section_start = match section {
+        "" => Some(0),
+        _other => None,
+    };
+    for (index, line) in lines.iter_mut().enumerate() {
+        let content = strip_comment(line).trim();
+        if let Some(header) = content.strip_prefix('[') {
+            current_section = header.trim_end_matches(']').trim().to_string();
+            if current_section == section && section_start.is_none() {
+                section_start = Some(index + 1);
+            }
+            continue;
+        }
+        let line_key = match content.split_once('=') {
+            Some((line_key, _)) => line_key.trim().trim_matches('"'),
+            None => continue,
+        };
+        if current_section == section && line_key == name {
+            let comment = &line[strip_comment(line).len()..];
+            *line = match comment.is_empty() {
+                true => new_line,
+                false => format!("{} {}", new_line, comment),
+            };
+            return lines.join("\n") + "\n";
+        }
+    }
+    match section_start {
+        Some(index) => lines.insert(index, new_line),
+        None => {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{}]", section));
+            lines.push(new_line);
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _other => {}
+        }
+    }
+    line
+}
+
+/// A type that can be read from a raw config value.
+pub trait ConfigValue: Sized {
+    fn parse(raw: &str) -> Option<Self>;
+}
+impl ConfigValue for u64 {
+    fn parse(raw: &str) -> Option<Self> {
+        raw.replace('_', "").parse().ok()
+    }
+}
+impl ConfigValue for bool {
+    fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "true" => Some(true),
+            "false" => Some(false),
+            _other => None,
+        }
+    }
+}
+impl ConfigValue for String {
+    fn parse(raw: &str) -> Option<Self> {
+        let inner = raw.strip_prefix('"')?.strip_suffix('"')?;
+        Some(inner.to_string())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ConfigError {
+    line: Option<usize>,
+    message: String,
+}
+impl ConfigError {
+    fn new(message: String) -> Self {
+        Self {
+            line: None,
+            message,
+        }
+    }
+    fn at_line(line: usize, message: &str) -> Self {
+        Self {
+            line: Some(line),
+            message: message.to_string(),
+        }
+    }
+}
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "Config error on line {}: {}", line, self.message),
+            None => write!(f, "Config error: {}", self.message),
+        }
+    }
+}
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "
+# Faster soft drop
+[game]
+soft_drop_factor = 40 # times gravity
+rotation_system = \"nintendo\"
+
+[top_out]
+lock_out = false
+";
+
+    #[test]
+    fn test_values_are_read_by_section_and_key() {
+        let config = Config::parse(EXAMPLE).unwrap();
+        let mut soft_drop_factor = 20;
+        config
+            .read("game.soft_drop_factor", &mut soft_drop_factor)
+            .unwrap();
+        assert_eq!(soft_drop_factor, 40);
+        let mut lock_out = true;
+        config.read("top_out.lock_out", &mut lock_out).unwrap();
+        assert!(!lock_out);
+        let mut rotation_system = String::new();
+        config
+            .read("game.rotation_system", &mut rotation_system)
+            .unwrap();
+        assert_eq!(rotation_system, "nintendo");
+    }
+
+    #[test]
+    fn test_missing_keys_keep_their_value() {
+        let config = Config::parse(EXAMPLE).unwrap();
+        let mut entry_delay = 7;
+        config.read("game.entry_delay", &mut entry_delay).unwrap();
+        assert_eq!(entry_delay, 7);
+    }
+
+    #[test]
+    fn test_errors_report_line_number() {
+        assert_eq!(
+            Config::parse("[game]\nsoft_drop_factor").unwrap_err().line,
+            Some(2)
+        );
+        let config = Config::parse("[game]\n\nsoft_drop_factor = fast").unwrap();
+        let mut soft_drop_factor = 20;
+        let error = config
+            .read("game.soft_drop_factor", &mut soft_drop_factor)
+            .unwrap_err();
+        assert_eq!(error.line, Some(3));
+    }
+
+    #[test]
+    fn test_read_choice() {
+        let config = Config::parse(EXAMPLE).unwrap();
+        let choices = [("srs", 0), ("nintendo", 1)];
+        let mut choice = 0;
+        config
+            .read_choice("game.rotation_system", &choices, &mut choice)
+            .unwrap();
+        assert_eq!(choice, 1);
+        let config = Config::parse("[game]\nrotation_system = \"ars\"").unwrap();
+        assert!(config
+            .read_choice("game.rotation_system", &choices, &mut choice)
+            .is_err());
+    }
+
+    #[test]
+    fn test_set_value_keeps_the_rest_of_the_file() {
+        let text = set_value(EXAMPLE, "game.soft_drop_factor", "60");
+        assert!(text.contains("soft_drop_factor = 60 # times gravity"));
+        assert!(text.contains("# Faster soft drop"));
+        let text = set_value(&text, "top_out.block_out", "false");
+        let text = set_value(&text, "keys.hold", "\"c\"");
+        let config = Config::parse(&text).unwrap();
+        let mut soft_drop_factor = 20;
+        config
+            .read("game.soft_drop_factor", &mut soft_drop_factor)
+            .unwrap();
+        assert_eq!(soft_drop_factor, 60);
+        let (mut block_out, mut lock_out) = (true, true);
+        config.read("top_out.block_out", &mut block_out).unwrap();
+        config.read("top_out.lock_out", &mut lock_out).unwrap();
+        assert!(!block_out && !lock_out);
+        let mut hold = String::new();
+        config.read("keys.hold", &mut hold).unwrap();
+        assert_eq!(hold, "c");
+        assert!(text.ends_with("\n[keys]\nhold = \"c\"\n"));
+    }
+}
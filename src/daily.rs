@@ -0,0 +1,68 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The daily challenge for one day. Its pieces are dealt from a seed taken
+/// from the date, so that everyone playing on the same day gets the same
+/// pieces. Days change at midnight UTC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DailyChallenge {
+    /// Days since 1970-01-01.
+    day: u64,
+}
+impl DailyChallenge {
+    const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+    /// Today's challenge.
+    pub fn today() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_secs());
+        Self::for_day(seconds / Self::SECONDS_PER_DAY)
+    }
+    /// The challenge for the day `day` days after 1970-01-01.
+    pub fn for_day(day: u64) -> Self {
+        Self { day }
+    }
+    /// Seed the challenge's pieces are dealt from.
+    pub fn seed(&self) -> u64 {
+        self.day
+    }
+    /// The challenge's date, e.g. `2025-10-15`.
+    pub fn date(&self) -> String {
+        // Converts days to a date in the proleptic Gregorian calendar, in
+        // 400 year eras starting from 0000-03-01.
+        let days = self.day as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_from_march = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+        let month = match month_from_march {
+            0..=9 => month_from_march + 3,
+            _other => month_from_march - 9,
+        };
+        let year = era * 400 + year_of_era + (month <= 2) as i64;
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+    /// Name the day's best score is recorded under in the leaderboard.
+    pub fn leaderboard_category(&self) -> String {
+        format!("daily-{}", self.date())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_days_are_dated() {
+        assert_eq!(DailyChallenge::for_day(0).date(), "1970-01-01");
+        assert_eq!(DailyChallenge::for_day(19_782).date(), "2024-02-29");
+        assert_eq!(DailyChallenge::for_day(20_376).date(), "2025-10-15");
+        assert_eq!(
+            DailyChallenge::for_day(20_376).leaderboard_category(),
+            "daily-2025-10-15"
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use std::time::{Duration, Instant};
+
+use crate::key_repeat::KeyRepeats;
+use crate::models::{Command, KeyInput, KeyState};
+
+/// Turns two presses of the soft drop key in quick succession into a hard
+/// drop, for terminals where Space is awkward to reach. The first press
+/// still soft drops. Terminals that cannot report key releases send a held
+/// key as quick taps, so there taps closer together than the terminal
+/// repeats keys are not taken as a double tap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoubleTap {
+    /// Longest gap between the presses, or `None` if double tapping is off.
+    window: Option<Duration>,
+    /// When the soft drop key was last pressed.
+    last_press: Option<Instant>,
+}
+impl DoubleTap {
+    /// Hard drops on presses at most `window` milliseconds apart. A window of
+    /// zero turns double tapping off.
+    pub fn new(window: u64) -> Self {
+        Self {
+            window: (window > 0).then(|| Duration::from_millis(window)),
+            last_press: None,
+        }
+    }
+    /// Returns the input to apply for a key input read at `now`, where
+    /// `is_repeat` marks the terminal's own repeats of a held key.
+    pub fn observe(&mut self, input: KeyInput, is_repeat: bool, now: Instant) -> KeyInput {
+        let Some(window) = self.window else {
+            return input;
+        };
+        if input.command != Command::MoveDown || input.state == KeyState::Released || is_repeat {
+            return input;
+        }
+        let gap = self.last_press.map(|at| now.duration_since(at));
+        self.last_press = Some(now);
+        let is_double_tap = match (gap, input.state) {
+            (Some(gap), KeyState::Tapped) => gap > KeyRepeats::REPEAT_GAP && gap <= window,
+            (Some(gap), _pressed) => gap <= window,
+            (None, _first) => false,
+        };
+        if !is_double_tap {
+            return input;
+        }
+        // A third press starts a new double tap rather than finishing this
+        // one again.
+        self.last_press = None;
+        KeyInput {
+            command: Command::HardDrop,
+            state: KeyState::Tapped,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(command: Command, state: KeyState) -> KeyInput {
+        KeyInput { command, state }
+    }
+
+    #[test]
+    fn test_pressing_down_twice_quickly_hard_drops() {
+        let mut double_tap = DoubleTap::new(200);
+        let start = Instant::now();
+        let after = |millis: u64| start + Duration::from_millis(millis);
+        let press = input(Command::MoveDown, KeyState::Pressed);
+        let release = input(Command::MoveDown, KeyState::Released);
+        let hard_drop = input(Command::HardDrop, KeyState::Tapped);
+        assert_eq!(double_tap.observe(press, false, start), press);
+        assert_eq!(double_tap.observe(press, true, after(30)), press);
+        assert_eq!(double_tap.observe(release, false, after(40)), release);
+        assert_eq!(double_tap.observe(press, false, after(80)), hard_drop);
+        assert_eq!(double_tap.observe(press, false, after(150)), press);
+        assert_eq!(double_tap.observe(press, false, after(400)), press);
+    }
+
+    #[test]
+    fn test_terminal_repeats_of_taps_are_not_double_taps() {
+        let mut double_tap = DoubleTap::new(200);
+        let start = Instant::now();
+        let after = |millis: u64| start + Duration::from_millis(millis);
+        let tap = input(Command::MoveDown, KeyState::Tapped);
+        assert_eq!(double_tap.observe(tap, false, start), tap);
+        assert_eq!(double_tap.observe(tap, false, after(300)), tap);
+        assert_eq!(double_tap.observe(tap, false, after(330)), tap);
+        assert_eq!(
+            double_tap.observe(tap, false, after(480)).command,
+            Command::HardDrop
+        );
+    }
+
+    #[test]
+    fn test_double_tapping_is_off_with_no_window() {
+        let mut double_tap = DoubleTap::new(0);
+        let now = Instant::now();
+        let press = input(Command::MoveDown, KeyState::Pressed);
+        assert_eq!(double_tap.observe(press, false, now), press);
+        assert_eq!(double_tap.observe(press, false, now), press);
+    }
+}
@@ -0,0 +1,444 @@
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, SystemClock};
+use crate::game::GameState;
+use crate::models::{Command, KeyInput, KeyState, TurnEvent};
+use crate::scheduler::Scheduler;
+use crate::settings::GameSettings;
+
+/// Runs a game in fixed frames of a sixtieth of a second. Each frame the
+/// inputs read during it are applied, then the game's timers run on by the
+/// length of the frame, and every timed event that has come due is taken
+/// from one `Scheduler` in deadline order. Each event keeps a deadline of
+/// its own, moved only by what changes it. The piece falls on the gravity
+/// deadline while it is in the air, and locks on the lock deadline once it
+/// rests on the stack, which moving it there can put back. The entry delay
+/// runs from a piece locking to the next one spawning. Both are timed from
+/// when the turn started, so a level up or a soft drop moves the deadline
+/// of the turn already under way. Holding a move key schedules its auto
+/// shift, which repeats the move from when the delay has passed until the
+/// key is let go, whatever the piece does in between. Input is read and
+/// drawn once per frame around it, so everything moves in lockstep on one
+/// thread.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Engine {
+    /// Game time run so far, which stops while the game is paused.
+    now: Duration,
+    /// When the piece last fell, locked or spawned.
+    turn_started: Duration,
+    /// The move key held down for auto shift to repeat.
+    held_move: Option<Command>,
+    scheduler: Scheduler<Timed, Duration>,
+}
+
+/// The timed events of a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timed {
+    /// The active piece falls.
+    Gravity,
+    /// The active piece, resting on the stack, locks.
+    Lock,
+    /// The entry delay ends and the next piece spawns.
+    EntryDelay,
+    /// The held move key repeats.
+    AutoShift,
+}
+
+/// What a frame did, for deciding what to draw.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Frame {
+    /// Whether the board or the active piece may have changed.
+    pub changed: bool,
+    /// Whether a piece locked, so a new one is due or has spawned.
+    pub locked: bool,
+}
+
+impl Engine {
+    /// Length of a frame at 60Hz.
+    pub const FRAME: Duration = Duration::from_nanos(16_666_667);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Starts the turn afresh, e.g. after the pause menu. Auto shift stops,
+    /// as the key may have been let go while the menu was up.
+    pub fn restart_turn(&mut self) {
+        self.turn_started = self.now;
+        self.held_move = None;
+        self.scheduler.cancel(&Timed::AutoShift);
+    }
+    /// Runs a frame `elapsed` long, applying `inputs` first. Inputs after
+    /// one that pauses or ends the game are dropped.
+    pub fn tick(
+        &mut self,
+        game_state: &mut GameState,
+        inputs: impl IntoIterator<Item = KeyInput>,
+        elapsed: Duration,
+    ) -> Frame {
+        let mut frame = Frame::default();
+        for input in inputs {
+            if Self::is_stopped(game_state) {
+                return frame;
+            }
+            frame.changed = true;
+            self.observe_move(&game_state.settings, input);
+            if let Some(TurnEvent::EndTurn) = game_state.apply_input(input) {
+                self.end_turn(game_state, &mut frame);
+            }
+        }
+        if Self::is_stopped(game_state) {
+            return frame;
+        }
+        let piece = game_state.piece.clone();
+        game_state.update_timers(elapsed);
+        frame.changed |= game_state.piece != piece || game_state.zone.is_active();
+        self.now += elapsed;
+        self.run_due_events(game_state, &mut frame);
+        frame
+    }
+    /// Starts auto shift for a move key pressed, to repeat once the delay
+    /// has passed, or for one the terminal has started repeating, to repeat
+    /// at the repeat rate straight away. The last move key held takes over
+    /// from any other, and letting it go stops auto shift.
+    fn observe_move(&mut self, settings: &GameSettings, input: KeyInput) {
+        if !matches!(input.command, Command::MoveLeft | Command::MoveRight) {
+            return;
+        }
+        let is_held = self.held_move == Some(input.command);
+        let delay = match input.state {
+            KeyState::Pressed if !is_held => Duration::from_millis(settings.slowed(settings.das)),
+            KeyState::Repeated if !is_held => Self::repeat_interval(settings),
+            KeyState::Released if is_held => {
+                self.held_move = None;
+                self.scheduler.cancel(&Timed::AutoShift);
+                return;
+            }
+            _other => return,
+        };
+        self.held_move = Some(input.command);
+        self.scheduler.schedule(Timed::AutoShift, self.now + delay);
+    }
+    /// Time between auto shift repeats. A rate of zero shifts the piece to
+    /// the wall at once, so it repeats once a frame, to keep the piece there
+    /// as it turns.
+    fn repeat_interval(settings: &GameSettings) -> Duration {
+        match settings.arr {
+            0 => Self::FRAME,
+            arr => Duration::from_millis(arr),
+        }
+    }
+    /// Runs every event that has come due. Time past the deadline of a fall
+    /// carries over to the next, so that falling keeps pace over a long
+    /// game, and a slow frame catches up on every row that was due. A piece
+    /// locking or spawning starts its turn afresh.
+    fn run_due_events(&mut self, game_state: &mut GameState, frame: &mut Frame) {
+        loop {
+            self.schedule_turn(game_state);
+            let (event, deadline) = match self.scheduler.pop_due(self.now) {
+                Some(due) => due,
+                None => return,
+            };
+            if Self::is_stopped(game_state) {
+                return;
+            }
+            match event {
+                Timed::AutoShift => self.auto_shift(game_state, frame, deadline),
+                Timed::EntryDelay | Timed::Lock => {
+                    self.end_turn(game_state, frame);
+                    return;
+                }
+                Timed::Gravity => {
+                    let interval = deadline - self.turn_started;
+                    self.end_turn(game_state, frame);
+                    if frame.locked || interval.is_zero() {
+                        return;
+                    }
+                    self.turn_started = deadline;
+                }
+            }
+        }
+    }
+    /// Schedules the event that ends the turn under way, for the piece's
+    /// current state and speed, and cancels the other two, which cannot
+    /// happen in it. Auto shift is left to carry on.
+    fn schedule_turn(&mut self, game_state: &GameState) {
+        let (event, interval) = match game_state.is_spawn_pending() {
+            true => (Timed::EntryDelay, game_state.settings.entry_delay),
+            false if game_state.is_grounded() => (Timed::Lock, game_state.turn_interval()),
+            false => (Timed::Gravity, game_state.turn_interval()),
+        };
+        for other in [Timed::Gravity, Timed::Lock, Timed::EntryDelay] {
+            if other != event {
+                self.scheduler.cancel(&other);
+            }
+        }
+        self.scheduler
+            .schedule(event, self.turn_started + Duration::from_millis(interval));
+    }
+    /// Repeats the held move key, due at `deadline`, and schedules the next
+    /// repeat.
+    fn auto_shift(&mut self, game_state: &mut GameState, frame: &mut Frame, deadline: Duration) {
+        let Some(command) = self.held_move else {
+            return;
+        };
+        let piece = game_state.piece.clone();
+        let repeat = KeyInput {
+            command,
+            state: KeyState::Repeated,
+        };
+        if let Some(TurnEvent::EndTurn) = game_state.apply_input(repeat) {
+            self.end_turn(game_state, frame);
+        }
+        frame.changed |= game_state.piece != piece;
+        let next_repeat = deadline + Self::repeat_interval(&game_state.settings);
+        self.scheduler.schedule(Timed::AutoShift, next_repeat);
+    }
+    /// Whether the game is paused or over, so that frames leave it alone.
+    fn is_stopped(game_state: &GameState) -> bool {
+        game_state.is_paused()
+            || game_state.is_topped_out()
+            || game_state.is_finished()
+            || game_state.exit().is_some()
+    }
+    /// Ends the turn: the next piece spawns if the entry delay has passed,
+    /// otherwise the piece falls or locks. Inputs during the entry delay
+    /// only end it once it has passed.
+    fn end_turn(&mut self, game_state: &mut GameState, frame: &mut Frame) {
+        if Self::is_stopped(game_state) {
+            return;
+        }
+        if game_state.is_spawn_pending() {
+            let in_turn = self.now - self.turn_started;
+            if in_turn < Duration::from_millis(game_state.settings.entry_delay) {
+                return;
+            }
+            game_state.spawn_next_piece();
+        } else if !game_state.apply_gravity() {
+            frame.locked = true;
+        }
+        frame.changed = true;
+        self.turn_started = self.now;
+    }
+}
+
+/// When each frame ends, kept to a fixed schedule from when play started so
+/// that frames do not drift, taking the time from a `Clock`.
+#[derive(Debug, Clone)]
+pub struct FrameSchedule<C: Clock = SystemClock> {
+    clock: C,
+    frame_started: Instant,
+    deadline: Instant,
+}
+impl FrameSchedule {
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock)
+    }
+}
+impl Default for FrameSchedule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<C: Clock> FrameSchedule<C> {
+    /// Starts the first frame now.
+    pub fn with_clock(clock: C) -> Self {
+        let now = clock.now();
+        Self {
+            clock,
+            frame_started: now,
+            deadline: now + Engine::FRAME,
+        }
+    }
+    pub fn frame_started(&self) -> Instant {
+        self.frame_started
+    }
+    /// When the frame under way is due to end.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+    /// Waits until `at`, e.g. for the rest of a frame that input stopped
+    /// being read for early.
+    pub fn wait_until(&self, at: Instant) {
+        self.clock.sleep_until(at);
+    }
+    /// Ends the frame under way and starts the next, returning how long the
+    /// frame lasted. Frames that a slow frame ran over are skipped.
+    pub fn next_frame(&mut self) -> Duration {
+        let now = self.clock.now();
+        let elapsed = now - self.frame_started;
+        self.frame_started = now;
+        while self.deadline <= now {
+            self.deadline += Engine::FRAME;
+        }
+        elapsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::piece::{PieceShape, TetrisPiece};
+
+    fn tap(command: Command) -> KeyInput {
+        KeyInput {
+            command,
+            state: KeyState::Tapped,
+        }
+    }
+
+    #[test]
+    fn test_piece_falls_once_the_turn_interval_has_passed() {
+        let mut game_state = GameState::new();
+        let mut engine = Engine::new();
+        let start_row = game_state.piece.position().row;
+        let frames = game_state.turn_interval() as u32 * 60 / 1000;
+        for _ in 1..frames {
+            assert!(!engine.tick(&mut game_state, [], Engine::FRAME).changed);
+        }
+        assert_eq!(game_state.piece.position().row, start_row);
+        assert!(engine.tick(&mut game_state, [], Engine::FRAME).changed);
+        assert_eq!(game_state.piece.position().row, start_row + 1);
+    }
+
+    #[test]
+    fn test_falling_keeps_pace_when_frames_run_long() {
+        let mut game_state = GameState::new();
+        let mut engine = Engine::new();
+        let start_row = game_state.piece.position().row;
+        for _ in 0..10 {
+            engine.tick(&mut game_state, [], Duration::from_millis(700));
+        }
+        assert_eq!(game_state.piece.position().row, start_row + 7);
+    }
+
+    #[test]
+    fn test_level_up_shortens_the_turn_under_way() {
+        let mut game_state = GameState::new();
+        let mut engine = Engine::new();
+        let start_row = game_state.piece.position().row;
+        for _ in 0..30 {
+            engine.tick(&mut game_state, [], Engine::FRAME);
+        }
+        assert_eq!(game_state.piece.position().row, start_row);
+        game_state.score.level = 5;
+        engine.tick(&mut game_state, [], Engine::FRAME);
+        assert_eq!(game_state.piece.position().row, start_row + 1);
+    }
+
+    #[test]
+    fn test_hard_drop_locks_within_the_frame() {
+        let mut game_state = GameState::new();
+        let mut engine = Engine::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::O);
+        let frame = engine.tick(&mut game_state, [tap(Command::HardDrop)], Engine::FRAME);
+        assert!(frame.locked);
+        assert!(!game_state.board.is_empty());
+    }
+
+    #[test]
+    fn test_grounded_piece_locks_once_the_lock_delay_has_passed() {
+        let mut game_state = GameState::new();
+        let mut engine = Engine::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::T).shifted(0, 18);
+        assert!(game_state.is_grounded());
+        let lock_delay = Duration::from_millis(game_state.settings.lock_delay.delay);
+        let frame = engine.tick(&mut game_state, [], lock_delay - Duration::from_millis(1));
+        assert!(!frame.locked);
+        let frame = engine.tick(&mut game_state, [], Duration::from_millis(1));
+        assert!(frame.locked);
+        assert!(!game_state.board.is_empty());
+    }
+
+    #[test]
+    fn test_next_piece_spawns_after_the_entry_delay() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            entry_delay: 100,
+            ..GameSettings::default()
+        });
+        let mut engine = Engine::new();
+        engine.tick(&mut game_state, [tap(Command::HardDrop)], Engine::FRAME);
+        assert!(game_state.is_spawn_pending());
+        for _ in 0..4 {
+            engine.tick(&mut game_state, [], Engine::FRAME);
+        }
+        assert!(game_state.is_spawn_pending());
+        engine.tick(&mut game_state, [], Engine::FRAME);
+        assert!(!game_state.is_spawn_pending());
+    }
+
+    #[test]
+    fn test_held_move_auto_shifts_until_it_is_let_go() {
+        let mut game_state = GameState::new();
+        let mut engine = Engine::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::T);
+        let start_col = game_state.piece.position().col;
+        let input = |state| KeyInput {
+            command: Command::MoveLeft,
+            state,
+        };
+        let millis = Duration::from_millis;
+        // The delay is 167ms and the repeat rate 33ms.
+        engine.tick(&mut game_state, [input(KeyState::Pressed)], millis(100));
+        assert_eq!(game_state.piece.position().col, start_col - 1);
+        engine.tick(&mut game_state, [], millis(100));
+        assert_eq!(game_state.piece.position().col, start_col - 3);
+        engine.tick(&mut game_state, [input(KeyState::Released)], millis(100));
+        assert_eq!(game_state.piece.position().col, start_col - 3);
+    }
+
+    #[test]
+    fn test_release_is_applied_while_auto_shift_repeats_every_frame() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            das: 0,
+            arr: 0,
+            ..GameSettings::default()
+        });
+        let mut engine = Engine::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::T);
+        let input = |command, state| KeyInput { command, state };
+        let press = input(Command::MoveLeft, KeyState::Pressed);
+        engine.tick(&mut game_state, [press], Engine::FRAME);
+        let wall = TetrisPiece::new(&PieceShape::T).shifted(-3, 0);
+        assert_eq!(game_state.piece.position().col, wall.position().col);
+        let inputs = [
+            input(Command::MoveLeft, KeyState::Released),
+            input(Command::MoveRight, KeyState::Tapped),
+        ];
+        engine.tick(&mut game_state, inputs, Engine::FRAME);
+        for _ in 0..5 {
+            engine.tick(&mut game_state, [], Engine::FRAME);
+        }
+        assert_eq!(game_state.piece.position().col, wall.position().col + 1);
+    }
+
+    #[test]
+    fn test_frames_leave_a_paused_game_alone() {
+        let mut game_state = GameState::new();
+        let mut engine = Engine::new();
+        let piece = game_state.piece.clone();
+        let inputs = [tap(Command::Pause), tap(Command::HardDrop)];
+        engine.tick(&mut game_state, inputs, Duration::from_secs(5));
+        assert!(game_state.is_paused());
+        assert_eq!(game_state.piece, piece);
+    }
+
+    #[test]
+    fn test_frames_keep_to_their_schedule() {
+        let clock = MockClock::new();
+        let start = clock.now();
+        let mut schedule = FrameSchedule::with_clock(clock.clone());
+        assert_eq!(schedule.deadline(), start + Engine::FRAME);
+        clock.advance(Engine::FRAME + Duration::from_millis(2));
+        assert_eq!(
+            schedule.next_frame(),
+            Engine::FRAME + Duration::from_millis(2)
+        );
+        assert_eq!(schedule.deadline(), start + Engine::FRAME * 2);
+        clock.advance(Engine::FRAME * 2);
+        schedule.next_frame();
+        assert_eq!(schedule.deadline(), start + Engine::FRAME * 4);
+        clock.advance(Engine::FRAME);
+        schedule.wait_until(schedule.deadline());
+    }
+}
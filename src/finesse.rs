@@ -0,0 +1,198 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::board::{PiecePositionValidity, TetrisBoard};
+use crate::models::{Command, Coord};
+use crate::piece::{Orientation, Rotation, TetrisPiece};
+
+/// Counts the move and rotate key presses used to place each piece and
+/// compares them with the fewest that could have placed it, flagging the
+/// extra presses as finesse errors.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FinesseTracker {
+    /// Move and rotate presses for the active piece so far.
+    inputs: usize,
+    /// Whether the active piece has been soft dropped, which allows tucks
+    /// and spins that finesse does not judge.
+    soft_dropped: bool,
+    /// Placements judged so far.
+    pub placements: u64,
+    /// Placements that used more presses than they needed.
+    pub errors: u64,
+}
+impl FinesseTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Counts a key press, if it is one that finesse judges.
+    pub fn record_input(&mut self, command: Command) {
+        match command {
+            Command::MoveLeft
+            | Command::MoveRight
+            | Command::RotateClockwise
+            | Command::RotateAnticlockwise
+            | Command::Rotate180 => self.inputs += 1,
+            Command::MoveDown | Command::SonicDrop => self.soft_dropped = true,
+            _other => {}
+        }
+    }
+    /// Starts counting afresh for a new active piece.
+    pub fn reset(&mut self) {
+        self.inputs = 0;
+        self.soft_dropped = false;
+    }
+    /// Returns whether placing `placed` used more presses than it needed
+    /// from `spawned`, the same piece as it spawned. Placements that were
+    /// soft dropped, or that could not have been hard dropped into place,
+    /// are not judged.
+    pub fn is_fault(
+        &self,
+        spawned: &TetrisPiece,
+        placed: &TetrisPiece,
+        board: &TetrisBoard,
+    ) -> bool {
+        if self.soft_dropped || !can_drop_into_place(spawned, placed, board) {
+            return false;
+        }
+        match optimal_inputs(spawned, placed) {
+            Some(optimal) => self.inputs > optimal,
+            None => false,
+        }
+    }
+    /// Judges a placement, counting it towards the totals. Returns whether
+    /// it was a finesse error.
+    pub fn judge(
+        &mut self,
+        spawned: &TetrisPiece,
+        placed: &TetrisPiece,
+        board: &TetrisBoard,
+    ) -> bool {
+        let fault = self.is_fault(spawned, placed, board);
+        self.placements += 1;
+        if fault {
+            self.errors += 1;
+        }
+        self.reset();
+        return fault;
+    }
+}
+
+/// Whether the piece could have fallen straight down into its place from
+/// the height it spawned at.
+fn can_drop_into_place(spawned: &TetrisPiece, placed: &TetrisPiece, board: &TetrisBoard) -> bool {
+    let rows_fallen = placed.position().row - spawned.position().row;
+    (1..=rows_fallen).all(|rows| {
+        board.check_is_valid_position(&placed.shifted(0, -rows).coordinates())
+            == PiecePositionValidity::Valid
+    })
+}
+
+/// Cells of a piece moved to the top of the board, so that pieces in the
+/// same columns with the same footprint compare equal at any height.
+fn footprint(piece: &TetrisPiece) -> Vec<Coord> {
+    let coordinates = piece.coordinates();
+    let top = coordinates.iter().map(|coord| coord.row).min().unwrap_or(0);
+    let mut footprint: Vec<Coord> = coordinates
+        .into_iter()
+        .map(|coord| Coord {
+            col: coord.col,
+            row: coord.row - top,
+        })
+        .collect();
+    footprint.sort_by_key(|coord| (coord.row, coord.col));
+    return footprint;
+}
+
+/// Fewest move and rotate presses that take `spawned` to the columns and
+/// footprint of `placed` on an empty board, or `None` if it cannot get
+/// there. Holding a move key until auto shift takes the piece to the wall
+/// is one press.
+pub fn optimal_inputs(spawned: &TetrisPiece, placed: &TetrisPiece) -> Option<usize> {
+    let board = TetrisBoard::new();
+    let target = footprint(placed);
+    let mut seen: HashSet<(i16, i16, Orientation)> = HashSet::new();
+    let mut queue = VecDeque::from([(spawned.clone(), 0)]);
+    while let Some((piece, presses)) = queue.pop_front() {
+        let position = piece.position();
+        if !seen.insert((position.col, position.row, piece.orientation())) {
+            continue;
+        }
+        if footprint(&piece) == target {
+            return Some(presses);
+        }
+        for command in [Command::MoveLeft, Command::MoveRight] {
+            let mut moved = piece.clone();
+            moved.move_peice(&board, command);
+            queue.push_back((moved.clone(), presses + 1));
+            let mut at_wall = moved;
+            loop {
+                let before = at_wall.clone();
+                at_wall.move_peice(&board, command);
+                if at_wall == before {
+                    break;
+                }
+            }
+            queue.push_back((at_wall, presses + 1));
+        }
+        for rotation in [Rotation::Clockwise, Rotation::Anticlockwise, Rotation::Half] {
+            let mut rotated = piece.clone();
+            rotated.rotate(&board, rotation);
+            queue.push_back((rotated, presses + 1));
+        }
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::PieceShape;
+
+    #[test]
+    fn test_optimal_inputs() {
+        let spawned = TetrisPiece::new(&PieceShape::T);
+        assert_eq!(optimal_inputs(&spawned, &spawned.shifted(0, 18)), Some(0));
+        assert_eq!(optimal_inputs(&spawned, &spawned.shifted(-2, 18)), Some(2));
+        // Auto shift takes the piece to the wall in one press.
+        assert_eq!(optimal_inputs(&spawned, &spawned.shifted(-3, 18)), Some(1));
+        assert_eq!(optimal_inputs(&spawned, &spawned.shifted(4, 18)), Some(1));
+        let flipped = spawned.rotated(Rotation::Half).shifted(2, 17);
+        assert_eq!(optimal_inputs(&spawned, &flipped), Some(3));
+        // The O looks the same whichever way it is turned.
+        let o_piece = TetrisPiece::new(&PieceShape::O);
+        let turned = o_piece.rotated(Rotation::Clockwise).shifted(1, 18);
+        assert_eq!(optimal_inputs(&o_piece, &turned), Some(1));
+    }
+
+    #[test]
+    fn test_extra_presses_are_finesse_errors() {
+        let board = TetrisBoard::new();
+        let spawned = TetrisPiece::new(&PieceShape::T);
+        let placed = spawned.shifted(-1, 18);
+        let mut finesse = FinesseTracker::new();
+        finesse.record_input(Command::MoveLeft);
+        assert!(!finesse.judge(&spawned, &placed, &board));
+        for command in [Command::MoveLeft, Command::MoveLeft, Command::MoveRight] {
+            finesse.record_input(command);
+        }
+        assert!(finesse.judge(&spawned, &placed, &board));
+        assert_eq!((finesse.placements, finesse.errors), (2, 1));
+    }
+
+    #[test]
+    fn test_soft_dropped_and_tucked_placements_are_not_judged() {
+        let mut board = TetrisBoard::new();
+        let spawned = TetrisPiece::new(&PieceShape::T);
+        let placed = spawned.shifted(-3, 18);
+        let mut finesse = FinesseTracker::new();
+        for command in [Command::MoveDown, Command::MoveLeft, Command::MoveRight] {
+            finesse.record_input(command);
+        }
+        assert!(!finesse.is_fault(&spawned, &spawned.shifted(0, 18), &board));
+        finesse.reset();
+        board.board[10][0] = true;
+        for _ in 0..5 {
+            finesse.record_input(Command::MoveLeft);
+        }
+        assert!(!finesse.is_fault(&spawned, &placed, &board));
+    }
+}
@@ -0,0 +1,1822 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::board::{GarbageRules, PiecePositionValidity, TetrisBoard};
+use crate::daily::DailyChallenge;
+use crate::finesse::FinesseTracker;
+use crate::garbage_timer::GarbageTimer;
+use crate::gravity::Gravity;
+use crate::items::ItemQueue;
+use crate::mode::GameMode;
+use crate::models::{Command, KeyInput, KeyState, TurnEvent};
+use crate::piece::{PieceSet, PieceShape, Rotation, TetrisPiece};
+use crate::puzzle::{FixedSequence, Goal};
+use crate::randomizer::{PieceGenerator, Randomizer};
+use crate::score::{LineClear, Score};
+use crate::settings::{GameSettings, LockReset};
+use crate::spin::{detect_spin, SpinKind};
+use crate::zone::Zone;
+
+/// The reason a game ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TopOut {
+    BlockOut,
+    LockOut,
+    GarbageOut,
+}
+
+/// A piece hard dropped onto the stack, for drawing the trail it left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HardDrop {
+    /// The piece where it landed.
+    pub piece: TetrisPiece,
+    /// Rows it fell.
+    pub distance: i16,
+    /// How many pieces had locked before it, to tell one drop from the next.
+    pub piece_number: usize,
+}
+
+/// Why the player left a game before it ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    Quit,
+    /// Start a new game with the same settings.
+    Restart,
+}
+
+/// Everything that describes a game in progress: the board, the piece the
+/// player is controlling and the score reached so far.
+pub struct GameState {
+    pub settings: GameSettings,
+    /// Deals the pieces, following the settings' randomizer.
+    generator: Box<dyn PieceGenerator>,
+    /// Pieces dealt ahead of time for the preview, next first.
+    upcoming: VecDeque<PieceShape>,
+    pub board: TetrisBoard,
+    pub piece: TetrisPiece,
+    pub score: Score,
+    pub held_piece: Option<PieceShape>,
+    /// The most recent lock that scored, for announcing in the HUD.
+    pub last_clear: Option<LineClear>,
+    /// The most recent hard drop.
+    pub last_hard_drop: Option<HardDrop>,
+    /// Items earned in party mode.
+    pub items: ItemQueue,
+    /// Number of pieces locked so far.
+    pieces_locked: usize,
+    pub finesse: FinesseTracker,
+    /// The zone meter, which only fills when the zone setting is on.
+    pub zone: Zone,
+    /// Raises garbage on a timer, in survival and dig race modes.
+    pub garbage_timer: Option<GarbageTimer>,
+    /// Time left to play, in modes played against the clock.
+    time_left: Option<Duration>,
+    /// Pieces chosen in the practice editor to come next, before the
+    /// randomizer's.
+    chosen_pieces: Vec<PieceShape>,
+    /// Whether play is paused for the pause menu.
+    paused: bool,
+    /// Set once the player quits or restarts.
+    exit: Option<Exit>,
+    can_hold: bool,
+    /// Kick used by the active piece's last rotation, if its last successful
+    /// move was a rotation. Used to detect spins when the piece locks.
+    last_rotation_kick: Option<usize>,
+    /// Whether the current turn is a soft drop turn, which is shorter than
+    /// a normal turn and scores the rows fallen.
+    soft_dropping: bool,
+    /// Whether soft drop was pressed again during the current turn, which
+    /// keeps the soft drop going into the next turn.
+    soft_drop_held: bool,
+    top_out: Option<TopOut>,
+    /// Times the active piece's lock delay has been restarted since it last
+    /// reached a new lowest row.
+    lock_resets: u64,
+    /// Lowest row the active piece has reached.
+    lowest_row: i16,
+    /// Whether the active piece has moved on the stack since gravity last
+    /// pulled on it, restarting its lock delay.
+    lock_delay_restarted: bool,
+    /// Commands whose keys are currently held down, oldest first.
+    keys_down: Vec<Command>,
+    /// Whether the last piece has locked and the next is waiting out the
+    /// entry delay.
+    spawn_pending: bool,
+    /// Moves, rotations and holds pressed during the entry delay, in order,
+    /// replayed when the next piece spawns.
+    buffered_inputs: Vec<Command>,
+}
+impl Default for GameState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl GameState {
+    pub fn new() -> Self {
+        Self::with_settings(GameSettings::default())
+    }
+    /// Number of pieces to come shown in the preview.
+    pub const PREVIEW_PIECES: usize = 5;
+    /// Lines to clear to finish marathon mode.
+    pub const MARATHON_LINES: u64 = 150;
+    /// Lines to clear to finish sprint mode.
+    pub const SPRINT_LINES: u64 = 40;
+    /// Time before the first row of garbage rises in survival mode.
+    pub const SURVIVAL_START_INTERVAL: Duration = Duration::from_secs(10);
+    /// How much sooner each row of survival garbage rises than the last.
+    const SURVIVAL_SPEED_UP: Duration = Duration::from_millis(250);
+    /// Fastest survival garbage rises.
+    const SURVIVAL_MIN_INTERVAL: Duration = Duration::from_secs(2);
+    /// Length of a dig race.
+    pub const DIG_RACE_TIME: Duration = Duration::from_secs(120);
+    /// Time between rows of garbage in a dig race.
+    pub const DIG_RACE_INTERVAL: Duration = Duration::from_secs(4);
+    /// Garbage rows a dig race starts with.
+    const DIG_RACE_START_ROWS: u64 = 4;
+    /// Most garbage rows cheese mode starts with, leaving room to spawn.
+    const MAX_CHEESE_ROWS: u64 = TetrisBoard::NUM_ROWS as u64 - 4;
+
+    pub fn with_settings(mut settings: GameSettings) -> Self {
+        if settings.mode == GameMode::Daily {
+            settings.randomizer = Randomizer::SevenBag;
+            settings.piece_set = PieceSet::Tetrominoes;
+            settings.seed = Some(settings.seed.unwrap_or(DailyChallenge::today().seed()));
+        }
+        let mut generator: Box<dyn PieceGenerator> = match (&settings.puzzle, settings.seed) {
+            (Some(puzzle), _) if settings.mode == GameMode::Puzzle => {
+                Box::new(FixedSequence::new(puzzle.pieces.clone()))
+            }
+            (_, Some(seed)) => settings
+                .randomizer
+                .seeded_generator(settings.piece_set, seed),
+            _other => settings.randomizer.generator(settings.piece_set),
+        };
+        let piece = Self::new_piece(&settings, &generator.next_piece());
+        let upcoming = (0..Self::PREVIEW_PIECES)
+            .map(|_| generator.next_piece())
+            .collect();
+        let lowest_row = piece.position().row;
+        let mut score = Score::with_rules(settings.lines_per_level, settings.score_table.clone());
+        score.start_at_level(settings.start_level);
+        let mut board = TetrisBoard::with_clear_style(settings.clear_style);
+        board.garbage_rules = GarbageRules {
+            entry_delay: 0,
+            ..settings.garbage
+        };
+        let start_garbage = match settings.mode {
+            GameMode::Cheese => settings.cheese_rows.min(Self::MAX_CHEESE_ROWS),
+            GameMode::DigRace => Self::DIG_RACE_START_ROWS,
+            _other => 0,
+        };
+        board.receive_garbage(start_garbage as usize);
+        board.raise_garbage();
+        board.garbage_rules = settings.garbage;
+        if let Some(puzzle) = &settings.puzzle {
+            if settings.mode == GameMode::Puzzle {
+                puzzle.fill_board(&mut board);
+            }
+        }
+        let garbage_timer = match settings.mode {
+            GameMode::Survival => Some(GarbageTimer::accelerating(
+                Self::SURVIVAL_START_INTERVAL,
+                Self::SURVIVAL_SPEED_UP,
+                Self::SURVIVAL_MIN_INTERVAL,
+            )),
+            GameMode::DigRace => Some(GarbageTimer::new(Self::DIG_RACE_INTERVAL)),
+            _other => None,
+        };
+        let time_left = match settings.mode {
+            GameMode::DigRace => Some(Self::DIG_RACE_TIME),
+            _other => None,
+        };
+        let mut game_state = Self {
+            settings,
+            generator,
+            upcoming,
+            board,
+            piece,
+            score,
+            held_piece: None,
+            last_clear: None,
+            last_hard_drop: None,
+            items: ItemQueue::new(),
+            pieces_locked: 0,
+            finesse: FinesseTracker::new(),
+            zone: Zone::new(),
+            garbage_timer,
+            time_left,
+            chosen_pieces: Vec::new(),
+            paused: false,
+            exit: None,
+            can_hold: true,
+            last_rotation_kick: None,
+            soft_dropping: false,
+            soft_drop_held: false,
+            top_out: None,
+            lock_resets: 0,
+            lowest_row,
+            lock_delay_restarted: false,
+            keys_down: Vec::new(),
+            spawn_pending: false,
+            buffered_inputs: Vec::new(),
+        };
+        game_state.fall_at_20g();
+        game_state
+    }
+    /// A piece at its spawn position, following the settings' rotation
+    /// system and mode.
+    fn new_piece(settings: &GameSettings, piece_shape: &PieceShape) -> TetrisPiece {
+        let piece = TetrisPiece::with_rotation_system(piece_shape, settings.rotation_system);
+        match settings.mode.piece_scale() {
+            1 => piece,
+            scale => piece.scaled(scale),
+        }
+    }
+    /// How fast the active piece falls at the current level, or at level 1
+    /// while a slow gravity item lasts, lengthened by any slowdown. Master
+    /// mode is always 20G.
+    pub fn gravity(&self) -> Gravity {
+        let gravity = if self.settings.mode == GameMode::Master {
+            Gravity::twenty_g()
+        } else if self.items.is_slow_gravity() {
+            Gravity::for_level(1)
+        } else {
+            Gravity::for_level(self.score.level)
+        };
+        Gravity {
+            interval: self.settings.slowed(gravity.interval),
+            ..gravity
+        }
+    }
+    /// Time in milliseconds until the active piece next falls, which is
+    /// shortened by the soft drop factor while soft dropping. A piece
+    /// resting on the stack waits out the lock delay instead, unless
+    /// locking is classic.
+    pub fn turn_interval(&self) -> u64 {
+        if self.is_grounded() && self.settings.lock_delay.reset != LockReset::Classic {
+            return self.settings.lock_delay.delay;
+        }
+        let interval = self.gravity().interval;
+        if self.soft_dropping {
+            return (interval / self.settings.soft_drop_factor.max(1)).max(1);
+        }
+        return interval;
+    }
+    /// Whether the active piece is resting on the stack or the floor.
+    pub fn is_grounded(&self) -> bool {
+        self.piece.drop_distance(&self.board) == 0
+    }
+    /// The top out that ended the game, if it has ended.
+    pub fn top_out(&self) -> Option<TopOut> {
+        self.top_out
+    }
+    pub fn is_topped_out(&self) -> bool {
+        self.top_out.is_some()
+    }
+    /// How the player left the game, if they quit or restarted it.
+    pub fn exit(&self) -> Option<Exit> {
+        self.exit
+    }
+    /// Whether the goal of the mode has been reached, which in cheese mode
+    /// is digging out every row of garbage, or in a dig race is the time
+    /// running out. Endless modes never finish.
+    pub fn is_finished(&self) -> bool {
+        match self.settings.mode {
+            GameMode::Cheese => self.board.garbage_rows() == 0,
+            GameMode::DigRace => self.time_left == Some(Duration::ZERO),
+            GameMode::Marathon => self.score.lines >= Self::MARATHON_LINES,
+            GameMode::Sprint => self.score.lines >= Self::SPRINT_LINES,
+            GameMode::Puzzle => self.is_puzzle_solved() || self.is_out_of_puzzle_pieces(),
+            _other => false,
+        }
+    }
+    /// Number of pieces locked so far.
+    pub fn pieces_locked(&self) -> usize {
+        self.pieces_locked
+    }
+    /// Whether the goal of the puzzle being played has been reached.
+    pub fn is_puzzle_solved(&self) -> bool {
+        let puzzle = match &self.settings.puzzle {
+            Some(puzzle) if self.settings.mode == GameMode::Puzzle => puzzle,
+            _other => return false,
+        };
+        match puzzle.goal {
+            Goal::PerfectClear => self
+                .last_clear
+                .is_some_and(|line_clear| line_clear.perfect_clear),
+            Goal::Lines(lines) => self.score.lines >= lines,
+        }
+    }
+    /// Whether every piece of the puzzle has been locked or is stuck in the
+    /// hold, so that the active piece is not one of the puzzle's.
+    fn is_out_of_puzzle_pieces(&self) -> bool {
+        let puzzle = match &self.settings.puzzle {
+            Some(puzzle) => puzzle,
+            None => return false,
+        };
+        self.pieces_locked + self.held_piece.is_some() as usize >= puzzle.pieces.len()
+    }
+    /// Time left to play, in modes played against the clock.
+    pub fn time_left(&self) -> Option<Duration> {
+        self.time_left
+    }
+    /// Name that results are recorded under in the leaderboard: finishing
+    /// times for modes raced against the clock, and garbage cleared in a
+    /// dig race.
+    pub fn leaderboard_category(&self) -> Option<String> {
+        match self.settings.mode {
+            GameMode::Cheese => Some(format!(
+                "cheese-{}",
+                self.settings.cheese_rows.min(Self::MAX_CHEESE_ROWS)
+            )),
+            GameMode::Sprint => Some(format!("sprint-{}", Self::SPRINT_LINES)),
+            GameMode::DigRace => Some(format!("dig-race-{}m", Self::DIG_RACE_TIME.as_secs() / 60)),
+            _other => None,
+        }
+    }
+    /// Ends the game with a top out, unless in zen mode, where the bottom
+    /// half of the board is cleared instead so that play can go on. Returns
+    /// whether the game ended.
+    fn top_out_with(&mut self, top_out: TopOut) -> bool {
+        if self.settings.mode == GameMode::Zen {
+            for _ in 0..TetrisBoard::NUM_ROWS / 2 {
+                self.board.remove_row(TetrisBoard::NUM_ROWS - 1);
+            }
+            return false;
+        }
+        self.top_out = Some(top_out);
+        return true;
+    }
+    /// Makes a new piece of the given shape the active piece. If it overlaps
+    /// the stack the game ends in a block out, unless that rule is off, in
+    /// which case the piece is raised into the vanish zone to find room.
+    fn spawn(&mut self, piece_shape: &PieceShape) {
+        self.piece = Self::new_piece(&self.settings, piece_shape);
+        self.finesse.reset();
+        self.apply_initial_actions();
+        self.reset_lock_delay();
+        let piece = self.piece.clone();
+        let fits = |piece: &TetrisPiece| {
+            self.board.check_is_valid_position(&piece.coordinates()) == PiecePositionValidity::Valid
+        };
+        if fits(&piece) {
+            self.fall_at_20g();
+            return;
+        }
+        if !self.settings.top_out.block_out {
+            for rows in 1..=TetrisBoard::VANISH_ROWS as i16 {
+                let raised = piece.shifted(0, -rows);
+                if fits(&raised) {
+                    self.piece = raised;
+                    self.fall_at_20g();
+                    return;
+                }
+            }
+        }
+        self.top_out_with(TopOut::BlockOut);
+    }
+    /// Drops the active piece straight onto the stack under 20G, so that it
+    /// spawns, moves and rotates on the stack rather than above it.
+    fn fall_at_20g(&mut self) {
+        if !self.gravity().is_20g() || self.zone.is_active() {
+            return;
+        }
+        let distance = self.piece.drop_distance(&self.board);
+        if distance > 0 {
+            self.piece = self.piece.shifted(0, distance);
+            self.last_rotation_kick = None;
+        }
+    }
+    /// The shape of the next piece to play: the first piece chosen in the
+    /// practice editor, or else the randomizer's next piece.
+    fn next_piece_shape(&mut self) -> PieceShape {
+        if self.chosen_pieces.is_empty() {
+            self.upcoming.push_back(self.generator.next_piece());
+            return self
+                .upcoming
+                .pop_front()
+                .expect("Preview always holds a piece.");
+        }
+        self.chosen_pieces.remove(0)
+    }
+    /// The pieces to come, next first, as many as the preview shows.
+    pub fn next_pieces(&self) -> Vec<PieceShape> {
+        self.chosen_pieces
+            .iter()
+            .chain(self.upcoming.iter())
+            .take(Self::PREVIEW_PIECES)
+            .copied()
+            .collect()
+    }
+    /// Pieces chosen in the practice editor to come next, first to come
+    /// first.
+    pub fn chosen_pieces(&self) -> &[PieceShape] {
+        &self.chosen_pieces
+    }
+    /// Adds a piece to the end of the chosen pieces.
+    pub fn choose_piece(&mut self, piece_shape: PieceShape) {
+        self.chosen_pieces.push(piece_shape);
+    }
+    /// Takes back the last piece chosen.
+    pub fn unchoose_piece(&mut self) {
+        self.chosen_pieces.pop();
+    }
+    /// Whether play is paused for the pause menu.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+    /// Carries on after the pause menu. In practice mode the board may have
+    /// been edited, so the active piece starts again from its spawn
+    /// position, swapped for the first chosen piece if any were chosen.
+    pub fn resume(&mut self) {
+        if !self.paused {
+            return;
+        }
+        self.paused = false;
+        self.keys_down.clear();
+        if self.spawn_pending || self.settings.mode != GameMode::Practice {
+            return;
+        }
+        let piece_shape = match self.chosen_pieces.is_empty() {
+            true => self.piece.piece_shape(),
+            false => self.chosen_pieces.remove(0),
+        };
+        self.spawn(&piece_shape);
+    }
+    /// Returns whether the last piece has locked and the next piece is
+    /// waiting for the entry delay to pass.
+    pub fn is_spawn_pending(&self) -> bool {
+        self.spawn_pending
+    }
+    /// Spawns the next piece once the entry delay has passed.
+    pub fn spawn_next_piece(&mut self) {
+        if self.spawn_pending {
+            self.spawn_pending = false;
+            let piece_shape = self.next_piece_shape();
+            self.spawn(&piece_shape);
+        }
+    }
+    /// Applies the inputs held down since before the entry delay to a piece
+    /// that has just spawned: the Initial Hold and Initial Rotation Systems,
+    /// then a shift towards a held direction so that movement charged during
+    /// the delay is not lost. The rotation is not kicked, so it is skipped if
+    /// the rotated piece would not fit. Inputs pressed during the delay are
+    /// then replayed in order, so that none are lost.
+    fn apply_initial_actions(&mut self) {
+        let buffered = std::mem::take(&mut self.buffered_inputs);
+        let inputs: Vec<Command> = self
+            .keys_down
+            .iter()
+            .filter(|command| !buffered.contains(command))
+            .copied()
+            .collect();
+        self.apply_held_actions(&inputs);
+        for command in buffered {
+            self.apply_command(command);
+        }
+    }
+    fn apply_held_actions(&mut self, inputs: &[Command]) {
+        if self.settings.initial_hold && inputs.contains(&Command::Hold) {
+            self.hold();
+        }
+        let shift = inputs
+            .iter()
+            .rev()
+            .find(|command| matches!(command, Command::MoveLeft | Command::MoveRight));
+        if let Some(shift) = shift {
+            self.piece.move_peice(&self.board, *shift);
+        }
+        if !self.settings.initial_rotation {
+            return;
+        }
+        let rotation = inputs.iter().rev().find_map(|command| match command {
+            Command::RotateClockwise => Some(Rotation::Clockwise),
+            Command::RotateAnticlockwise => Some(Rotation::Anticlockwise),
+            Command::Rotate180 => Some(Rotation::Half),
+            _other => None,
+        });
+        if let Some(rotation) = rotation {
+            let rotated_piece = self.piece.rotated(rotation);
+            if let PiecePositionValidity::Valid = self
+                .board
+                .check_is_valid_position(&rotated_piece.coordinates())
+            {
+                self.piece = rotated_piece;
+            }
+        }
+    }
+    /// Applies a key press as a command and tracks which keys are held, for
+    /// the Initial Hold and Initial Rotation Systems. Moves, rotations and
+    /// holds pressed during the entry delay are buffered until the next
+    /// piece spawns. Returns `TurnEvent::EndTurn` if the command should end
+    /// the current turn early.
+    pub fn apply_input(&mut self, input: KeyInput) -> Option<TurnEvent> {
+        // Pausing, quitting and restarting do not wait for the next piece.
+        let controls_game = matches!(
+            input.command,
+            Command::Pause | Command::Quit | Command::Restart
+        );
+        match input.state {
+            KeyState::Pressed => {
+                if !self.keys_down.contains(&input.command) {
+                    self.keys_down.push(input.command);
+                }
+            }
+            KeyState::Released => {
+                self.keys_down.retain(|command| *command != input.command);
+                return None;
+            }
+            KeyState::Tapped => {}
+            // Repeats are not buffered, as the key still being held keeps
+            // them coming once the piece spawns.
+            KeyState::Repeated if self.spawn_pending => return None,
+            KeyState::Repeated => return self.auto_shift(input.command),
+        }
+        if self.spawn_pending && !controls_game {
+            let buffered = matches!(
+                input.command,
+                Command::MoveLeft
+                    | Command::MoveRight
+                    | Command::RotateClockwise
+                    | Command::RotateAnticlockwise
+                    | Command::Rotate180
+                    | Command::Hold
+            );
+            if buffered {
+                self.buffered_inputs.push(input.command);
+            }
+            return None;
+        }
+        self.apply_command(input.command)
+    }
+    /// Applies a player command to the active piece. Returns
+    /// `TurnEvent::EndTurn` if the command should end the current turn early.
+    pub fn apply_command(&mut self, command: Command) -> Option<TurnEvent> {
+        self.finesse.record_input(command);
+        self.run_command(command)
+    }
+    /// Moves the piece again for a held move key, which finesse does not
+    /// count as another press. With an auto repeat rate of zero the piece
+    /// shifts all the way to the wall.
+    fn auto_shift(&mut self, command: Command) -> Option<TurnEvent> {
+        if self.settings.arr > 0 {
+            return self.run_command(command);
+        }
+        let mut turn_event = None;
+        loop {
+            let start_piece = self.piece.clone();
+            turn_event = self.run_command(command).or(turn_event);
+            if self.piece == start_piece {
+                return turn_event;
+            }
+        }
+    }
+    /// Carries out a command, without counting it towards finesse.
+    fn run_command(&mut self, command: Command) -> Option<TurnEvent> {
+        match command {
+            Command::HardDrop => {
+                self.hard_drop();
+                Some(TurnEvent::EndTurn)
+            }
+            Command::Hold => {
+                self.hold();
+                None
+            }
+            Command::MoveDown => self.soft_drop(),
+            Command::SonicDrop => {
+                self.sonic_drop();
+                None
+            }
+            Command::MoveToColumn(col) => self.move_to_column(col),
+            Command::ActivateZone => {
+                if self.settings.zone {
+                    self.zone.activate();
+                }
+                None
+            }
+            Command::Pause => {
+                self.paused = true;
+                Some(TurnEvent::EndTurn)
+            }
+            Command::Quit => {
+                self.exit = Some(Exit::Quit);
+                Some(TurnEvent::EndTurn)
+            }
+            Command::Restart => {
+                self.exit = Some(Exit::Restart);
+                Some(TurnEvent::EndTurn)
+            }
+            Command::RotateClockwise => self.rotate(Rotation::Clockwise),
+            Command::RotateAnticlockwise => self.rotate(Rotation::Anticlockwise),
+            Command::Rotate180 => self.rotate(Rotation::Half),
+            // Menu commands only mean something in menus.
+            Command::MenuUp | Command::MenuDown | Command::Select | Command::Back => None,
+            other_command => {
+                let start_piece = self.piece.clone();
+                let was_grounded = self.is_grounded();
+                let turn_event = self.piece.move_peice(&self.board, other_command);
+                if self.piece != start_piece {
+                    self.last_rotation_kick = None;
+                    self.fall_at_20g();
+                    return self.restart_lock_delay(was_grounded).or(turn_event);
+                }
+                turn_event
+            }
+        }
+    }
+    /// Starts or continues a soft drop. Starting one ends the turn so the
+    /// piece falls straight away. Holding the key keeps sending the command,
+    /// which keeps the soft drop going at the faster turn interval.
+    fn soft_drop(&mut self) -> Option<TurnEvent> {
+        self.soft_drop_held = true;
+        if self.soft_dropping {
+            return None;
+        }
+        self.soft_dropping = true;
+        return Some(TurnEvent::EndTurn);
+    }
+    fn rotate(&mut self, rotation: Rotation) -> Option<TurnEvent> {
+        let was_grounded = self.is_grounded();
+        let kick_table = self.settings.kick_table.as_ref();
+        let kick_index = self
+            .piece
+            .rotate_with_kicks(&self.board, rotation, kick_table)?;
+        // Half turns use their own kick table, so none of their kicks is the
+        // T-spin triple kick.
+        self.last_rotation_kick = match rotation {
+            Rotation::Half => Some(0),
+            _other => Some(kick_index),
+        };
+        self.fall_at_20g();
+        self.restart_lock_delay(was_grounded)
+    }
+    /// Restarts the lock delay after the active piece moved or rotated on
+    /// the stack, if move reset allows it. Returns `TurnEvent::EndTurn` so
+    /// that a new turn, as long as the lock delay, starts.
+    fn restart_lock_delay(&mut self, was_grounded: bool) -> Option<TurnEvent> {
+        let rules = self.settings.lock_delay;
+        if rules.reset != LockReset::MoveReset
+            || self.lock_resets >= rules.max_resets
+            || !(was_grounded || self.is_grounded())
+        {
+            return None;
+        }
+        self.lock_resets += 1;
+        self.lock_delay_restarted = true;
+        return Some(TurnEvent::EndTurn);
+    }
+    /// Starts the lock delay rules afresh for a new active piece.
+    fn reset_lock_delay(&mut self) {
+        self.lock_resets = 0;
+        self.lowest_row = self.piece.position().row;
+        self.lock_delay_restarted = false;
+    }
+    /// Runs down every timer of the game by the time that has passed: the
+    /// zone, the garbage timer and the time left to play.
+    pub fn update_timers(&mut self, elapsed: Duration) {
+        self.update_zone(elapsed);
+        self.update_garbage_timer(elapsed);
+        if let Some(time_left) = &mut self.time_left {
+            *time_left = time_left.saturating_sub(elapsed);
+        }
+    }
+    /// Runs down the zone's timer by the time that has passed. When the zone
+    /// ends, the rows banked during it are cleared and scored together.
+    pub fn update_zone(&mut self, elapsed: Duration) {
+        if self.zone.tick(elapsed) {
+            let rows = self.board.clear_banked_rows();
+            self.score.award_zone_clear(rows);
+        }
+    }
+    /// Runs down the garbage timer by the time that has passed, raising the
+    /// rows that are due. The active piece is pushed up with the stack, and
+    /// garbage pushing blocks off the top is a garbage out. Garbage waits
+    /// while the zone is active.
+    pub fn update_garbage_timer(&mut self, elapsed: Duration) {
+        if self.zone.is_active() || self.is_topped_out() {
+            return;
+        }
+        let rows = match &mut self.garbage_timer {
+            Some(garbage_timer) => garbage_timer.tick(elapsed),
+            None => return,
+        };
+        if rows == 0 {
+            return;
+        }
+        let pushed_off = self.board.raise_garbage_now(rows);
+        if pushed_off && self.settings.top_out.garbage_out && self.top_out_with(TopOut::GarbageOut)
+        {
+            return;
+        }
+        for _ in 0..rows {
+            if self
+                .board
+                .check_is_valid_position(&self.piece.coordinates())
+                == PiecePositionValidity::Valid
+            {
+                break;
+            }
+            self.piece = self.piece.shifted(0, -1);
+            self.lowest_row -= 1;
+        }
+    }
+    /// Returns whether the player may still hold the active piece.
+    pub fn can_hold(&self) -> bool {
+        self.can_hold
+    }
+    /// Swaps the active piece with the held piece, or with the next piece if
+    /// nothing is held yet. The new active piece starts again from its spawn
+    /// position. Holding is only allowed once per piece, and is ignored if
+    /// the swapped-in piece would not fit.
+    fn hold(&mut self) {
+        if !self.can_hold {
+            return;
+        }
+        let incoming_shape = match self.held_piece {
+            Some(piece_shape) => piece_shape,
+            None => self.next_piece_shape(),
+        };
+        let incoming_piece = Self::new_piece(&self.settings, &incoming_shape);
+        if let PiecePositionValidity::Valid = self
+            .board
+            .check_is_valid_position(&incoming_piece.coordinates())
+        {
+            self.held_piece = Some(self.piece.piece_shape());
+            self.piece = incoming_piece;
+            self.finesse.reset();
+            self.reset_lock_delay();
+            self.can_hold = false;
+            self.last_rotation_kick = None;
+        }
+    }
+    /// Moves the piece one column at a time until it covers `col` or is
+    /// blocked.
+    fn move_to_column(&mut self, col: i16) -> Option<TurnEvent> {
+        let mut turn_event = None;
+        loop {
+            let cols: Vec<i16> = self.piece.coordinates().iter().map(|c| c.col).collect();
+            let step = match (cols.iter().min(), cols.iter().max()) {
+                (Some(left), _) if col < *left => Command::MoveLeft,
+                (_, Some(right)) if col > *right => Command::MoveRight,
+                _other => return turn_event,
+            };
+            let start_piece = self.piece.clone();
+            turn_event = self.apply_command(step).or(turn_event);
+            if self.piece == start_piece {
+                return turn_event;
+            }
+        }
+    }
+    /// Drops the piece onto the stack without locking it, leaving it free
+    /// to move until gravity or lock delay locks it. Scores as a soft drop.
+    fn sonic_drop(&mut self) {
+        let distance = self.piece.drop_distance(&self.board);
+        if distance == 0 {
+            return;
+        }
+        self.piece = self.piece.shifted(0, distance);
+        self.last_rotation_kick = None;
+        if self.piece.position().row > self.lowest_row {
+            self.lowest_row = self.piece.position().row;
+            self.lock_resets = 0;
+        }
+        self.score
+            .award_drop(distance as u64, self.score.table.soft_drop);
+    }
+    /// Drops the active piece straight onto the stack, scoring the score
+    /// table's hard drop points per row dropped. The piece can no longer
+    /// move down, so it locks at the end of the turn.
+    fn hard_drop(&mut self) {
+        let distance = self.piece.drop_distance(&self.board);
+        if distance > 0 {
+            self.piece = self.piece.shifted(0, distance);
+            self.last_rotation_kick = None;
+            self.last_hard_drop = Some(HardDrop {
+                piece: self.piece.clone(),
+                distance,
+                piece_number: self.pieces_locked,
+            });
+        }
+        self.score
+            .award_drop(distance as u64, self.score.table.hard_drop);
+    }
+    /// Moves the active piece down as far as the current gravity allows,
+    /// scoring a point per row while soft dropping. If it cannot move at all
+    /// it is locked in place, as for `move_down`, unless it moved on the
+    /// stack and restarted its lock delay. In the zone the piece only falls
+    /// while soft dropping. Returns whether the piece is still active.
+    pub fn apply_gravity(&mut self) -> bool {
+        let soft_dropping = self.soft_dropping;
+        self.soft_dropping = self.soft_drop_held;
+        self.soft_drop_held = false;
+        let lock_delay_restarted = self.lock_delay_restarted;
+        self.lock_delay_restarted = false;
+        let drop_distance = self.piece.drop_distance(&self.board);
+        if drop_distance == 0 {
+            if lock_delay_restarted {
+                return true;
+            }
+            return self.move_down();
+        }
+        if self.zone.is_active() && !soft_dropping {
+            return true;
+        }
+        let distance = drop_distance.min(self.gravity().rows);
+        self.piece = self.piece.shifted(0, distance);
+        self.last_rotation_kick = None;
+        if self.piece.position().row > self.lowest_row {
+            self.lowest_row = self.piece.position().row;
+            self.lock_resets = 0;
+        }
+        if soft_dropping {
+            self.score
+                .award_drop(distance as u64, self.score.table.soft_drop);
+        }
+        return true;
+    }
+    /// Moves the active piece down a row. If it cannot move it is locked in
+    /// place, any full rows are cleared and scored along with any T-spin
+    /// and cascades, party mode items trigger and drop,
+    /// queued garbage is cancelled by clears or rises if nothing was
+    /// cleared and its entry delay has passed, and the next piece is
+    /// spawned, or left pending if there is an entry delay. With strict
+    /// finesse on, a piece placed with a finesse error is sent back to its
+    /// spawn position instead of locking. In the zone, full rows are banked
+    /// at the bottom of the board instead of cleared, and garbage waits.
+    /// Locking or spawning may end the game, see `top_out`. Returns whether
+    /// the piece moved down.
+    pub fn move_down(&mut self) -> bool {
+        let spin = detect_spin(
+            &self.piece,
+            &self.board,
+            self.last_rotation_kick,
+            self.settings.all_spin,
+        );
+        let locked_cells = self.piece.coordinates();
+        if self.piece.drop_distance(&self.board) == 0 {
+            let spawned = Self::new_piece(&self.settings, &self.piece.piece_shape());
+            let fault = self.finesse.judge(&spawned, &self.piece, &self.board);
+            if fault && self.settings.strict_finesse {
+                self.piece = spawned;
+                self.last_rotation_kick = None;
+                self.reset_lock_delay();
+                return false;
+            }
+        }
+        if let Some(moved_piece) = self.piece.clone().move_down(&mut self.board) {
+            self.piece = moved_piece;
+            self.last_rotation_kick = None;
+            return true;
+        }
+        if self.settings.top_out.lock_out
+            && locked_cells.iter().all(|coord| coord.row < 0)
+            && self.top_out_with(TopOut::LockOut)
+        {
+            return false;
+        }
+        self.pieces_locked += 1;
+        let chain = if self.zone.is_active() {
+            self.board.bank_full_rows();
+            Vec::new()
+        } else {
+            self.board.clear_lines()
+        };
+        let rows = chain.first().copied().unwrap_or(0);
+        if rows > 0 && self.board.garbage_rules.cancel {
+            let cleared: u16 = chain.iter().sum();
+            self.board.cancel_garbage(cleared as usize);
+        }
+        if rows == 0
+            && !self.zone.is_active()
+            && self.board.raise_garbage()
+            && self.settings.top_out.garbage_out
+            && self.top_out_with(TopOut::GarbageOut)
+        {
+            return false;
+        }
+        self.board.count_down_garbage();
+        let line_clear = LineClear {
+            rows,
+            spin,
+            perfect_clear: rows > 0 && self.board.is_empty(),
+        };
+        self.score.award_line_clear(&line_clear);
+        for (step, &cascade_rows) in chain.iter().enumerate().skip(1) {
+            self.score.award_cascade(step as u64, cascade_rows);
+        }
+        if line_clear.rows > 0 || line_clear.spin != SpinKind::NoSpin {
+            self.last_clear = Some(line_clear);
+        }
+        if self.settings.zone {
+            self.zone.fill(chain.iter().map(|&rows| rows as u64).sum());
+        }
+        if self.settings.mode == GameMode::Party {
+            self.items.trigger_next(&mut self.board);
+            self.items.roll_drop(rows);
+        }
+        self.can_hold = true;
+        self.last_rotation_kick = None;
+        self.soft_dropping = false;
+        self.soft_drop_held = false;
+        if self.settings.entry_delay > 0 {
+            self.spawn_pending = true;
+        } else {
+            let piece_shape = self.next_piece_shape();
+            self.spawn(&piece_shape);
+        }
+        return false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::ClearStyle;
+    use crate::items::Item;
+    use crate::models::Coord;
+    use crate::piece::Orientation;
+    use crate::puzzle::Puzzle;
+    use crate::settings::LockDelayRules;
+
+    #[test]
+    fn test_hard_drop_moves_piece_to_floor_and_scores() {
+        let mut game_state = GameState::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::I);
+        let event = game_state.apply_command(Command::HardDrop);
+        assert!(matches!(event, Some(TurnEvent::EndTurn)));
+        assert_eq!(game_state.score.points, 36);
+        for coord in game_state.piece.coordinates() {
+            assert_eq!(coord.row, TetrisBoard::NUM_ROWS as i16 - 1);
+        }
+    }
+
+    #[test]
+    fn test_hard_dropped_piece_locks_on_move_down() {
+        let mut game_state = GameState::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::O);
+        game_state.apply_command(Command::HardDrop);
+        assert!(!game_state.move_down());
+        assert_eq!(
+            game_state.board.occupied_rows(),
+            vec![TetrisBoard::NUM_ROWS - 2, TetrisBoard::NUM_ROWS - 1]
+        );
+    }
+
+    #[test]
+    fn test_hard_drop_is_recorded_for_its_trail() {
+        let mut game_state = GameState::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::I);
+        game_state.apply_command(Command::MoveDown);
+        assert_eq!(game_state.last_hard_drop, None);
+        game_state.apply_command(Command::HardDrop);
+        assert_eq!(
+            game_state.last_hard_drop,
+            Some(HardDrop {
+                piece: TetrisPiece::new(&PieceShape::I).shifted(0, 18),
+                distance: 18,
+                piece_number: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_locking_piece_clears_rows_and_scores() {
+        let mut game_state = GameState::new();
+        game_state.board.board[TetrisBoard::NUM_ROWS - 1] = vec![true; TetrisBoard::NUM_COLS];
+        game_state.board.board[TetrisBoard::NUM_ROWS - 1][0] = false;
+        game_state.board.board[TetrisBoard::NUM_ROWS - 1][1] = false;
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(-4, 18);
+        assert!(!game_state.move_down());
+        assert_eq!(game_state.score.points, 100);
+        assert_eq!(
+            game_state.board.occupied_rows(),
+            vec![TetrisBoard::NUM_ROWS - 1]
+        );
+    }
+
+    #[test]
+    fn test_gravity_follows_level() {
+        let mut game_state = GameState::new();
+        let start_interval = game_state.gravity().interval;
+        game_state.score.level = 2;
+        assert!(game_state.gravity().interval < start_interval);
+    }
+
+    #[test]
+    fn test_slowdown_lengthens_gravity() {
+        let game_state = GameState::with_settings(GameSettings {
+            slowdown: 25,
+            ..GameSettings::default()
+        });
+        assert_eq!(game_state.gravity().interval, 1250);
+    }
+
+    #[test]
+    fn test_20g_drops_piece_to_stack() {
+        let mut game_state = GameState::new();
+        game_state.score.level = 20;
+        game_state.piece = TetrisPiece::new(&PieceShape::O);
+        assert!(game_state.apply_gravity());
+        assert_eq!(game_state.piece.drop_distance(&game_state.board), 0);
+        assert!(!game_state.apply_gravity());
+        assert_eq!(
+            game_state.board.occupied_rows(),
+            vec![TetrisBoard::NUM_ROWS - 2, TetrisBoard::NUM_ROWS - 1]
+        );
+    }
+
+    #[test]
+    fn test_hold_swaps_active_and_held_pieces() {
+        let mut game_state = GameState::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::T);
+        game_state.held_piece = Some(PieceShape::I);
+        game_state.apply_command(Command::Hold);
+        assert_eq!(game_state.held_piece, Some(PieceShape::T));
+        assert_eq!(game_state.piece, TetrisPiece::new(&PieceShape::I));
+    }
+
+    #[test]
+    fn test_preview_shows_the_pieces_dealt_next() {
+        let mut game_state = GameState::new();
+        let next_pieces = game_state.next_pieces();
+        assert_eq!(next_pieces.len(), GameState::PREVIEW_PIECES);
+        game_state.choose_piece(PieceShape::O);
+        assert_eq!(game_state.next_pieces()[0], PieceShape::O);
+        assert_eq!(game_state.next_pieces()[1..], next_pieces[..4]);
+        for piece_shape in [PieceShape::O, next_pieces[0], next_pieces[1]] {
+            assert_eq!(game_state.next_piece_shape(), piece_shape);
+        }
+        assert_eq!(game_state.next_pieces()[..3], next_pieces[2..]);
+    }
+
+    #[test]
+    fn test_first_hold_takes_a_new_piece() {
+        let mut game_state = GameState::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::T).shifted(0, 5);
+        game_state.apply_command(Command::Hold);
+        assert_eq!(game_state.held_piece, Some(PieceShape::T));
+        assert_eq!(
+            game_state.piece,
+            TetrisPiece::new(&game_state.piece.piece_shape())
+        );
+    }
+
+    #[test]
+    fn test_hold_is_allowed_once_per_piece() {
+        let mut game_state = GameState::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::T);
+        game_state.held_piece = Some(PieceShape::I);
+        game_state.apply_command(Command::Hold);
+        assert!(!game_state.can_hold());
+        game_state.apply_command(Command::Hold);
+        assert_eq!(game_state.held_piece, Some(PieceShape::T));
+        assert_eq!(game_state.piece.piece_shape(), PieceShape::I);
+
+        game_state.apply_command(Command::HardDrop);
+        game_state.move_down();
+        assert!(game_state.can_hold());
+    }
+    #[test]
+    fn test_t_spin_double_is_scored_and_announced() {
+        let mut game_state = GameState::new();
+        for col in 0..TetrisBoard::NUM_COLS {
+            if col != 4 {
+                game_state.board.board[19][col] = true;
+            }
+            if !(3..=5).contains(&col) {
+                game_state.board.board[18][col] = true;
+            }
+        }
+        game_state.board.board[17][3] = true;
+        // A T pointing left, sitting in the slot, which twists round to
+        // point down into it.
+        game_state.piece = TetrisPiece::new(&PieceShape::T)
+            .rotated(Rotation::Anticlockwise)
+            .shifted(0, 17);
+        game_state.apply_command(Command::RotateAnticlockwise);
+        assert!(!game_state.move_down());
+        assert_eq!(game_state.score.points, 1200);
+        assert_eq!(
+            game_state
+                .last_clear
+                .and_then(|line_clear| line_clear.name()),
+            Some(String::from("T-Spin Double"))
+        );
+    }
+
+    #[test]
+    fn test_moving_after_rotation_is_not_a_spin() {
+        let mut game_state = GameState::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::T).shifted(0, 10);
+        game_state.apply_command(Command::RotateClockwise);
+        assert!(game_state.last_rotation_kick.is_some());
+        game_state.apply_command(Command::MoveLeft);
+        assert!(game_state.last_rotation_kick.is_none());
+    }
+    #[test]
+    fn test_soft_drop_scores_and_speeds_up_turns() {
+        let mut game_state = GameState::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::T);
+        let normal_interval = game_state.turn_interval();
+        assert!(matches!(
+            game_state.apply_command(Command::MoveDown),
+            Some(TurnEvent::EndTurn)
+        ));
+        assert!(game_state.apply_gravity());
+        assert_eq!(game_state.score.points, 1);
+        assert_eq!(
+            game_state.turn_interval(),
+            normal_interval / game_state.settings.soft_drop_factor
+        );
+    }
+
+    #[test]
+    fn test_held_soft_drop_continues_without_ending_turn() {
+        let mut game_state = GameState::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::T);
+        game_state.apply_command(Command::MoveDown);
+        game_state.apply_gravity();
+        assert!(game_state.apply_command(Command::MoveDown).is_none());
+        game_state.apply_gravity();
+        assert_eq!(game_state.score.points, 2);
+        // Released: the next turn is soft dropped, then gravity is normal.
+        game_state.apply_gravity();
+        assert_eq!(game_state.score.points, 3);
+        assert_eq!(game_state.turn_interval(), game_state.gravity().interval);
+        game_state.apply_gravity();
+        assert_eq!(game_state.score.points, 3);
+    }
+    #[test]
+    fn test_emptying_the_board_is_a_perfect_clear() {
+        let mut game_state = GameState::new();
+        game_state.board.board[19] = vec![true; TetrisBoard::NUM_COLS];
+        for col in 3..7 {
+            game_state.board.board[19][col] = false;
+        }
+        game_state.piece = TetrisPiece::new(&PieceShape::I);
+        game_state.apply_command(Command::HardDrop);
+        game_state.move_down();
+        assert!(game_state.board.is_empty());
+        assert!(game_state.last_clear.unwrap().perfect_clear);
+        assert_eq!(game_state.score.points, 2 * 18 + 100 + 800);
+    }
+    // Fills the top two rows, leaving a gap so they do not clear.
+    fn fill_spawn_rows(game_state: &mut GameState) {
+        for row in 0..2 {
+            game_state.board.board[row] = vec![true; TetrisBoard::NUM_COLS];
+            game_state.board.board[row][0] = false;
+        }
+    }
+    #[test]
+    fn test_spawning_into_the_stack_is_a_block_out() {
+        let mut game_state = GameState::new();
+        fill_spawn_rows(&mut game_state);
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(0, 18);
+        assert!(!game_state.is_topped_out());
+        game_state.move_down();
+        assert_eq!(game_state.top_out(), Some(TopOut::BlockOut));
+    }
+    #[test]
+    fn test_zen_mode_clears_the_bottom_half_instead_of_topping_out() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            mode: GameMode::Zen,
+            ..GameSettings::default()
+        });
+        fill_spawn_rows(&mut game_state);
+        game_state.board.board[19][3] = true;
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(0, 18);
+        game_state.move_down();
+        assert!(!game_state.is_topped_out());
+        assert_eq!(game_state.board.occupied_rows(), vec![10, 11]);
+        assert_eq!(
+            game_state
+                .board
+                .check_is_valid_position(&game_state.piece.coordinates()),
+            PiecePositionValidity::Valid
+        );
+    }
+    #[test]
+    fn test_piece_spawns_in_vanish_zone_without_block_out() {
+        let mut settings = GameSettings::default();
+        settings.top_out.block_out = false;
+        let mut game_state = GameState::with_settings(settings);
+        fill_spawn_rows(&mut game_state);
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(0, 18);
+        game_state.move_down();
+        assert!(!game_state.is_topped_out());
+        for coord in game_state.piece.coordinates() {
+            assert!(coord.row < 0);
+        }
+    }
+    #[test]
+    fn test_locking_above_the_board_is_a_lock_out() {
+        let mut game_state = GameState::new();
+        game_state.board.board[0] = vec![true; TetrisBoard::NUM_COLS];
+        game_state.board.board[0][0] = false;
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(0, -2);
+        game_state.move_down();
+        assert_eq!(game_state.top_out(), Some(TopOut::LockOut));
+    }
+    #[test]
+    fn test_held_rotate_key_rotates_next_piece_on_spawn() {
+        let mut game_state = GameState::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(0, 18);
+        game_state.apply_input(KeyInput {
+            command: Command::RotateClockwise,
+            state: KeyState::Pressed,
+        });
+        game_state.move_down();
+        assert_eq!(game_state.piece.orientation(), Orientation::Right);
+    }
+    #[test]
+    fn test_released_rotate_key_does_not_rotate_next_piece() {
+        let mut game_state = GameState::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(0, 18);
+        for state in [KeyState::Pressed, KeyState::Released] {
+            game_state.apply_input(KeyInput {
+                command: Command::RotateClockwise,
+                state,
+            });
+        }
+        game_state.move_down();
+        assert_eq!(game_state.piece.orientation(), Orientation::Spawn);
+    }
+    #[test]
+    fn test_tapped_rotate_key_does_not_rotate_next_piece() {
+        let mut game_state = GameState::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(0, 18);
+        game_state.apply_input(KeyInput {
+            command: Command::RotateClockwise,
+            state: KeyState::Tapped,
+        });
+        game_state.move_down();
+        assert_eq!(game_state.piece.orientation(), Orientation::Spawn);
+    }
+    #[test]
+    fn test_held_hold_key_holds_next_piece_on_spawn() {
+        let mut game_state = GameState::new();
+        game_state.held_piece = Some(PieceShape::I);
+        game_state.can_hold = false;
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(0, 18);
+        game_state.apply_input(KeyInput {
+            command: Command::Hold,
+            state: KeyState::Pressed,
+        });
+        game_state.move_down();
+        assert_eq!(game_state.piece.piece_shape(), PieceShape::I);
+        assert!(!game_state.can_hold());
+    }
+    #[test]
+    fn test_garbage_rises_when_piece_locks_without_clearing() {
+        let mut game_state = GameState::new();
+        game_state.board.receive_garbage(2);
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(0, 18);
+        game_state.move_down();
+        assert_eq!(game_state.board.occupied_rows(), vec![16, 17, 18, 19]);
+        assert!(!game_state.is_topped_out());
+    }
+    #[test]
+    fn test_garbage_pushing_stack_off_top_is_a_garbage_out() {
+        let mut game_state = GameState::new();
+        game_state.board.board[0][9] = true;
+        game_state.board.receive_garbage(1);
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(0, 18);
+        game_state.move_down();
+        assert_eq!(game_state.top_out(), Some(TopOut::GarbageOut));
+    }
+    #[test]
+    fn test_entry_delay_buffers_inputs_until_spawn() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            entry_delay: 100,
+            ..GameSettings::default()
+        });
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(0, 18);
+        game_state.move_down();
+        assert!(game_state.is_spawn_pending());
+        for command in [Command::RotateClockwise, Command::MoveLeft] {
+            let event = game_state.apply_input(KeyInput {
+                command,
+                state: KeyState::Tapped,
+            });
+            assert!(event.is_none());
+        }
+        game_state.spawn_next_piece();
+        assert!(!game_state.is_spawn_pending());
+        let spawned_piece = TetrisPiece::new(&game_state.piece.piece_shape())
+            .rotated(Rotation::Clockwise)
+            .shifted(-1, 0);
+        assert_eq!(game_state.piece, spawned_piece);
+    }
+    #[test]
+    fn test_every_input_during_entry_delay_is_replayed() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            entry_delay: 100,
+            ..GameSettings::default()
+        });
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(0, 18);
+        game_state.move_down();
+        let inputs = [
+            (Command::MoveLeft, KeyState::Pressed),
+            (Command::MoveLeft, KeyState::Released),
+            (Command::MoveLeft, KeyState::Pressed),
+            (Command::MoveLeft, KeyState::Released),
+            (Command::HardDrop, KeyState::Tapped),
+        ];
+        for (command, state) in inputs {
+            assert!(game_state
+                .apply_input(KeyInput { command, state })
+                .is_none());
+        }
+        game_state.spawn_next_piece();
+        let spawned_piece = TetrisPiece::new(&game_state.piece.piece_shape()).shifted(-2, 0);
+        assert_eq!(game_state.piece, spawned_piece);
+        assert_eq!(game_state.pieces_locked(), 1);
+    }
+    #[test]
+    fn test_quit_and_restart_are_not_held_for_the_entry_delay() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            entry_delay: 100,
+            ..GameSettings::default()
+        });
+        assert_eq!(game_state.exit(), None);
+        game_state.hard_drop();
+        game_state.move_down();
+        assert!(game_state.is_spawn_pending());
+        let event = game_state.apply_input(KeyInput {
+            command: Command::Restart,
+            state: KeyState::Tapped,
+        });
+        assert!(matches!(event, Some(TurnEvent::EndTurn)));
+        assert_eq!(game_state.exit(), Some(Exit::Restart));
+        game_state.apply_command(Command::Quit);
+        assert_eq!(game_state.exit(), Some(Exit::Quit));
+    }
+    #[test]
+    fn test_menu_commands_leave_the_game_alone() {
+        let mut game_state = GameState::new();
+        let piece = game_state.piece.clone();
+        for command in [
+            Command::MenuUp,
+            Command::MenuDown,
+            Command::Select,
+            Command::Back,
+        ] {
+            assert!(game_state.apply_command(command).is_none());
+        }
+        assert_eq!(game_state.piece, piece);
+        assert!(!game_state.is_paused());
+        assert_eq!(game_state.exit(), None);
+    }
+    #[test]
+    fn test_sonic_drop_lands_without_locking() {
+        let mut game_state = GameState::new();
+        let event = game_state.apply_command(Command::SonicDrop);
+        assert!(event.is_none());
+        assert!(game_state.is_grounded());
+        assert_eq!(game_state.pieces_locked(), 0);
+        let landed = game_state.piece.clone();
+        game_state.apply_command(Command::MoveLeft);
+        assert_eq!(game_state.piece, landed.shifted(-1, 0));
+        assert!(game_state.score.points > 0);
+    }
+    #[test]
+    fn test_piece_is_steered_to_a_column_until_blocked() {
+        let mut game_state = GameState::new();
+        game_state.piece = TetrisPiece::new(&PieceShape::O);
+        game_state.apply_command(Command::MoveToColumn(8));
+        let cols: Vec<i16> = game_state
+            .piece
+            .coordinates()
+            .iter()
+            .map(|c| c.col)
+            .collect();
+        assert!(cols.contains(&8) && cols.iter().all(|col| *col >= 7));
+        game_state.apply_command(Command::MoveToColumn(-5));
+        let cols: Vec<i16> = game_state
+            .piece
+            .coordinates()
+            .iter()
+            .map(|c| c.col)
+            .collect();
+        assert_eq!(cols.iter().min(), Some(&0));
+    }
+    #[test]
+    fn test_big_mode_pieces_clear_two_lines_per_mino_row() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            mode: GameMode::Big,
+            ..GameSettings::default()
+        });
+        assert_eq!(game_state.piece.scale(), 2);
+        for row in 18..20 {
+            game_state.board.board[row] = vec![true; TetrisBoard::NUM_COLS];
+            for col in 0..4 {
+                game_state.board.board[row][col] = false;
+            }
+        }
+        game_state.piece =
+            GameState::new_piece(&game_state.settings, &PieceShape::O).shifted(-3, 0);
+        game_state.apply_command(Command::HardDrop);
+        game_state.move_down();
+        assert_eq!(game_state.score.lines, 2);
+        assert_eq!(game_state.board.occupied_rows(), vec![18, 19]);
+    }
+    #[test]
+    fn test_cascade_chains_score_after_the_lock() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            clear_style: ClearStyle::Cascade,
+            ..GameSettings::default()
+        });
+        game_state.board.board[19] = vec![true; TetrisBoard::NUM_COLS];
+        game_state.board.board[19][9] = false;
+        game_state.board.board[18] = vec![true; TetrisBoard::NUM_COLS];
+        game_state.board.board[18][0] = false;
+        game_state.board.board[18][1] = false;
+        game_state.board.board[17][9] = true;
+        // The O completes row 18, then the cell above it falls into the
+        // hole in row 19 and completes that too.
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(-4, 17);
+        game_state.move_down();
+        assert_eq!(game_state.score.lines, 2);
+        assert_eq!(game_state.score.points, 100 + 200);
+    }
+    #[test]
+    fn test_party_mode_triggers_an_item_when_a_piece_locks() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            mode: GameMode::Party,
+            ..GameSettings::default()
+        });
+        game_state.score.level = 10;
+        game_state.items.push(Item::SlowGravity);
+        let fast = game_state.gravity();
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(0, 18);
+        game_state.move_down();
+        assert!(game_state.items.queued().is_empty());
+        assert!(game_state.gravity().interval > fast.interval);
+    }
+    #[test]
+    fn test_cheese_mode_finishes_when_garbage_is_dug_out() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            mode: GameMode::Cheese,
+            cheese_rows: 1,
+            ..GameSettings::default()
+        });
+        assert_eq!(game_state.board.occupied_rows(), vec![19]);
+        assert!(!game_state.is_finished());
+        assert_eq!(
+            game_state.leaderboard_category(),
+            Some(String::from("cheese-1"))
+        );
+        game_state.board.board[19] = vec![true; TetrisBoard::NUM_COLS];
+        game_state.board.board[19][0] = false;
+        game_state.piece = TetrisPiece::new(&PieceShape::I)
+            .rotated(Rotation::Clockwise)
+            .shifted(-5, 16);
+        game_state.move_down();
+        assert_eq!(game_state.score.lines, 1);
+        assert!(game_state.is_finished());
+        assert!(!GameState::new().is_finished());
+    }
+    #[test]
+    fn test_marathon_starts_at_the_chosen_level_and_finishes() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            mode: GameMode::Marathon,
+            start_level: 3,
+            ..GameSettings::default()
+        });
+        assert_eq!(game_state.score.level, 3);
+        game_state.score.lines = GameState::MARATHON_LINES - 1;
+        assert!(!game_state.is_finished());
+        game_state.score.lines = GameState::MARATHON_LINES;
+        assert!(game_state.is_finished());
+        assert_eq!(game_state.leaderboard_category(), None);
+    }
+    #[test]
+    fn test_master_pieces_spawn_and_move_on_the_stack() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            mode: GameMode::Master,
+            ..GameSettings::default()
+        });
+        assert!(game_state.gravity().is_20g());
+        assert!(game_state.is_grounded());
+        // An O resting on a ledge drops off it as soon as it moves past.
+        game_state.board.board[19][0] = true;
+        game_state.board.board[19][1] = true;
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(-4, 17);
+        game_state.apply_command(Command::MoveRight);
+        assert_eq!(
+            game_state.piece,
+            TetrisPiece::new(&PieceShape::O).shifted(-3, 17)
+        );
+        game_state.apply_command(Command::MoveRight);
+        assert_eq!(
+            game_state.piece,
+            TetrisPiece::new(&PieceShape::O).shifted(-2, 18)
+        );
+    }
+    #[test]
+    fn test_survival_garbage_rises_on_a_timer_and_pushes_the_piece_up() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            mode: GameMode::Survival,
+            ..GameSettings::default()
+        });
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(0, 18);
+        game_state.update_garbage_timer(GameState::SURVIVAL_START_INTERVAL / 2);
+        assert_eq!(game_state.board.garbage_rows(), 0);
+        game_state.update_garbage_timer(GameState::SURVIVAL_START_INTERVAL / 2);
+        assert_eq!(game_state.board.garbage_rows(), 1);
+        assert_eq!(
+            game_state.piece,
+            TetrisPiece::new(&PieceShape::O).shifted(0, 17)
+        );
+        assert!(
+            game_state.garbage_timer.as_ref().unwrap().until_next()
+                < GameState::SURVIVAL_START_INTERVAL
+        );
+        game_state.board.board[0][0] = true;
+        game_state.update_garbage_timer(GameState::SURVIVAL_START_INTERVAL);
+        assert_eq!(game_state.top_out(), Some(TopOut::GarbageOut));
+    }
+
+    #[test]
+    fn test_dig_race_regenerates_garbage_until_time_runs_out() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            mode: GameMode::DigRace,
+            ..GameSettings::default()
+        });
+        let start_rows = GameState::DIG_RACE_START_ROWS as usize;
+        assert_eq!(game_state.board.garbage_rows(), start_rows);
+        game_state.board.board[TetrisBoard::NUM_ROWS - 1] = vec![true; TetrisBoard::NUM_COLS];
+        game_state.board.clear_lines();
+        assert_eq!(game_state.board.garbage_cleared(), 1);
+        game_state.update_timers(GameState::DIG_RACE_INTERVAL);
+        assert_eq!(game_state.board.garbage_rows(), start_rows);
+        assert!(!game_state.is_finished());
+        game_state.update_timers(GameState::DIG_RACE_TIME);
+        assert!(game_state.is_finished());
+        assert_eq!(game_state.time_left(), Some(Duration::ZERO));
+        assert_eq!(
+            game_state.leaderboard_category(),
+            Some(String::from("dig-race-2m"))
+        );
+    }
+
+    #[test]
+    fn test_practice_pauses_to_edit_the_board_and_choose_pieces() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            mode: GameMode::Practice,
+            ..GameSettings::default()
+        });
+        let event = game_state.apply_command(Command::Pause);
+        assert!(matches!(event, Some(TurnEvent::EndTurn)));
+        assert!(game_state.is_paused());
+        let cell = Coord { col: 3, row: 19 };
+        game_state.board.toggle_cell(&cell);
+        assert!(game_state.board.is_occupied(&cell));
+        game_state.choose_piece(PieceShape::I);
+        game_state.choose_piece(PieceShape::S);
+        game_state.choose_piece(PieceShape::Z);
+        game_state.unchoose_piece();
+        game_state.resume();
+        assert!(!game_state.is_paused());
+        assert_eq!(game_state.piece, TetrisPiece::new(&PieceShape::I));
+        assert_eq!(game_state.chosen_pieces(), [PieceShape::S]);
+        game_state.hard_drop();
+        game_state.move_down();
+        assert_eq!(game_state.piece.piece_shape(), PieceShape::S);
+        assert!(game_state.chosen_pieces().is_empty());
+    }
+
+    #[test]
+    fn test_resuming_outside_practice_keeps_the_piece_in_place() {
+        let mut game_state = GameState::new();
+        game_state.move_down();
+        let piece = game_state.piece.clone();
+        let event = game_state.apply_command(Command::Pause);
+        assert!(matches!(event, Some(TurnEvent::EndTurn)));
+        assert!(game_state.is_paused());
+        game_state.resume();
+        assert!(!game_state.is_paused());
+        assert_eq!(game_state.piece, piece);
+    }
+
+    #[test]
+    fn test_daily_games_with_the_same_seed_get_the_same_pieces() {
+        let daily_settings = GameSettings {
+            mode: GameMode::Daily,
+            randomizer: Randomizer::PureRandom,
+            seed: Some(DailyChallenge::for_day(20_376).seed()),
+            ..GameSettings::default()
+        };
+        let deal = |settings: GameSettings| -> Vec<PieceShape> {
+            let mut game_state = GameState::with_settings(settings);
+            let mut pieces = vec![game_state.piece.piece_shape()];
+            pieces.extend((1..14).map(|_| game_state.next_piece_shape()));
+            pieces
+        };
+        assert_eq!(deal(daily_settings.clone()), deal(daily_settings.clone()));
+        let mut first_bag = deal(daily_settings)[..7].to_vec();
+        first_bag.sort_by_key(|piece_shape| piece_shape.name());
+        assert_eq!(
+            first_bag,
+            [
+                PieceShape::I,
+                PieceShape::J,
+                PieceShape::L,
+                PieceShape::O,
+                PieceShape::S,
+                PieceShape::T,
+                PieceShape::Z
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sprint_finishes_after_forty_lines() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            mode: GameMode::Sprint,
+            ..GameSettings::default()
+        });
+        game_state.score.lines = GameState::SPRINT_LINES - 1;
+        assert!(!game_state.is_finished());
+        game_state.board.board[19] = vec![true; TetrisBoard::NUM_COLS];
+        game_state.board.board[19][0] = false;
+        game_state.piece = TetrisPiece::new(&PieceShape::I)
+            .rotated(Rotation::Clockwise)
+            .shifted(-5, 16);
+        game_state.move_down();
+        assert!(game_state.is_finished());
+        assert_eq!(game_state.pieces_locked(), 1);
+        assert_eq!(
+            game_state.leaderboard_category(),
+            Some(String::from("sprint-40"))
+        );
+    }
+    #[test]
+    fn test_puzzle_is_solved_by_reaching_its_goal() {
+        let puzzle = Puzzle::built_in().remove(0);
+        let mut game_state = GameState::with_settings(GameSettings {
+            mode: GameMode::Puzzle,
+            puzzle: Some(puzzle),
+            ..GameSettings::default()
+        });
+        assert_eq!(game_state.board.occupied_rows(), vec![16, 17, 18, 19]);
+        assert_eq!(game_state.piece.piece_shape(), PieceShape::I);
+        assert!(!game_state.is_finished());
+        game_state.apply_command(Command::RotateClockwise);
+        for _ in 0..5 {
+            game_state.apply_command(Command::MoveRight);
+        }
+        game_state.apply_command(Command::HardDrop);
+        game_state.move_down();
+        assert!(game_state.is_puzzle_solved());
+        assert!(game_state.is_finished());
+    }
+    #[test]
+    fn test_puzzle_fails_when_pieces_run_out() {
+        let puzzle = Puzzle::built_in().remove(0);
+        let mut game_state = GameState::with_settings(GameSettings {
+            mode: GameMode::Puzzle,
+            puzzle: Some(puzzle),
+            ..GameSettings::default()
+        });
+        game_state.apply_command(Command::HardDrop);
+        game_state.move_down();
+        assert!(!game_state.is_puzzle_solved());
+        assert!(game_state.is_finished());
+    }
+    #[test]
+    fn test_strict_finesse_sends_misplaced_pieces_back_to_spawn() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            strict_finesse: true,
+            ..GameSettings::default()
+        });
+        game_state.piece = TetrisPiece::new(&PieceShape::T);
+        for command in [Command::MoveRight, Command::MoveLeft, Command::HardDrop] {
+            game_state.apply_command(command);
+        }
+        assert!(!game_state.move_down());
+        assert!(game_state.board.is_empty());
+        assert_eq!(game_state.piece, TetrisPiece::new(&PieceShape::T));
+        assert_eq!(game_state.finesse.errors, 1);
+        game_state.apply_command(Command::HardDrop);
+        game_state.move_down();
+        assert!(!game_state.board.is_empty());
+        assert_eq!(game_state.finesse.errors, 1);
+    }
+    #[test]
+    fn test_auto_shift_repeats_are_not_finesse_presses() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            arr: 0,
+            ..GameSettings::default()
+        });
+        game_state.piece = TetrisPiece::new(&PieceShape::T);
+        for state in [KeyState::Pressed, KeyState::Repeated, KeyState::Released] {
+            game_state.apply_input(KeyInput {
+                command: Command::MoveLeft,
+                state,
+            });
+        }
+        // With no repeat delay the piece shifts straight to the wall.
+        assert_eq!(
+            game_state.piece,
+            TetrisPiece::new(&PieceShape::T).shifted(-3, 0)
+        );
+        game_state.apply_command(Command::HardDrop);
+        game_state.move_down();
+        assert_eq!(
+            (game_state.finesse.placements, game_state.finesse.errors),
+            (1, 0)
+        );
+    }
+
+    #[test]
+    fn test_zone_banks_rows_until_it_ends() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            zone: true,
+            ..GameSettings::default()
+        });
+        game_state.zone.fill(Zone::MIN_LINES);
+        game_state.apply_command(Command::ActivateZone);
+        assert!(game_state.zone.is_active());
+        game_state.piece = TetrisPiece::new(&PieceShape::O);
+        assert!(game_state.apply_gravity());
+        assert_eq!(game_state.piece, TetrisPiece::new(&PieceShape::O));
+        game_state.board.board[TetrisBoard::NUM_ROWS - 1] = vec![true; TetrisBoard::NUM_COLS];
+        game_state.board.board[TetrisBoard::NUM_ROWS - 1][0] = false;
+        game_state.board.board[TetrisBoard::NUM_ROWS - 1][1] = false;
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(-4, 18);
+        assert!(!game_state.move_down());
+        assert_eq!(game_state.board.banked_rows(), 1);
+        assert_eq!(game_state.score.lines, 0);
+        assert_eq!(game_state.zone.meter(), 0);
+        game_state.update_zone(game_state.zone.remaining().unwrap());
+        assert!(!game_state.zone.is_active());
+        assert_eq!(game_state.board.banked_rows(), 0);
+        assert_eq!(game_state.score.lines, 1);
+        assert_eq!(
+            game_state.board.occupied_rows(),
+            vec![TetrisBoard::NUM_ROWS - 1]
+        );
+    }
+
+    #[test]
+    fn test_moving_on_the_stack_restarts_lock_delay_up_to_the_limit() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            lock_delay: LockDelayRules {
+                max_resets: 2,
+                ..LockDelayRules::default()
+            },
+            ..GameSettings::default()
+        });
+        game_state.piece = TetrisPiece::new(&PieceShape::T).shifted(0, 18);
+        assert_eq!(
+            game_state.turn_interval(),
+            game_state.settings.lock_delay.delay
+        );
+        for command in [Command::MoveLeft, Command::MoveRight] {
+            assert!(matches!(
+                game_state.apply_command(command),
+                Some(TurnEvent::EndTurn)
+            ));
+            assert!(game_state.apply_gravity());
+        }
+        assert!(game_state.apply_command(Command::MoveLeft).is_none());
+        assert!(!game_state.apply_gravity());
+        assert!(!game_state.board.is_empty());
+    }
+
+    #[test]
+    fn test_classic_locking_has_no_lock_delay() {
+        let mut game_state = GameState::with_settings(GameSettings {
+            lock_delay: LockDelayRules {
+                reset: LockReset::Classic,
+                ..LockDelayRules::default()
+            },
+            ..GameSettings::default()
+        });
+        game_state.piece = TetrisPiece::new(&PieceShape::T).shifted(0, 18);
+        assert_eq!(game_state.turn_interval(), game_state.gravity().interval);
+        assert!(game_state.apply_command(Command::MoveLeft).is_none());
+        assert!(!game_state.apply_gravity());
+        assert!(!game_state.board.is_empty());
+    }
+
+    #[test]
+    fn test_line_clears_cancel_queued_garbage() {
+        let mut game_state = GameState::new();
+        game_state.board.board[TetrisBoard::NUM_ROWS - 1] = vec![true; TetrisBoard::NUM_COLS];
+        game_state.board.board[TetrisBoard::NUM_ROWS - 1][0] = false;
+        game_state.board.board[TetrisBoard::NUM_ROWS - 1][1] = false;
+        game_state.board.receive_garbage(3);
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(-4, 18);
+        game_state.move_down();
+        assert_eq!(game_state.board.pending_garbage(), 2);
+        game_state.board.garbage_rules.cancel = false;
+        game_state.board.board[TetrisBoard::NUM_ROWS - 1] = vec![true; TetrisBoard::NUM_COLS];
+        game_state.board.board[TetrisBoard::NUM_ROWS - 1][0] = false;
+        game_state.board.board[TetrisBoard::NUM_ROWS - 1][1] = false;
+        game_state.piece = TetrisPiece::new(&PieceShape::O).shifted(-4, 17);
+        game_state.move_down();
+        assert_eq!(game_state.board.pending_garbage(), 2);
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+/// A repeating timer that raises garbage from the bottom of the board,
+/// running alongside the turn timer. Each time it goes off its interval
+/// can shorten, down to a floor, so that garbage rises faster the longer
+/// the game goes on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GarbageTimer {
+    /// Time between rows of garbage.
+    interval: Duration,
+    /// Time left before the next row rises.
+    until_next: Duration,
+    /// How much shorter the interval gets each time a row rises.
+    speed_up: Duration,
+    /// Shortest the interval can get.
+    min_interval: Duration,
+}
+impl GarbageTimer {
+    /// Shortest interval allowed, so that a tick always ends.
+    const SHORTEST: Duration = Duration::from_millis(1);
+
+    /// A timer that raises a row every `interval`.
+    pub fn new(interval: Duration) -> Self {
+        Self::accelerating(interval, Duration::ZERO, interval)
+    }
+    /// A timer that starts by raising a row every `interval`, which then
+    /// shortens by `speed_up` with every row until it reaches
+    /// `min_interval`.
+    pub fn accelerating(interval: Duration, speed_up: Duration, min_interval: Duration) -> Self {
+        let interval = interval.max(Self::SHORTEST);
+        Self {
+            interval,
+            until_next: interval,
+            speed_up,
+            min_interval: min_interval.clamp(Self::SHORTEST, interval),
+        }
+    }
+    /// Time left before the next row rises.
+    pub fn until_next(&self) -> Duration {
+        self.until_next
+    }
+    /// Runs down the timer. Returns the number of rows that are due to
+    /// rise, which is more than one if several intervals have passed.
+    pub fn tick(&mut self, elapsed: Duration) -> usize {
+        let mut elapsed = elapsed;
+        let mut rows = 0;
+        while elapsed >= self.until_next {
+            elapsed -= self.until_next;
+            rows += 1;
+            self.interval = self
+                .interval
+                .saturating_sub(self.speed_up)
+                .max(self.min_interval);
+            self.until_next = self.interval;
+        }
+        self.until_next -= elapsed;
+        return rows;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_garbage_timer_repeats() {
+        let mut timer = GarbageTimer::new(Duration::from_secs(2));
+        assert_eq!(timer.tick(Duration::from_secs(1)), 0);
+        assert_eq!(timer.until_next(), Duration::from_secs(1));
+        assert_eq!(timer.tick(Duration::from_secs(1)), 1);
+        assert_eq!(timer.until_next(), Duration::from_secs(2));
+        assert_eq!(timer.tick(Duration::from_secs(5)), 2);
+        assert_eq!(timer.until_next(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_accelerating_garbage_timer_speeds_up_to_its_limit() {
+        let mut timer = GarbageTimer::accelerating(
+            Duration::from_secs(4),
+            Duration::from_secs(1),
+            Duration::from_secs(2),
+        );
+        assert_eq!(timer.tick(Duration::from_secs(4)), 1);
+        assert_eq!(timer.until_next(), Duration::from_secs(3));
+        assert_eq!(timer.tick(Duration::from_secs(3)), 1);
+        assert_eq!(timer.until_next(), Duration::from_secs(2));
+        assert_eq!(timer.tick(Duration::from_secs(2)), 1);
+        assert_eq!(timer.until_next(), Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,83 @@
+use crate::board::TetrisBoard;
+
+/// How quickly the active piece falls: it is moved down `rows` rows every
+/// `interval` milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gravity {
+    pub interval: u64,
+    pub rows: i16,
+}
+impl Gravity {
+    /// Length of a frame at 60Hz in milliseconds, the shortest interval used.
+    /// Faster speeds are reached by moving more than one row per frame.
+    const FRAME: f64 = 1000.0 / 60.0;
+    /// Level at which pieces fall the full height of the board every frame.
+    const LEVEL_20G: u64 = 20;
+
+    /// Gravity for a level, following the guideline curve where the time
+    /// per row in seconds is `(0.8 - (level - 1) * 0.007) ^ (level - 1)`.
+    pub fn for_level(level: u64) -> Self {
+        if level >= Self::LEVEL_20G {
+            return Self::twenty_g();
+        }
+        let level = level.max(1) as f64;
+        let seconds_per_row = (0.8 - (level - 1.0) * 0.007).powf(level - 1.0);
+        let millis_per_row = seconds_per_row * 1000.0;
+        if millis_per_row >= Self::FRAME {
+            return Self {
+                interval: millis_per_row.round() as u64,
+                rows: 1,
+            };
+        }
+        let rows_per_frame = (Self::FRAME / millis_per_row).ceil() as i16;
+        Self {
+            interval: Self::FRAME.round() as u64,
+            rows: rows_per_frame.min(TetrisBoard::NUM_ROWS as i16),
+        }
+    }
+    /// Pieces drop straight to the stack as soon as they spawn.
+    pub fn twenty_g() -> Self {
+        Self {
+            interval: Self::FRAME.round() as u64,
+            rows: TetrisBoard::NUM_ROWS as i16,
+        }
+    }
+    pub fn is_20g(&self) -> bool {
+        self.rows >= TetrisBoard::NUM_ROWS as i16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_one_falls_a_row_a_second() {
+        assert_eq!(
+            Gravity::for_level(1),
+            Gravity {
+                interval: 1000,
+                rows: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_gravity_speeds_up_with_level() {
+        let mut previous = Gravity::for_level(1);
+        for level in 2..25 {
+            let gravity = Gravity::for_level(level);
+            let previous_speed = previous.rows as f64 / previous.interval as f64;
+            let speed = gravity.rows as f64 / gravity.interval as f64;
+            assert!(speed >= previous_speed, "level {} is slower", level);
+            previous = gravity;
+        }
+    }
+
+    #[test]
+    fn test_high_levels_reach_20g() {
+        assert!(!Gravity::for_level(10).is_20g());
+        assert!(Gravity::for_level(20).is_20g());
+        assert!(Gravity::for_level(30).is_20g());
+    }
+}
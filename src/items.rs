@@ -0,0 +1,136 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::VecDeque;
+
+use crate::board::TetrisBoard;
+
+/// Power-ups dropped by line clears in party mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Item {
+    /// Removes the bottom row of the stack.
+    ClearBottomRow,
+    /// Scatters the cells of each row across the row.
+    ShuffleStack,
+    /// Drops gravity to level 1 speed for a while.
+    SlowGravity,
+}
+impl Item {
+    pub const ALL: [Item; 3] = [Item::ClearBottomRow, Item::ShuffleStack, Item::SlowGravity];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Item::ClearBottomRow => "Clear Row",
+            Item::ShuffleStack => "Shuffle",
+            Item::SlowGravity => "Slow Down",
+        }
+    }
+}
+
+/// Items waiting to trigger, oldest first, and the effects of those that
+/// have. One item triggers each time a piece locks.
+#[derive(Debug, Default)]
+pub struct ItemQueue {
+    queue: VecDeque<Item>,
+    /// Pieces left to lock before slow gravity wears off.
+    slow_gravity_pieces: u64,
+}
+impl ItemQueue {
+    /// Chance of a line-clearing lock dropping an item.
+    const DROP_CHANCE: f64 = 0.3;
+    /// Number of pieces that fall slowly after slow gravity triggers.
+    const SLOW_GRAVITY_PIECES: u64 = 10;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn queued(&self) -> Vec<Item> {
+        self.queue.iter().copied().collect()
+    }
+    pub fn push(&mut self, item: Item) {
+        self.queue.push_back(item);
+    }
+    /// Has a chance of queuing a random item if the lock cleared rows.
+    /// Returns the item dropped, if any.
+    pub fn roll_drop(&mut self, rows_cleared: u16) -> Option<Item> {
+        let mut rng = rand::thread_rng();
+        if rows_cleared == 0 || !rng.gen_bool(Self::DROP_CHANCE) {
+            return None;
+        }
+        let item = *Item::ALL.choose(&mut rng).unwrap();
+        self.push(item);
+        return Some(item);
+    }
+    /// Called each time a piece locks. Wears down any slow gravity, then
+    /// triggers the oldest queued item on the board. Returns the item
+    /// triggered, if any.
+    pub fn trigger_next(&mut self, board: &mut TetrisBoard) -> Option<Item> {
+        self.slow_gravity_pieces = self.slow_gravity_pieces.saturating_sub(1);
+        let item = self.queue.pop_front()?;
+        match item {
+            Item::ClearBottomRow => board.remove_row(TetrisBoard::NUM_ROWS - 1),
+            Item::ShuffleStack => {
+                let mut rng = rand::thread_rng();
+                for row in board.board.iter_mut() {
+                    row.shuffle(&mut rng);
+                }
+            }
+            Item::SlowGravity => self.slow_gravity_pieces = Self::SLOW_GRAVITY_PIECES,
+        }
+        return Some(item);
+    }
+    pub fn is_slow_gravity(&self) -> bool {
+        self.slow_gravity_pieces > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_items_trigger_in_order_one_per_lock() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.board[19][0] = true;
+        tetris_board.board[18][1] = true;
+        let mut items = ItemQueue::new();
+        items.push(Item::ClearBottomRow);
+        items.push(Item::SlowGravity);
+        assert_eq!(
+            items.trigger_next(&mut tetris_board),
+            Some(Item::ClearBottomRow)
+        );
+        assert_eq!(tetris_board.occupied_rows(), vec![19]);
+        assert!(tetris_board.board[19][1]);
+        assert_eq!(items.queued(), vec![Item::SlowGravity]);
+        assert!(!items.is_slow_gravity());
+        items.trigger_next(&mut tetris_board);
+        assert!(items.is_slow_gravity());
+        for _ in 0..ItemQueue::SLOW_GRAVITY_PIECES {
+            assert_eq!(items.trigger_next(&mut tetris_board), None);
+        }
+        assert!(!items.is_slow_gravity());
+    }
+
+    #[test]
+    fn test_shuffle_keeps_cells_in_their_rows() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.board[19] = vec![true; TetrisBoard::NUM_COLS];
+        tetris_board.board[19][4] = false;
+        tetris_board.board[17][0] = true;
+        let mut items = ItemQueue::new();
+        items.push(Item::ShuffleStack);
+        items.trigger_next(&mut tetris_board);
+        assert_eq!(tetris_board.occupied_rows(), vec![17, 19]);
+        assert_eq!(tetris_board.board[19].iter().filter(|x| **x).count(), 9);
+        assert_eq!(tetris_board.board[17].iter().filter(|x| **x).count(), 1);
+    }
+
+    #[test]
+    fn test_items_only_drop_from_clears() {
+        let mut items = ItemQueue::new();
+        for _ in 0..50 {
+            assert_eq!(items.roll_drop(0), None);
+        }
+        assert!(items.queued().is_empty());
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::{Duration, Instant};
+
+use crate::models::{Command, KeyInput, KeyState};
+
+/// Sorts the terminal's own repeats of a held move key out from the keys
+/// pressed, as auto shift repeats moves itself, see `Engine`. Terminals that
+/// report key releases mark their repeats, which are dropped. Terminals that
+/// cannot report key releases only send taps, so there a key is taken to be
+/// held once the terminal starts repeating it, and let go once the repeats
+/// stop.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyRepeats {
+    /// The move key the terminal is repeating taps of.
+    held: Option<Command>,
+    /// The last move key tapped and when, to spot the terminal repeating
+    /// it.
+    last_tap: Option<(Command, Instant)>,
+}
+impl KeyRepeats {
+    /// Longest gap between taps of a key for them to be taken as the
+    /// terminal repeating it, which is quicker than it can be tapped.
+    pub const REPEAT_GAP: Duration = Duration::from_millis(100);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Notes a key input read at `now`, where `is_repeat` marks the
+    /// terminal's own repeats of a held key. Returns the input to pass on,
+    /// or `None` if it only repeats a held move key.
+    pub fn observe(&mut self, input: KeyInput, is_repeat: bool, now: Instant) -> Option<KeyInput> {
+        if !matches!(input.command, Command::MoveLeft | Command::MoveRight) {
+            return Some(input);
+        }
+        match input.state {
+            KeyState::Pressed if is_repeat => None,
+            KeyState::Tapped => self.observe_tap(input.command, now),
+            _other => Some(input),
+        }
+    }
+    /// Notes a tap, on a terminal that cannot report key releases. The
+    /// first of the terminal's repeats is passed on as a repeat, to start
+    /// auto shift without waiting out its delay again, as the terminal's
+    /// repeat delay stands in for it. The rest are swallowed.
+    fn observe_tap(&mut self, command: Command, now: Instant) -> Option<KeyInput> {
+        let repeats = matches!(
+            self.last_tap,
+            Some((tapped, at)) if tapped == command && now.duration_since(at) <= Self::REPEAT_GAP
+        );
+        self.last_tap = Some((command, now));
+        let state = match (repeats, self.held == Some(command)) {
+            (false, _not_held) => KeyState::Tapped,
+            (true, true) => return None,
+            (true, false) => {
+                self.held = Some(command);
+                KeyState::Repeated
+            }
+        };
+        Some(KeyInput { command, state })
+    }
+    /// Returns the release of the move key whose taps the terminal was
+    /// repeating, once they have stopped by `now`.
+    pub fn let_go(&mut self, now: Instant) -> Option<KeyInput> {
+        let (_, at) = self.last_tap?;
+        if now.duration_since(at) <= Self::REPEAT_GAP {
+            return None;
+        }
+        self.last_tap = None;
+        self.held.take().map(|command| KeyInput {
+            command,
+            state: KeyState::Released,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(command: Command, state: KeyState) -> KeyInput {
+        KeyInput { command, state }
+    }
+
+    #[test]
+    fn test_terminal_repeats_of_held_moves_are_dropped() {
+        let mut key_repeats = KeyRepeats::new();
+        let now = Instant::now();
+        let press = input(Command::MoveLeft, KeyState::Pressed);
+        let release = input(Command::MoveLeft, KeyState::Released);
+        assert_eq!(key_repeats.observe(press, false, now), Some(press));
+        assert_eq!(key_repeats.observe(press, true, now), None);
+        assert_eq!(key_repeats.observe(release, false, now), Some(release));
+        let soft_drop = input(Command::MoveDown, KeyState::Pressed);
+        assert_eq!(key_repeats.observe(soft_drop, true, now), Some(soft_drop));
+    }
+
+    #[test]
+    fn test_terminal_repeats_of_taps_hold_the_key_until_they_stop() {
+        let mut key_repeats = KeyRepeats::new();
+        let start = Instant::now();
+        let after = |millis: u64| start + Duration::from_millis(millis);
+        let tap = input(Command::MoveRight, KeyState::Tapped);
+        assert_eq!(key_repeats.observe(tap, false, start), Some(tap));
+        assert_eq!(key_repeats.observe(tap, false, after(500)), Some(tap));
+        assert_eq!(key_repeats.let_go(after(505)), None);
+        assert_eq!(
+            key_repeats.observe(tap, false, after(530)),
+            Some(input(Command::MoveRight, KeyState::Repeated))
+        );
+        assert_eq!(key_repeats.observe(tap, false, after(560)), None);
+        assert_eq!(key_repeats.let_go(after(600)), None);
+        assert_eq!(
+            key_repeats.let_go(after(661)),
+            Some(input(Command::MoveRight, KeyState::Released))
+        );
+        assert_eq!(key_repeats.let_go(after(700)), None);
+        assert_eq!(key_repeats.observe(tap, false, after(700)), Some(tap));
+    }
+
+    #[test]
+    fn test_taps_of_other_keys_pass_through() {
+        let mut key_repeats = KeyRepeats::new();
+        let now = Instant::now();
+        let tap = input(Command::RotateClockwise, KeyState::Tapped);
+        assert_eq!(key_repeats.observe(tap, false, now), Some(tap));
+        assert_eq!(key_repeats.observe(tap, false, now), Some(tap));
+        assert_eq!(key_repeats.let_go(now + Duration::from_secs(1)), None);
+    }
+}
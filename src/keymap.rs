@@ -0,0 +1,433 @@
+use crossterm::event::KeyCode;
+
+use crate::config::{Config, ConfigError};
+use crate::models::Command;
+
+/// Which keys trigger each command in game. Commands are bound in the
+/// `[keys]` section of the config by name, e.g. `rotate_clockwise = "x up"`,
+/// with several keys separated by spaces. A key is a single character or
+/// one of `left`, `right`, `up`, `down`, `space`, `enter`, `tab`,
+/// `backspace` and `esc`. Commands left out keep the keys of the preset
+/// named by `preset`, except for any keys the config binds to other
+/// commands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    bindings: Vec<(KeyCode, Command)>,
+}
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: vec![
+                (KeyCode::Left, Command::MoveLeft),
+                (KeyCode::Right, Command::MoveRight),
+                (KeyCode::Down, Command::MoveDown),
+                (KeyCode::Char(' '), Command::HardDrop),
+                (KeyCode::Up, Command::SonicDrop),
+                (KeyCode::Char('x'), Command::RotateClockwise),
+                (KeyCode::Char('z'), Command::RotateAnticlockwise),
+                (KeyCode::Char('a'), Command::Rotate180),
+                (KeyCode::Char('c'), Command::Hold),
+                (KeyCode::Char('v'), Command::ActivateZone),
+                (KeyCode::Char('p'), Command::Pause),
+                (KeyCode::Esc, Command::Pause),
+                (KeyCode::Char('q'), Command::Quit),
+                (KeyCode::Char('r'), Command::Restart),
+            ],
+        }
+    }
+}
+impl Keymap {
+    /// Name of each command in the config.
+    pub const COMMANDS: [(&'static str, Command); 13] = [
+        ("move_left", Command::MoveLeft),
+        ("move_right", Command::MoveRight),
+        ("soft_drop", Command::MoveDown),
+        ("hard_drop", Command::HardDrop),
+        ("sonic_drop", Command::SonicDrop),
+        ("rotate_clockwise", Command::RotateClockwise),
+        ("rotate_anticlockwise", Command::RotateAnticlockwise),
+        ("rotate_180", Command::Rotate180),
+        ("hold", Command::Hold),
+        ("zone", Command::ActivateZone),
+        ("pause", Command::Pause),
+        ("quit", Command::Quit),
+        ("restart", Command::Restart),
+    ];
+
+    /// The keys of a preset, before any config changes.
+    pub fn preset(preset: KeymapPreset) -> Self {
+        let mut keymap = Self::default();
+        let mut rebound = Vec::new();
+        for (key_code, command) in preset.keys {
+            match rebound.contains(command) {
+                true => keymap.add_key(*command, *key_code),
+                false => keymap.bind(*command, *key_code),
+            };
+            rebound.push(*command);
+        }
+        keymap
+    }
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let mut preset = KeymapPreset::DEFAULT;
+        config.read_choice("keys.preset", &KeymapPreset::choices(), &mut preset)?;
+        let mut configured: Vec<(KeyCode, Command)> = Vec::new();
+        let mut configured_commands = Vec::new();
+        for (name, command) in Self::COMMANDS {
+            let key = format!("keys.{}", name);
+            if !config.contains(&key) {
+                continue;
+            }
+            let mut key_names = String::new();
+            config.read(&key, &mut key_names)?;
+            for key_name in key_names.split_whitespace() {
+                let key_code = parse_key(key_name).ok_or_else(|| {
+                    config.error_at(&key, &format!("unknown key `{}` for `{}`", key_name, key))
+                })?;
+                if let Some((_, other)) = configured.iter().find(|(bound, _)| *bound == key_code) {
+                    return Err(config.error_at(
+                        &key,
+                        &format!(
+                            "`{}` is bound to both `{}` and `{}`",
+                            key_name,
+                            command_name(*other),
+                            name
+                        ),
+                    ));
+                }
+                configured.push((key_code, command));
+            }
+            configured_commands.push(command);
+        }
+        let mut keymap = Self::preset(preset);
+        keymap.bindings.retain(|(key_code, command)| {
+            !configured_commands.contains(command)
+                && !configured.iter().any(|(bound, _)| bound == key_code)
+        });
+        keymap.bindings.extend(configured);
+        Ok(keymap)
+    }
+    /// The command bound to a key, if any.
+    pub fn command(&self, key_code: KeyCode) -> Option<Command> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == key_code)
+            .map(|(_, command)| *command)
+    }
+    /// Every key bound to a command.
+    pub fn keys(&self, command: Command) -> Vec<KeyCode> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == command)
+            .map(|(key_code, _)| *key_code)
+            .collect()
+    }
+    /// The keys bound to a command as they are written in the config.
+    pub fn key_names(&self, command: Command) -> String {
+        let names: Vec<String> = self
+            .keys(command)
+            .into_iter()
+            .filter_map(key_name)
+            .collect();
+        names.join(" ")
+    }
+    /// Makes `key_code` the only key for `command`, taking it from any
+    /// command it was bound to. Returns the commands whose keys changed.
+    pub fn bind(&mut self, command: Command, key_code: KeyCode) -> Vec<Command> {
+        self.bindings.retain(|(_, bound)| *bound != command);
+        self.add_key(command, key_code)
+    }
+    /// Binds `key_code` to `command` alongside its other keys, taking it
+    /// from any command it was bound to. Returns the commands whose keys
+    /// changed.
+    pub fn add_key(&mut self, command: Command, key_code: KeyCode) -> Vec<Command> {
+        let mut changed = vec![command];
+        if let Some(other) = self.command(key_code) {
+            if other != command {
+                changed.push(other);
+            }
+        }
+        self.bindings.retain(|(bound, _)| *bound != key_code);
+        self.bindings.push((key_code, command));
+        changed
+    }
+}
+
+/// A set of keys to start from: the default keys, with the commands listed
+/// bound to the keys listed instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeymapPreset {
+    pub name: &'static str,
+    keys: &'static [(KeyCode, Command)],
+}
+impl KeymapPreset {
+    /// Arrows to move, `x` and `z` to rotate.
+    pub const DEFAULT: KeymapPreset = KeymapPreset {
+        name: "default",
+        keys: &[],
+    };
+    /// `h` and `l` to move, `j` to soft drop and `k` to rotate clockwise,
+    /// as in vim.
+    pub const VIM: KeymapPreset = KeymapPreset {
+        name: "vim",
+        keys: &[
+            (KeyCode::Char('h'), Command::MoveLeft),
+            (KeyCode::Char('l'), Command::MoveRight),
+            (KeyCode::Char('j'), Command::MoveDown),
+            (KeyCode::Char('k'), Command::RotateClockwise),
+        ],
+    };
+    /// `a` and `d` to move, `s` to soft drop, `w` to hard drop and `j`, `k`
+    /// and `l` to rotate, for the left hand on WASD.
+    pub const WASD: KeymapPreset = KeymapPreset {
+        name: "wasd",
+        keys: &[
+            (KeyCode::Char('a'), Command::MoveLeft),
+            (KeyCode::Char('d'), Command::MoveRight),
+            (KeyCode::Char('s'), Command::MoveDown),
+            (KeyCode::Char('w'), Command::HardDrop),
+            (KeyCode::Char(' '), Command::HardDrop),
+            (KeyCode::Char('j'), Command::RotateAnticlockwise),
+            (KeyCode::Char('k'), Command::RotateClockwise),
+            (KeyCode::Char('l'), Command::Rotate180),
+        ],
+    };
+    /// Everything under the left hand: `a` and `d` to move, `s` to soft
+    /// drop, `w` to hard drop, `x` to sonic drop, `q`, `e` and `f` to
+    /// rotate, with `1` to quit.
+    pub const LEFT_HAND: KeymapPreset = KeymapPreset {
+        name: "left-hand",
+        keys: &[
+            (KeyCode::Char('a'), Command::MoveLeft),
+            (KeyCode::Char('d'), Command::MoveRight),
+            (KeyCode::Char('s'), Command::MoveDown),
+            (KeyCode::Char('w'), Command::HardDrop),
+            (KeyCode::Char('x'), Command::SonicDrop),
+            (KeyCode::Char('q'), Command::RotateAnticlockwise),
+            (KeyCode::Char('e'), Command::RotateClockwise),
+            (KeyCode::Char('f'), Command::Rotate180),
+            (KeyCode::Char('1'), Command::Quit),
+        ],
+    };
+    /// Everything under the right hand: `j` and `l` to move, `k` to soft
+    /// drop, `i` to hard drop, `m` to sonic drop, `u`, `o` and `y` to
+    /// rotate, `h` to hold and `n` for the zone, with `0` to quit and `9` to
+    /// restart.
+    pub const RIGHT_HAND: KeymapPreset = KeymapPreset {
+        name: "right-hand",
+        keys: &[
+            (KeyCode::Char('j'), Command::MoveLeft),
+            (KeyCode::Char('l'), Command::MoveRight),
+            (KeyCode::Char('k'), Command::MoveDown),
+            (KeyCode::Char('i'), Command::HardDrop),
+            (KeyCode::Char('m'), Command::SonicDrop),
+            (KeyCode::Char('u'), Command::RotateAnticlockwise),
+            (KeyCode::Char('o'), Command::RotateClockwise),
+            (KeyCode::Char('y'), Command::Rotate180),
+            (KeyCode::Char('h'), Command::Hold),
+            (KeyCode::Char('n'), Command::ActivateZone),
+            (KeyCode::Char('0'), Command::Quit),
+            (KeyCode::Char('9'), Command::Restart),
+        ],
+    };
+    pub const ALL: [KeymapPreset; 5] = [
+        Self::DEFAULT,
+        Self::VIM,
+        Self::WASD,
+        Self::LEFT_HAND,
+        Self::RIGHT_HAND,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name == name)
+    }
+    /// Name and preset of every preset, for reading one from the config.
+    pub fn choices() -> Vec<(&'static str, Self)> {
+        Self::ALL
+            .iter()
+            .map(|preset| (preset.name, *preset))
+            .collect()
+    }
+}
+
+/// Name of a command in the config.
+fn command_name(command: Command) -> &'static str {
+    Keymap::COMMANDS
+        .iter()
+        .find(|(_, named)| *named == command)
+        .map_or("", |(name, _)| *name)
+}
+
+/// Named keys that are not a single character.
+const KEY_NAMES: [(&str, KeyCode); 9] = [
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("space", KeyCode::Char(' ')),
+    ("enter", KeyCode::Enter),
+    ("tab", KeyCode::Tab),
+    ("backspace", KeyCode::Backspace),
+    ("esc", KeyCode::Esc),
+];
+
+/// Reads a key from its name in the config.
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    if let Some((_, key_code)) = KEY_NAMES.iter().find(|(key_name, _)| *key_name == name) {
+        return Some(*key_code);
+    }
+    let mut characters = name.chars();
+    match (characters.next(), characters.next()) {
+        (Some(character), None) => Some(KeyCode::Char(character)),
+        _other => None,
+    }
+}
+
+/// Name of a key in the config, if it can be bound.
+pub fn key_name(key_code: KeyCode) -> Option<String> {
+    if let Some((name, _)) = KEY_NAMES.iter().find(|(_, named)| *named == key_code) {
+        return Some(name.to_string());
+    }
+    match key_code {
+        KeyCode::Char('"') => None,
+        KeyCode::Char(character) => Some(character.to_string()),
+        _other => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_keys() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.command(KeyCode::Left), Some(Command::MoveLeft));
+        assert_eq!(
+            keymap.command(KeyCode::Char('x')),
+            Some(Command::RotateClockwise)
+        );
+        assert_eq!(keymap.command(KeyCode::Esc), Some(Command::Pause));
+        assert_eq!(keymap.command(KeyCode::Char('q')), Some(Command::Quit));
+        assert_eq!(keymap.command(KeyCode::Char('k')), None);
+    }
+
+    #[test]
+    fn test_config_rebinds_commands() {
+        let config =
+            Config::parse("[keys]\nrotate_clockwise = \"up z\"\nhard_drop = \"k\"").unwrap();
+        let keymap = Keymap::from_config(&config).unwrap();
+        assert_eq!(keymap.command(KeyCode::Up), Some(Command::RotateClockwise));
+        assert_eq!(
+            keymap.command(KeyCode::Char('z')),
+            Some(Command::RotateClockwise)
+        );
+        assert_eq!(keymap.command(KeyCode::Char('x')), None);
+        assert_eq!(keymap.command(KeyCode::Char('k')), Some(Command::HardDrop));
+        assert_eq!(keymap.command(KeyCode::Char(' ')), None);
+        assert!(keymap.keys(Command::RotateAnticlockwise).is_empty());
+        assert_eq!(keymap.keys(Command::MoveLeft), vec![KeyCode::Left]);
+    }
+
+    #[test]
+    fn test_vim_preset() {
+        let config = Config::parse("[keys]\npreset = \"vim\"\nhold = \"h\"").unwrap();
+        let keymap = Keymap::from_config(&config).unwrap();
+        assert_eq!(keymap.command(KeyCode::Char('h')), Some(Command::Hold));
+        assert!(keymap.keys(Command::MoveLeft).is_empty());
+        assert_eq!(keymap.command(KeyCode::Char('l')), Some(Command::MoveRight));
+        assert_eq!(keymap.command(KeyCode::Char('j')), Some(Command::MoveDown));
+        assert_eq!(
+            keymap.keys(Command::RotateClockwise),
+            vec![KeyCode::Char('k')]
+        );
+        assert_eq!(keymap.command(KeyCode::Char(' ')), Some(Command::HardDrop));
+        assert_eq!(keymap.command(KeyCode::Left), None);
+        let config = Config::parse("[keys]\npreset = \"emacs\"").unwrap();
+        assert!(Keymap::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_wasd_preset() {
+        let keymap = Keymap::preset(KeymapPreset::WASD);
+        assert_eq!(keymap.command(KeyCode::Char('a')), Some(Command::MoveLeft));
+        assert_eq!(keymap.command(KeyCode::Char('s')), Some(Command::MoveDown));
+        assert_eq!(keymap.key_names(Command::HardDrop), "w space");
+        assert_eq!(
+            keymap.command(KeyCode::Char('j')),
+            Some(Command::RotateAnticlockwise)
+        );
+        assert_eq!(keymap.keys(Command::Rotate180), vec![KeyCode::Char('l')]);
+        assert_eq!(keymap.command(KeyCode::Char('c')), Some(Command::Hold));
+        assert_eq!(keymap.command(KeyCode::Left), None);
+    }
+
+    #[test]
+    fn test_one_hand_presets_bind_every_command() {
+        for preset in [KeymapPreset::LEFT_HAND, KeymapPreset::RIGHT_HAND] {
+            let keymap = Keymap::preset(preset);
+            for (name, command) in Keymap::COMMANDS {
+                assert!(!keymap.keys(command).is_empty(), "{} {}", preset.name, name);
+            }
+        }
+        let left_hand = Keymap::preset(KeymapPreset::LEFT_HAND);
+        assert_eq!(
+            left_hand.command(KeyCode::Char('q')),
+            Some(Command::RotateAnticlockwise)
+        );
+        assert_eq!(left_hand.key_names(Command::Quit), "1");
+        let right_hand = Keymap::preset(KeymapPreset::RIGHT_HAND);
+        assert_eq!(right_hand.command(KeyCode::Char('h')), Some(Command::Hold));
+        assert_eq!(right_hand.command(KeyCode::Char('c')), None);
+    }
+
+    #[test]
+    fn test_unknown_keys_are_errors() {
+        let config = Config::parse("[keys]\nhold = \"shift\"").unwrap();
+        assert!(Keymap::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_keys_bound_twice_are_errors() {
+        let config = Config::parse("[keys]\nhold = \"c\"\nhard_drop = \"c\"").unwrap();
+        assert!(Keymap::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_key_names_round_trip() {
+        for name in ["left", "space", "esc", "k", "/"] {
+            assert_eq!(key_name(parse_key(name).unwrap()).as_deref(), Some(name));
+        }
+        assert_eq!(parse_key("shift"), None);
+        assert_eq!(key_name(KeyCode::Char('"')), None);
+    }
+
+    #[test]
+    fn test_binding_a_key_takes_it_from_other_commands() {
+        let mut keymap = Keymap::default();
+        let changed = keymap.bind(Command::Hold, KeyCode::Char('x'));
+        assert_eq!(changed, vec![Command::Hold, Command::RotateClockwise]);
+        assert_eq!(keymap.command(KeyCode::Char('x')), Some(Command::Hold));
+        assert_eq!(keymap.command(KeyCode::Char('c')), None);
+        assert_eq!(keymap.key_names(Command::RotateClockwise), "");
+        assert_eq!(
+            keymap.bind(Command::HardDrop, KeyCode::Char(' ')),
+            vec![Command::HardDrop]
+        );
+        assert_eq!(keymap.key_names(Command::HardDrop), "space");
+    }
+
+    #[test]
+    fn test_commands_can_have_several_keys() {
+        let mut keymap = Keymap::default();
+        keymap.bind(Command::RotateClockwise, KeyCode::Char('x'));
+        let changed = keymap.add_key(Command::RotateClockwise, KeyCode::Up);
+        assert_eq!(changed, vec![Command::RotateClockwise, Command::SonicDrop]);
+        assert_eq!(keymap.key_names(Command::RotateClockwise), "x up");
+        assert_eq!(keymap.command(KeyCode::Up), Some(Command::RotateClockwise));
+        assert_eq!(
+            keymap.command(KeyCode::Char('x')),
+            Some(Command::RotateClockwise)
+        );
+        assert!(keymap.keys(Command::SonicDrop).is_empty());
+    }
+}
@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+/// Delays between reading a key and applying its command to the board, to
+/// tune the input poll interval and handling against.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyStats {
+    samples: Vec<Duration>,
+}
+impl LatencyStats {
+    pub fn record(&mut self, latency: Duration) {
+        self.samples.push(latency);
+    }
+    /// The latency `percent` of inputs were applied within, or `None` before
+    /// any input.
+    pub fn percentile(&self, percent: u64) -> Option<Duration> {
+        let mut sorted = self.samples.clone();
+        sorted.sort();
+        let last = sorted.len().checked_sub(1)?;
+        let index = (last as u64 * percent.min(100) + 50) / 100;
+        sorted.get(index as usize).copied()
+    }
+}
+
+/// Formats a latency in milliseconds, e.g. `1.4 ms`.
+pub fn format_latency(latency: Duration) -> String {
+    format!("{:.1} ms", latency.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let mut stats = LatencyStats::default();
+        assert_eq!(stats.percentile(50), None);
+        for millis in (1..=100).rev() {
+            stats.record(Duration::from_millis(millis));
+        }
+        assert_eq!(stats.percentile(50), Some(Duration::from_millis(51)));
+        assert_eq!(stats.percentile(99), Some(Duration::from_millis(99)));
+        assert_eq!(stats.percentile(100), Some(Duration::from_millis(100)));
+        assert_eq!(format_latency(Duration::from_micros(1400)), "1.4 ms");
+    }
+}
@@ -0,0 +1,113 @@
+use crossterm::cursor::MoveTo;
+
+/// Where each part of the screen is drawn, worked out from the size of the
+/// terminal so that the playfield sits in the middle of it. From left to
+/// right are the box of the held piece, the board, the box of pieces to
+/// come, the side panel of the score, the HUD and menus, and then the input
+/// display if it is shown. Positions are given relative to each part and
+/// turned into places on the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    /// Column of the left edge of the box of the held piece.
+    left: u16,
+    /// Row of the top of the board.
+    top: u16,
+}
+impl Default for Layout {
+    fn default() -> Self {
+        Self::for_size(Self::WIDTH, Self::HEIGHT, false)
+    }
+}
+impl Layout {
+    /// Columns the board and the panels beside it need, up to the end of
+    /// the score panel.
+    pub const WIDTH: u16 = Self::SIDE_COL + Self::SIDE_WIDTH;
+    /// Rows the board and the lines of the mode below the panels need.
+    pub const HEIGHT: u16 = 24;
+    /// Column the left wall of the board is drawn in, right of the box of
+    /// the held piece.
+    const BOARD_COL: u16 = 8;
+    /// Column the box of pieces to come is drawn in, right of the board.
+    const NEXT_COL: u16 = 21;
+    /// Column the score, menus and other text beside the board start in.
+    const SIDE_COL: u16 = 29;
+    /// Columns of the side panel.
+    const SIDE_WIDTH: u16 = 26;
+    /// Columns of the input display, right of the side panel.
+    pub const INPUT_DISPLAY_WIDTH: u16 = 20;
+
+    /// Columns needed, with room for the input display if it is shown.
+    pub fn width(input_display: bool) -> u16 {
+        match input_display {
+            true => Self::WIDTH + Self::INPUT_DISPLAY_WIDTH,
+            false => Self::WIDTH,
+        }
+    }
+    /// The layout for a terminal `cols` wide and `rows` tall, with the
+    /// input display if it is shown. A terminal too small to fit everything
+    /// has it drawn from the top left corner.
+    pub fn for_size(cols: u16, rows: u16, input_display: bool) -> Self {
+        Self {
+            left: cols.saturating_sub(Self::width(input_display)) / 2,
+            top: rows.saturating_sub(Self::HEIGHT) / 2,
+        }
+    }
+    /// Whether the board and the panels beside it, with the input display if
+    /// it is shown, fit in a terminal `cols` wide and `rows` tall.
+    pub fn fits(cols: u16, rows: u16, input_display: bool) -> bool {
+        cols >= Self::width(input_display) && rows >= Self::HEIGHT
+    }
+    /// Screen column of the left wall of the board.
+    pub fn board_col(&self) -> u16 {
+        self.left + Self::BOARD_COL
+    }
+    /// `col` and `row` of the box of the held piece.
+    pub fn hold(&self, col: u16, row: u16) -> MoveTo {
+        MoveTo(self.left + col, self.top + row)
+    }
+    /// `col` and `row` of the board, counting its left wall as column 0.
+    pub fn board(&self, col: u16, row: u16) -> MoveTo {
+        MoveTo(self.board_col() + col, self.top + row)
+    }
+    /// `col` and `row` of the box of pieces to come.
+    pub fn next(&self, col: u16, row: u16) -> MoveTo {
+        MoveTo(self.left + Self::NEXT_COL + col, self.top + row)
+    }
+    /// `col` and `row` of the side panel.
+    pub fn side(&self, col: u16, row: u16) -> MoveTo {
+        MoveTo(self.left + Self::SIDE_COL + col, self.top + row)
+    }
+    /// `row` of the input display.
+    pub fn input_display(&self, row: u16) -> MoveTo {
+        self.side(Self::SIDE_WIDTH, row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playfield_centred_in_the_terminal() {
+        let layout = Layout::for_size(Layout::WIDTH + 20, Layout::HEIGHT + 7, false);
+        assert_eq!(layout.hold(0, 0), MoveTo(10, 3));
+        assert_eq!(layout.board(1, 2), MoveTo(19, 5));
+        assert_eq!(layout.next(0, 0), MoveTo(31, 3));
+        assert_eq!(layout.side(2, 20), MoveTo(41, 23));
+        assert_eq!(Layout::default().board(0, 0), MoveTo(8, 0));
+        assert_eq!(Layout::for_size(40, 20, false), Layout::default());
+        assert!(!Layout::fits(40, 20, false));
+        assert!(Layout::fits(Layout::WIDTH, Layout::HEIGHT, false));
+    }
+
+    #[test]
+    fn test_input_display_widens_the_layout() {
+        let width = Layout::width(true);
+        assert!(!Layout::fits(Layout::WIDTH, Layout::HEIGHT, true));
+        assert!(Layout::fits(width, Layout::HEIGHT, true));
+        let layout = Layout::for_size(width, Layout::HEIGHT, true);
+        assert_eq!(layout, Layout::default());
+        let MoveTo(col, _) = layout.input_display(0);
+        assert_eq!(col + Layout::INPUT_DISPLAY_WIDTH, width);
+    }
+}
@@ -0,0 +1,213 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Fastest times for timed modes and highest counts for modes played
+/// against the clock, kept in a file between games. Each line of the file is
+/// a category, e.g. `cheese-10`, followed by a time in milliseconds or a
+/// count.
+#[derive(Debug, Default, PartialEq)]
+pub struct Leaderboard {
+    entries: Vec<(String, u64)>,
+}
+impl Leaderboard {
+    /// Number of times kept for each category.
+    const MAX_ENTRIES: usize = 10;
+
+    /// Reads the leaderboard at `path`. A missing file is an empty
+    /// leaderboard, and lines that cannot be read are skipped.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(error),
+        };
+        let entries = text
+            .lines()
+            .filter_map(|line| {
+                let (category, value) = line.trim().rsplit_once(' ')?;
+                Some((category.to_string(), value.parse().ok()?))
+            })
+            .collect();
+        Ok(Self { entries })
+    }
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text: String = self
+            .entries
+            .iter()
+            .map(|(category, value)| format!("{} {}\n", category, value))
+            .collect();
+        fs::write(path, text)
+    }
+    /// `tetris-cli/leaderboard.txt` in the user's data directory, following
+    /// `XDG_DATA_HOME` and falling back to `~/.local/share`.
+    pub fn default_path() -> Option<PathBuf> {
+        let data_dir = match env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _other => PathBuf::from(env::var_os("HOME")?)
+                .join(".local")
+                .join("share"),
+        };
+        Some(data_dir.join("tetris-cli").join("leaderboard.txt"))
+    }
+    /// Times recorded in a category, fastest first.
+    pub fn times(&self, category: &str) -> Vec<Duration> {
+        self.values(category)
+            .into_iter()
+            .map(Duration::from_millis)
+            .collect()
+    }
+    /// Counts recorded in a category, highest first.
+    pub fn counts(&self, category: &str) -> Vec<u64> {
+        let mut counts = self.values(category);
+        counts.reverse();
+        return counts;
+    }
+    /// Adds a time to a category, keeping only the fastest. Returns its
+    /// place in the category, starting from 1, or `None` if it was too slow
+    /// to be kept.
+    pub fn record(&mut self, category: &str, time: Duration) -> Option<usize> {
+        let mut times = self.values(category);
+        let millis = time.as_millis() as u64;
+        let place = times.partition_point(|recorded| *recorded <= millis);
+        times.insert(place, millis);
+        self.keep(category, times, place)
+    }
+    /// Adds a count to a category, keeping only the highest. Returns its
+    /// place in the category, starting from 1, or `None` if it was too low
+    /// to be kept.
+    pub fn record_count(&mut self, category: &str, count: u64) -> Option<usize> {
+        let mut counts = self.counts(category);
+        let place = counts.partition_point(|recorded| *recorded >= count);
+        counts.insert(place, count);
+        self.keep(category, counts, place)
+    }
+    /// Values recorded in a category, lowest first.
+    fn values(&self, category: &str) -> Vec<u64> {
+        let mut values: Vec<u64> = self
+            .entries
+            .iter()
+            .filter(|(entry_category, _)| entry_category == category)
+            .map(|(_, value)| *value)
+            .collect();
+        values.sort();
+        return values;
+    }
+    /// Replaces a category's values with the best of `ranked`, in which a
+    /// new value was inserted at `place`. Returns the new value's place,
+    /// starting from 1, or `None` if it was not kept.
+    fn keep(&mut self, category: &str, mut ranked: Vec<u64>, place: usize) -> Option<usize> {
+        if place >= Self::MAX_ENTRIES {
+            return None;
+        }
+        ranked.truncate(Self::MAX_ENTRIES);
+        self.entries
+            .retain(|(entry_category, _)| entry_category != category);
+        self.entries.extend(
+            ranked
+                .into_iter()
+                .map(|value| (category.to_string(), value)),
+        );
+        return Some(place + 1);
+    }
+}
+
+/// Formats a time as minutes, seconds and milliseconds, e.g. `1:02.345`.
+pub fn format_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_times_are_ranked_fastest_first() {
+        let mut leaderboard = Leaderboard::default();
+        assert_eq!(
+            leaderboard.record("cheese-10", Duration::from_secs(60)),
+            Some(1)
+        );
+        assert_eq!(
+            leaderboard.record("cheese-10", Duration::from_secs(30)),
+            Some(1)
+        );
+        assert_eq!(
+            leaderboard.record("cheese-10", Duration::from_secs(45)),
+            Some(2)
+        );
+        assert_eq!(
+            leaderboard.record("cheese-5", Duration::from_secs(90)),
+            Some(1)
+        );
+        assert_eq!(
+            leaderboard.times("cheese-10"),
+            vec![
+                Duration::from_secs(30),
+                Duration::from_secs(45),
+                Duration::from_secs(60)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_only_the_fastest_times_are_kept() {
+        let mut leaderboard = Leaderboard::default();
+        for seconds in 1..=Leaderboard::MAX_ENTRIES as u64 {
+            leaderboard.record("cheese-10", Duration::from_secs(seconds));
+        }
+        assert_eq!(
+            leaderboard.record("cheese-10", Duration::from_secs(99)),
+            None
+        );
+        assert_eq!(
+            leaderboard.record("cheese-10", Duration::from_millis(500)),
+            Some(1)
+        );
+        assert_eq!(
+            leaderboard.times("cheese-10").len(),
+            Leaderboard::MAX_ENTRIES
+        );
+    }
+
+    #[test]
+    fn test_counts_are_ranked_highest_first() {
+        let mut leaderboard = Leaderboard::default();
+        assert_eq!(leaderboard.record_count("dig-race-2m", 12), Some(1));
+        assert_eq!(leaderboard.record_count("dig-race-2m", 20), Some(1));
+        assert_eq!(leaderboard.record_count("dig-race-2m", 15), Some(2));
+        assert_eq!(leaderboard.counts("dig-race-2m"), vec![20, 15, 12]);
+        for _ in 0..Leaderboard::MAX_ENTRIES {
+            leaderboard.record_count("dig-race-2m", 30);
+        }
+        assert_eq!(leaderboard.record_count("dig-race-2m", 25), None);
+    }
+
+    #[test]
+    fn test_leaderboard_round_trips_through_a_file() {
+        let path = env::temp_dir().join(format!("tetris-leaderboard-{}.txt", std::process::id()));
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.record("cheese-10", Duration::from_millis(61_234));
+        leaderboard.save(&path).unwrap();
+        assert_eq!(Leaderboard::load(&path).unwrap(), leaderboard);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Leaderboard::load(&path).unwrap(), Leaderboard::default());
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(Duration::from_millis(62_345)), "1:02.345");
+        assert_eq!(format_time(Duration::from_millis(999)), "0:00.999");
+    }
+}
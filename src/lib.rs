@@ -1,4 +1,35 @@
+pub mod board;
+pub mod cli;
+pub mod clock;
+pub mod config;
+pub mod daily;
+pub mod double_tap;
+pub mod engine;
+pub mod finesse;
+pub mod game;
+pub mod garbage_timer;
+pub mod gravity;
+pub mod items;
+pub mod key_repeat;
+pub mod keymap;
+pub mod latency;
+pub mod layout;
+pub mod leaderboard;
+pub mod mode;
 pub mod models;
+pub mod piece;
+pub mod puzzle;
+pub mod randomizer;
+pub mod recording;
+pub mod renderer;
+pub mod rotation;
+pub mod scheduler;
+pub mod score;
+pub mod settings;
+pub mod spin;
+pub mod supervisor;
+pub mod theme;
 pub mod turn_timer;
 pub mod ui;
 pub mod views;
+pub mod zone;
@@ -1,121 +1,895 @@
-use log;
-use std::cmp;
+use std::env;
 use std::fmt;
 use std::io;
-use std::sync::mpsc;
-use std::thread;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
 
-use tetris::models::{Command, PiecePositionValidity, TetrisBoard, TetrisPiece, TurnEvent};
-use tetris::turn_timer::turn_timer::{
-    Notifier, TimerStatus, TurnTimer, TurnTimerSubscriber, TurnTimerSubscriberTrait,
+use crossterm::event::KeyCode;
+use crossterm::terminal;
+use tetris::board::TetrisBoard;
+use tetris::cli::CliArgs;
+use tetris::config::{Config, ConfigError};
+use tetris::daily::DailyChallenge;
+use tetris::engine::{Engine, FrameSchedule};
+use tetris::game::{Exit, GameState};
+use tetris::keymap::{key_name, Keymap};
+use tetris::latency::{format_latency, LatencyStats};
+use tetris::leaderboard::{format_time, Leaderboard};
+use tetris::mode::GameMode;
+use tetris::models::{Command, Coord, KeyInput, KeyState};
+use tetris::puzzle::Puzzle;
+use tetris::recording::{Recorder, Recording};
+use tetris::renderer::Renderer;
+use tetris::settings::GameSettings;
+use tetris::supervisor;
+use tetris::theme::Theme;
+use tetris::turn_timer::stopwatch::Stopwatch;
+use tetris::ui::{
+    catch_interrupts, coalesce_inputs, read_editor_input, read_frame_inputs, read_key,
+    read_menu_input, wait_discarding_input, wait_for_resize, CliCommandCollector, CommandCollector,
+    EditorInput, MouseCapture, PlaybackCommandCollector, ScopedRawMode,
 };
-use tetris::ui::{timed_user_input, CliCommandCollector};
-use tetris::views::CliView;
+use tetris::views::{needs_ascii, CliView};
+
+/// Time each number of the pre-game countdown is shown for.
+const COUNTDOWN_STEP: Duration = Duration::from_millis(800);
 
 fn main() {
-    println!("Game Over! Score: {}", game_runner());
+    let cli_args = match CliArgs::parse(env::args().skip(1)) {
+        Ok(cli_args) => cli_args,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(2);
+        }
+    };
+    let (mut settings, theme, mut keymap) = match load_config() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
+    if let Some(piece_set) = cli_args.piece_set {
+        settings.piece_set = piece_set;
+    }
+    if let Some(preset) = cli_args.keymap {
+        keymap = Keymap::preset(preset);
+    }
+    if cli_args.mouse {
+        settings.mouse = true;
+    }
+    if cli_args.ascii || needs_ascii(|name| env::var(name).ok()) {
+        settings.ascii = true;
+    }
+    let mut mode = cli_args.mode;
+    if let Some(path) = &cli_args.puzzle {
+        match Puzzle::load(path) {
+            Ok(puzzle) => settings.puzzle = Some(puzzle),
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        }
+        mode = Some(GameMode::Puzzle);
+    }
+    catch_interrupts().expect("Failed to catch interrupts");
+    let summary = match &cli_args.replay {
+        Some(path) => match Recording::load(path) {
+            Ok(recording) => watch_game(settings, theme, &recording),
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        },
+        None => game_runner(settings, theme, keymap, mode, cli_args.record.as_deref()),
+    };
+    // Stop the terminal event reader and any other long lived threads
+    // before exiting, so that none is cut off mid read.
+    supervisor::global()
+        .lock()
+        .expect("Supervisor poisoned.")
+        .shutdown();
+    println!("Game Over! Score: {}", summary.points);
+    match summary.puzzle_solved {
+        Some(true) => println!("Puzzle solved!"),
+        Some(false) => println!("Puzzle not solved. Try again!"),
+        None => {}
+    }
+    if let Some((date, best)) = summary.daily {
+        match best {
+            true => println!("New best for the {} daily challenge!", date),
+            false => println!("Played the {} daily challenge", date),
+        }
+    }
+    if let Some((p50, p99)) = summary.latency {
+        println!(
+            "Input latency: p50 {}, p99 {}",
+            format_latency(p50),
+            format_latency(p99)
+        );
+    }
+    if let Some(path) = summary.recorded_to {
+        println!("Inputs recorded to {}", path.display());
+    }
+    if let Some(survived) = summary.survived {
+        println!("Survived for {}", format_time(survived));
+    }
+    if let Some(garbage_cleared) = summary.garbage_cleared {
+        match summary.place {
+            Some(place) => println!(
+                "Cleared {} garbage rows (#{} on the leaderboard)",
+                garbage_cleared, place
+            ),
+            None => println!("Cleared {} garbage rows", garbage_cleared),
+        }
+    }
+    if let Some(finish_time) = summary.finish_time {
+        match summary.place {
+            Some(place) => println!(
+                "Finished in {} (#{} on the leaderboard)",
+                format_time(finish_time),
+                place
+            ),
+            None => println!("Finished in {}", format_time(finish_time)),
+        }
+    }
+}
+/// How a game went, for printing once the terminal is restored.
+#[derive(Default)]
+struct GameSummary {
+    points: u64,
+    /// Time taken to reach the mode's goal, if it was reached.
+    finish_time: Option<Duration>,
+    /// Place of the finish time on the leaderboard, if it made it.
+    place: Option<usize>,
+    /// Whether the puzzle was solved, in puzzle mode.
+    puzzle_solved: Option<bool>,
+    /// How long the player held out, in survival mode.
+    survived: Option<Duration>,
+    /// Garbage rows cleared by the end of a dig race.
+    garbage_cleared: Option<u64>,
+    /// Date of the daily challenge played and whether the score was the
+    /// day's best.
+    daily: Option<(String, bool)>,
+    /// File the game's inputs were recorded to.
+    recorded_to: Option<PathBuf>,
+    /// Median and 99th percentile input latency, if shown.
+    latency: Option<(Duration, Duration)>,
+}
+/// Adds a finishing time to the leaderboard file and returns its place.
+fn record_time(category: &str, time: Duration) -> Option<usize> {
+    update_leaderboard(|leaderboard| leaderboard.record(category, time))
+}
+/// Best score recorded in the leaderboard file for a daily challenge.
+fn daily_best(daily: &DailyChallenge) -> Option<u64> {
+    let path = Leaderboard::default_path()?;
+    match Leaderboard::load(&path) {
+        Ok(leaderboard) => leaderboard
+            .counts(&daily.leaderboard_category())
+            .first()
+            .copied(),
+        Err(error) => {
+            log::warn!("Could not read leaderboard: {}", error);
+            None
+        }
+    }
+}
+/// Adds a count to the leaderboard file and returns its place.
+fn record_count(category: &str, count: u64) -> Option<usize> {
+    update_leaderboard(|leaderboard| leaderboard.record_count(category, count))
+}
+/// Records a result in the leaderboard file with `record`, returning the
+/// place it gives.
+fn update_leaderboard(record: impl FnOnce(&mut Leaderboard) -> Option<usize>) -> Option<usize> {
+    let path = Leaderboard::default_path()?;
+    let mut leaderboard = match Leaderboard::load(&path) {
+        Ok(leaderboard) => leaderboard,
+        Err(error) => {
+            log::warn!("Could not read leaderboard: {}", error);
+            return None;
+        }
+    };
+    let place = record(&mut leaderboard);
+    if let Err(error) = leaderboard.save(&path) {
+        log::warn!("Could not save leaderboard: {}", error);
+        return None;
+    }
+    place
+}
+/// Reads the game settings, theme and keymap from the user's config file,
+/// if they have one.
+fn load_config() -> Result<(GameSettings, Theme, Keymap), ConfigError> {
+    let config = match Config::default_path() {
+        Some(path) => Config::load(&path)?,
+        None => Config::default(),
+    };
+    Ok((
+        GameSettings::from_config(&config)?,
+        Theme::from_config(&config)?,
+        Keymap::from_config(&config)?,
+    ))
+}
+/// Lets the player pick a mode, starting on `selected`. Returns `None` if
+/// they back out.
+fn choose_mode(cli_writer: &mut CliView<io::Stdout>, selected: GameMode) -> Option<GameMode> {
+    let options: Vec<(&str, &str)> = GameMode::ALL
+        .iter()
+        .map(|mode| (mode.name(), mode.description()))
+        .collect();
+    let mut selected = GameMode::ALL
+        .iter()
+        .position(|mode| *mode == selected)
+        .unwrap_or(0);
+    loop {
+        cli_writer
+            .draw_menu("Choose a mode:", &options, selected)
+            .expect("Failed to draw menu.");
+        match read_menu_input().expect("Failed to read menu input.") {
+            Command::MenuUp => selected = selected.saturating_sub(1),
+            Command::MenuDown => selected = (selected + 1).min(options.len() - 1),
+            Command::Select => return Some(GameMode::ALL[selected]),
+            Command::Back | Command::Quit => return None,
+            _other => {}
+        }
+    }
 }
-fn game_runner() -> u64 {
-    let mut tetris_board = TetrisBoard::new();
+/// Lets the player pick one of the built-in puzzles. Returns `None` if they
+/// back out.
+fn choose_puzzle(cli_writer: &mut CliView<io::Stdout>) -> Option<Puzzle> {
+    let mut puzzles = Puzzle::built_in();
+    let descriptions: Vec<String> = puzzles.iter().map(|puzzle| puzzle.description()).collect();
+    let options: Vec<(&str, &str)> = puzzles
+        .iter()
+        .zip(&descriptions)
+        .map(|(puzzle, description)| (puzzle.name.as_str(), description.as_str()))
+        .collect();
+    let mut selected = 0;
+    loop {
+        cli_writer
+            .draw_menu("Choose a puzzle:", &options, selected)
+            .expect("Failed to draw menu.");
+        match read_menu_input().expect("Failed to read menu input.") {
+            Command::MenuUp => selected = selected.saturating_sub(1),
+            Command::MenuDown => selected = (selected + 1).min(options.len() - 1),
+            Command::Select => return Some(puzzles.swap_remove(selected)),
+            Command::Back | Command::Quit => return None,
+            _other => {}
+        }
+    }
+}
+fn game_runner(
+    mut settings: GameSettings,
+    theme: Theme,
+    keymap: Keymap,
+    mode: Option<GameMode>,
+    record: Option<&Path>,
+) -> GameSummary {
     let mut cli_writer = CliView::<io::Stdout>::new();
+    cli_writer.set_theme(theme);
+    cli_writer.set_keymap(&keymap);
+    if wait_until_it_fits(&mut cli_writer).is_err() {
+        return GameSummary::default();
+    }
     cli_writer.draw_intro().unwrap();
-
-    let mut score = 0;
-    let mut level = 0;
-    let mut cleared_rows_count = 0;
-    loop {
-        let turn_duration = match level {
-            val if val < 9 => (1000 * (48 - (5 * level))) / 60,
-            _ => cmp::max((1000 * (9 - (level - 9))) / 60, 1000 / 60),
+    let daily = DailyChallenge::today();
+    cli_writer
+        .draw_daily_best(&daily.date(), daily_best(&daily))
+        .unwrap();
+    settings.mode = match mode {
+        Some(mode) => mode,
+        None => match choose_mode(&mut cli_writer, settings.mode) {
+            Some(mode) => mode,
+            None => return GameSummary::default(),
+        },
+    };
+    if settings.mode == GameMode::Puzzle && settings.puzzle.is_none() {
+        cli_writer.clear().unwrap();
+        cli_writer.draw_intro().unwrap();
+        settings.puzzle = match choose_puzzle(&mut cli_writer) {
+            Some(puzzle) => Some(puzzle),
+            None => return GameSummary::default(),
         };
-        cli_writer.draw_score(score, level, turn_duration).unwrap();
+    }
+    if settings.mode == GameMode::Daily {
+        settings.seed = Some(daily.seed());
+    }
+    cli_writer.clear().unwrap();
+    cli_writer.draw_intro().unwrap();
+    cli_writer.set_invisible_stack(settings.mode == GameMode::Invisible);
+    cli_writer
+        .set_input_display(settings.input_display)
+        .unwrap();
+    cli_writer.set_half_blocks(settings.half_blocks);
+    cli_writer.set_ascii(settings.ascii);
+    cli_writer.set_reduced_motion(settings.reduced_motion);
+    let mut command_collector = CliCommandCollector::with_settings(keymap, &settings);
+    let _mouse_capture = settings.mouse.then(MouseCapture::enable);
+    loop {
+        if let Some(summary) = play_game(
+            settings.clone(),
+            &mut cli_writer,
+            &mut command_collector,
+            &daily,
+            record,
+        ) {
+            return summary;
+        }
+        cli_writer.clear().unwrap();
+        cli_writer.draw_intro().unwrap();
+    }
+}
+/// Plays back a recorded game, from the same seed and mode, until it ends
+/// or the player stops it. Nothing is added to the leaderboard.
+fn watch_game(mut settings: GameSettings, theme: Theme, recording: &Recording) -> GameSummary {
+    settings.mode = recording.mode;
+    settings.seed = Some(recording.seed);
+    let mut cli_writer = CliView::<io::Stdout>::new();
+    cli_writer.set_theme(theme);
+    cli_writer
+        .set_input_display(settings.input_display)
+        .unwrap();
+    if wait_until_it_fits(&mut cli_writer).is_err() {
+        return GameSummary::default();
+    }
+    cli_writer.draw_intro().unwrap();
+    cli_writer.set_invisible_stack(settings.mode == GameMode::Invisible);
+    cli_writer.set_half_blocks(settings.half_blocks);
+    cli_writer.set_ascii(settings.ascii);
+    cli_writer.set_reduced_motion(settings.reduced_motion);
+    let mut game_state = GameState::with_settings(settings);
+    if run_countdown(&game_state, &mut cli_writer).is_err() {
+        return GameSummary::default();
+    }
+    let stopwatch = Stopwatch::new();
+    let mut command_collector = PlaybackCommandCollector::from_recording(recording);
+    let mut engine = Engine::new();
+    let mut latency = LatencyStats::default();
+    cli_writer
+        .draw_hud(&game_state, stopwatch.elapsed())
+        .expect("Failed to draw HUD.");
+    // Recordings leave out pausing, so this runs until the game ends, or
+    // the terminal becomes too small for it.
+    let _ = run_frames(
+        &mut game_state,
+        &mut cli_writer,
+        &mut command_collector,
+        &mut engine,
+        &mut latency,
+        &stopwatch,
+    );
+    GameSummary {
+        points: game_state.score.points,
+        ..GameSummary::default()
+    }
+}
+/// Plays a game until it ends or the player quits, recording its inputs to
+/// `record` if given. Returns `None` if they restart it instead.
+fn play_game(
+    mut settings: GameSettings,
+    cli_writer: &mut CliView<io::Stdout>,
+    command_collector: &mut CliCommandCollector,
+    daily: &DailyChallenge,
+    record: Option<&Path>,
+) -> Option<GameSummary> {
+    // A recording needs the seed to deal the same pieces again.
+    if record.is_some() && settings.seed.is_none() {
+        settings.seed = Some(rand::random());
+    }
+    let seed = settings.seed.unwrap_or_default();
+    let mut game_state = GameState::with_settings(settings);
+    // The terminal may have been resized while a menu was up.
+    if wait_until_it_fits(cli_writer).is_err() {
+        return Some(GameSummary::default());
+    }
+    if run_countdown(&game_state, cli_writer).is_err() {
+        return Some(GameSummary::default());
+    }
+    let mut stopwatch = Stopwatch::new();
+    if record.is_some() {
+        command_collector.record(Recorder::start(seed, game_state.settings.mode));
+    }
+    let mut engine = Engine::new();
+    let mut latency = LatencyStats::default();
 
-        let cleared_rows = match run_piece_loop(&mut tetris_board, turn_duration, &mut cli_writer) {
-            Ok(cleared_rows) => cleared_rows,
-            Err(_) => break,
+    loop {
+        if game_state.is_paused() {
+            stopwatch.pause();
+            // A game paused by the terminal shrinking waits for it to grow
+            // back before the menu is shown.
+            let menu = wait_until_it_fits(cli_writer)
+                .and_then(|()| run_pause_menu(&mut game_state, cli_writer, command_collector));
+            let paused_for = stopwatch.resume();
+            if let Some(recorder) = command_collector.recorder_mut() {
+                recorder.skip(paused_for);
+            }
+            if menu.is_err() {
+                break;
+            }
+            engine.restart_turn();
+        }
+        cli_writer
+            .draw_hud(&game_state, stopwatch.elapsed())
+            .expect("Failed to draw HUD.");
+        let frames = run_frames(
+            &mut game_state,
+            cli_writer,
+            command_collector,
+            &mut engine,
+            &mut latency,
+            &stopwatch,
+        );
+        if frames.is_err() {
+            break;
+        }
+    }
+    let recording = command_collector.take_recorder().map(Recorder::finish);
+    if game_state.exit() == Some(Exit::Restart) {
+        return None;
+    }
+    let mut summary = GameSummary {
+        points: game_state.score.points,
+        ..GameSummary::default()
+    };
+    if game_state.settings.show_latency {
+        summary.latency = latency.percentile(50).zip(latency.percentile(99));
+    }
+    let latency_results = latency_results(&summary);
+    if let (Some(recording), Some(path)) = (recording, record) {
+        match recording.save(path) {
+            Ok(()) => summary.recorded_to = Some(path.to_path_buf()),
+            Err(error) => log::warn!("Could not save recording: {}", error),
+        }
+    }
+    if game_state.settings.mode == GameMode::Puzzle {
+        summary.puzzle_solved = Some(game_state.is_puzzle_solved());
+    }
+    if game_state.settings.mode == GameMode::Survival {
+        summary.survived = Some(stopwatch.elapsed());
+    }
+    if game_state.settings.mode == GameMode::Daily {
+        let place = record_count(&daily.leaderboard_category(), game_state.score.points);
+        summary.daily = Some((daily.date(), place == Some(1)));
+    }
+    if game_state.settings.mode == GameMode::DigRace && game_state.is_finished() {
+        let garbage_cleared = game_state.board.garbage_cleared() as u64;
+        summary.garbage_cleared = Some(garbage_cleared);
+        summary.place = game_state
+            .leaderboard_category()
+            .and_then(|category| record_count(&category, garbage_cleared));
+        let results = [
+            ("Garbage cleared", garbage_cleared.to_string()),
+            ("Lines", game_state.score.lines.to_string()),
+            ("Pieces", game_state.pieces_locked().to_string()),
+            (
+                "Leaderboard",
+                summary
+                    .place
+                    .map_or(String::from("-"), |place| format!("#{}", place)),
+            ),
+        ];
+        show_results(
+            cli_writer,
+            "Time's up!",
+            &[&results[..], &latency_results].concat(),
+        );
+    } else if game_state.is_finished() && game_state.leaderboard_category().is_some() {
+        let finish_time = stopwatch.elapsed();
+        summary.finish_time = Some(finish_time);
+        summary.place = game_state
+            .leaderboard_category()
+            .and_then(|category| record_time(&category, finish_time));
+        if game_state.settings.mode == GameMode::Sprint {
+            let results = sprint_results(&game_state, &summary, finish_time);
+            let results = [&results[..], &latency_results].concat();
+            show_results(cli_writer, "Sprint complete!", &results);
+        }
+    }
+    if game_state.settings.mode == GameMode::Marathon && game_state.is_finished() {
+        let results = [
+            ("Score", game_state.score.points.to_string()),
+            ("Lines", game_state.score.lines.to_string()),
+            ("Level", game_state.score.level.to_string()),
+            ("Time", format_time(stopwatch.elapsed())),
+        ];
+        let results = [&results[..], &latency_results].concat();
+        show_results(cli_writer, "Marathon complete!", &results);
+    }
+    Some(summary)
+}
+/// The input latency percentiles, for results screens, if measured.
+fn latency_results(summary: &GameSummary) -> Vec<(&'static str, String)> {
+    match summary.latency {
+        Some((p50, p99)) => vec![
+            ("Latency p50", format_latency(p50)),
+            ("Latency p99", format_latency(p99)),
+        ],
+        None => vec![],
+    }
+}
+/// The time, pace and finesse of a finished sprint.
+fn sprint_results(
+    game_state: &GameState,
+    summary: &GameSummary,
+    finish_time: Duration,
+) -> [(&'static str, String); 5] {
+    let pieces = game_state.pieces_locked();
+    let pieces_per_second = pieces as f64 / finish_time.as_secs_f64().max(0.001);
+    [
+        ("Time", format_time(finish_time)),
+        ("Pieces", pieces.to_string()),
+        ("PPS", format!("{:.2}", pieces_per_second)),
+        (
+            "Finesse errors",
+            format!(
+                "{}/{}",
+                game_state.finesse.errors, game_state.finesse.placements
+            ),
+        ),
+        (
+            "Leaderboard",
+            summary
+                .place
+                .map_or(String::from("-"), |place| format!("#{}", place)),
+        ),
+    ]
+}
+/// Shows a results screen until the player moves on.
+fn show_results(cli_writer: &mut CliView<io::Stdout>, title: &str, results: &[(&str, String)]) {
+    cli_writer.clear().unwrap();
+    cli_writer.draw_intro().unwrap();
+    cli_writer
+        .draw_results(title, results)
+        .expect("Failed to draw results.");
+    loop {
+        match read_menu_input().expect("Failed to read menu input.") {
+            Command::Select | Command::Back | Command::Quit => return,
+            _other => {}
+        }
+    }
+}
+/// Shows that the terminal is too small for the game until it is made big
+/// enough, then lays the screen out for its size. Returns an error if the
+/// player quits instead. Terminals whose size cannot be read are left as
+/// they are.
+fn wait_until_it_fits(cli_writer: &mut CliView<io::Stdout>) -> Result<(), EndGameError> {
+    let Ok((mut cols, mut rows)) = terminal::size() else {
+        return Ok(());
+    };
+    let too_small = !cli_writer.fits(cols, rows);
+    while !cli_writer.fits(cols, rows) {
+        cli_writer
+            .draw_too_small(cols, rows)
+            .expect("Failed to draw message.");
+        (cols, rows) = match wait_for_resize().expect("Failed to read resize.") {
+            Some(size) => size,
+            None => return Err(EndGameError),
         };
-        cleared_rows_count += cleared_rows;
-        score += match cleared_rows {
-            1 => 40 * (level + 1),
-            2 => 100 * (level + 1),
-            3 => 300 * (level + 1),
-            4 => 1200 * (level + 1),
-            _other => 0,
+    }
+    if too_small {
+        cli_writer.clear().expect("Failed to clear screen.");
+    }
+    cli_writer
+        .set_size(cols, rows)
+        .expect("Failed to clear screen.");
+    Ok(())
+}
+/// Counts down from 3 over the board before the first piece appears, so
+/// that play does not start the instant the game is chosen. Keys pressed
+/// during the countdown are ignored, apart from Esc.
+fn run_countdown(
+    game_state: &GameState,
+    cli_writer: &mut CliView<io::Stdout>,
+) -> Result<(), EndGameError> {
+    for text in ["3", "2", "1", "GO"] {
+        cli_writer
+            .draw_board_text(&game_state.board, text)
+            .expect("Failed to draw countdown.");
+        if wait_discarding_input(COUNTDOWN_STEP).expect("Failed to read countdown input.") {
+            return Err(EndGameError);
+        }
+    }
+    Ok(())
+}
+/// Runs the game frame by frame until it is paused, returning an error if
+/// it ended or the player left it instead. Inputs are read throughout each
+/// frame and applied together at its end, adding the time from reading
+/// each input to applying it to `latency`. The score panel is drawn every
+/// frame, the board whenever a frame changes it or an effect on it is
+/// still playing out, and the rest of the HUD whenever a piece locks, with
+/// the time played read from `stopwatch`.
+/// Everything is drawn again once the terminal is resized, and the game is
+/// paused if it no longer fits.
+fn run_frames<T: CommandCollector, R: Renderer>(
+    game_state: &mut GameState,
+    renderer: &mut R,
+    command_collector: &mut T,
+    engine: &mut Engine,
+    latency: &mut LatencyStats,
+    stopwatch: &Stopwatch,
+) -> Result<(), EndGameError> {
+    let _guard = ScopedRawMode::new();
+    renderer
+        .draw_frame(game_state)
+        .expect("Failed to draw board.");
+    renderer
+        .draw_panel(game_state, stopwatch.elapsed())
+        .expect("Failed to draw score.");
+    let mut schedule = FrameSchedule::new();
+    loop {
+        if game_state.is_topped_out() || game_state.is_finished() || game_state.exit().is_some() {
+            return Err(EndGameError);
+        }
+        if game_state.is_paused() {
+            return Ok(());
+        }
+        // A timed game ends on the frame its time runs out, not the one
+        // after.
+        let frame_end = match game_state.time_left() {
+            Some(time_left) => schedule
+                .deadline()
+                .min(schedule.frame_started() + time_left),
+            None => schedule.deadline(),
         };
-        if cleared_rows_count >= 10 {
-            level += 1;
-            cleared_rows_count = 0;
+        let frame_inputs = read_frame_inputs(command_collector, frame_end);
+        let mut inputs = coalesce_inputs(frame_inputs.inputs);
+        if let Some((cols, rows)) = frame_inputs.resized {
+            let fits = renderer
+                .resize(cols, rows)
+                .expect("Failed to clear the screen.");
+            if !fits {
+                let pause = KeyInput {
+                    command: Command::Pause,
+                    state: KeyState::Tapped,
+                };
+                inputs.push((pause, Instant::now()));
+            }
+        }
+        schedule.wait_until(frame_end);
+        let frame = engine.tick(
+            game_state,
+            inputs.iter().map(|(input, _)| *input),
+            schedule.next_frame(),
+        );
+        for (input, read_at) in inputs {
+            latency.record(read_at.elapsed());
+            renderer.observe_input(input);
+        }
+        renderer
+            .draw_panel(game_state, stopwatch.elapsed())
+            .expect("Failed to draw score.");
+        if frame.locked || frame_inputs.resized.is_some() {
+            renderer
+                .draw_hud(game_state, stopwatch.elapsed())
+                .expect("Failed to draw HUD.");
+        }
+        if frame.changed || frame_inputs.resized.is_some() || renderer.is_animating() {
+            renderer
+                .draw_frame(game_state)
+                .expect("Failed to draw board.");
         }
     }
-    score
 }
-fn run_piece_loop(
-    tetris_board: &mut TetrisBoard,
-    turn_duration: u64,
+/// Choices in the pause menu.
+#[derive(Clone, Copy)]
+enum PauseChoice {
+    Resume,
+    EditBoard,
+    Controls,
+    Restart,
+    Quit,
+}
+/// Shows the pause menu until the player resumes, returning an error if
+/// they quit instead. The stack is hidden behind an empty board so that
+/// pausing cannot be used to study it.
+fn run_pause_menu(
+    game_state: &mut GameState,
     cli_writer: &mut CliView<io::Stdout>,
-) -> Result<u16, EndGameError> {
-    let mut tetris_piece = TetrisPiece::new(tetris::models::PieceShape::random());
-    if let PiecePositionValidity::PieceCollision =
-        tetris_board.check_is_valid_position(&tetris_piece.coordinates())
-    {
-        return Err(EndGameError);
+    command_collector: &mut CliCommandCollector,
+) -> Result<(), EndGameError> {
+    let mut choices = vec![("Resume", "Carry on playing.", PauseChoice::Resume)];
+    if game_state.settings.mode == GameMode::Practice {
+        choices.push((
+            "Edit board",
+            "Fill and empty cells and choose the next pieces.",
+            PauseChoice::EditBoard,
+        ));
     }
+    choices.push((
+        "Controls",
+        "Press a new key for each command.",
+        PauseChoice::Controls,
+    ));
+    choices.push(("Restart", "Start again from scratch.", PauseChoice::Restart));
+    choices.push(("Quit", "End the game.", PauseChoice::Quit));
+    let options: Vec<(&str, &str)> = choices
+        .iter()
+        .map(|(name, description, _)| (*name, *description))
+        .collect();
+    let mut selected = 0;
     loop {
+        cli_writer.clear().expect("Failed to clear screen.");
         cli_writer
-            .draw_piece_and_board(&tetris_piece, &tetris_board)
+            .draw_board_text(&TetrisBoard::new(), "PAUSED")
             .expect("Failed to draw board.");
-
-        let mut turn_timer = TurnTimer::new(turn_duration);
-        let mut turn_timer_subscriber = TurnTimerSubscriber::new();
-        let mut turn_timer_subscriber_1 = TurnTimerSubscriber::new();
-        turn_timer.add_subscriber(&mut turn_timer_subscriber);
-        turn_timer.add_subscriber(&mut turn_timer_subscriber_1);
-
-        turn_timer.run_timer();
-        thread::scope(|s| {
-            let (command_dispatcher, command_reciever) = mpsc::channel();
-            let (turn_event_sender, turn_event_reciever) = mpsc::channel::<TurnEvent>();
-            timed_user_input::<CliCommandCollector, TurnTimerSubscriber>(
-                turn_timer_subscriber,
-                command_dispatcher,
-                turn_event_reciever,
-                s,
-            );
-
-            for recieved in command_reciever {
-                if let TimerStatus::TimerComplete = turn_timer_subscriber_1.get_timer_status() {
+        cli_writer.draw_intro().expect("Failed to draw intro.");
+        cli_writer
+            .draw_menu("Paused", &options, selected)
+            .expect("Failed to draw menu.");
+        match read_menu_input().expect("Failed to read menu input.") {
+            Command::MenuUp => selected = selected.saturating_sub(1),
+            Command::MenuDown => selected = (selected + 1).min(options.len() - 1),
+            Command::Back => break,
+            Command::Quit => {
+                game_state.apply_command(Command::Quit);
+                return Err(EndGameError);
+            }
+            Command::Select => match choices[selected].2 {
+                PauseChoice::Resume => break,
+                PauseChoice::EditBoard => {
+                    cli_writer.clear().expect("Failed to clear screen.");
+                    run_editor(game_state, cli_writer);
                     break;
                 }
-                match recieved {
-                    Command::EndGame => {
-                        return Err(EndGameError);
-                    }
-                    other_command => {
-                        if let Some(TurnEvent::EndTurn) =
-                            tetris_piece.move_peice(&tetris_board, other_command)
-                        {
-                            if let Err(_) = turn_event_sender.send(TurnEvent::EndTurn) {
-                                log::warn!("End turn event sent to closed turn event channel.");
-                            };
-                            break;
-                        };
-                        cli_writer
-                            .draw_piece_and_board(&tetris_piece, &tetris_board)
-                            .expect("Failed to draw board.");
-                    }
+                PauseChoice::Controls => {
+                    run_controls_menu(cli_writer, command_collector.keymap_mut())
+                }
+                PauseChoice::Restart => {
+                    game_state.apply_command(Command::Restart);
+                    return Err(EndGameError);
+                }
+                PauseChoice::Quit => {
+                    game_state.apply_command(Command::Quit);
+                    return Err(EndGameError);
                 }
+            },
+            _other => {}
+        }
+    }
+    game_state.resume();
+    cli_writer.clear().expect("Failed to clear screen.");
+    cli_writer.draw_intro().expect("Failed to draw intro.");
+    Ok(())
+}
+/// Lets the player pick a command and press new keys for it, saving each
+/// change to the `[keys]` section of their config file.
+fn run_controls_menu(cli_writer: &mut CliView<io::Stdout>, keymap: &mut Keymap) {
+    let mut selected = 0;
+    let mut title = String::from("Controls:");
+    loop {
+        draw_controls(cli_writer, keymap, &title, selected);
+        match read_menu_input().expect("Failed to read menu input.") {
+            Command::MenuUp => selected = selected.saturating_sub(1),
+            Command::MenuDown => selected = (selected + 1).min(Keymap::COMMANDS.len() - 1),
+            Command::Back | Command::Quit => return,
+            Command::Select => {
+                let changed = read_new_keys(cli_writer, keymap, selected);
+                cli_writer.set_keymap(keymap);
+                title = match changed.is_empty() {
+                    true => String::from("Controls:"),
+                    false => match save_keys(keymap, &changed) {
+                        Ok(()) => String::from("Controls: saved."),
+                        Err(error) => format!("Controls: {}", error),
+                    },
+                };
             }
-            Ok(())
-        })?;
-        if let Some(out_piece) = tetris_piece.move_down(tetris_board) {
-            tetris_piece = out_piece;
-        } else {
-            break;
+            _other => {}
         }
     }
-    Ok(tetris_board.clear_rows())
 }
-
+/// Draws the controls menu, listing the keys bound to each command.
+fn draw_controls(
+    cli_writer: &mut CliView<io::Stdout>,
+    keymap: &Keymap,
+    title: &str,
+    selected: usize,
+) {
+    let lines: Vec<String> = Keymap::COMMANDS
+        .iter()
+        .map(|(name, command)| format!("{:<22}{}", name, keymap.key_names(*command)))
+        .collect();
+    let options: Vec<(&str, &str)> = lines
+        .iter()
+        .map(|line| {
+            (
+                line.as_str(),
+                "Press Enter, then the new keys. Esc goes back.",
+            )
+        })
+        .collect();
+    cli_writer.clear().expect("Failed to clear screen.");
+    cli_writer.draw_intro().expect("Failed to draw intro.");
+    cli_writer
+        .draw_menu(title, &options, selected)
+        .expect("Failed to draw menu.");
+}
+/// Binds the keys pressed next to the selected command. The first key
+/// replaces its keys and any more are bound alongside it, until Enter or
+/// Esc is pressed. Returns the commands whose keys changed.
+fn read_new_keys(
+    cli_writer: &mut CliView<io::Stdout>,
+    keymap: &mut Keymap,
+    selected: usize,
+) -> Vec<Command> {
+    let (name, command) = Keymap::COMMANDS[selected];
+    let mut changed: Vec<Command> = Vec::new();
+    loop {
+        let title = match changed.is_empty() {
+            true => format!("Press the new key for {}, or Esc to cancel:", name),
+            false => format!("Press another key for {}, or Enter to finish:", name),
+        };
+        draw_controls(cli_writer, keymap, &title, selected);
+        let key_code = match read_key().expect("Failed to read key.") {
+            Some(KeyCode::Enter) if !changed.is_empty() => return changed,
+            Some(key_code) => key_code,
+            None => return changed,
+        };
+        if key_name(key_code).is_none() {
+            continue;
+        }
+        let newly_changed = match changed.is_empty() {
+            true => keymap.bind(command, key_code),
+            false => keymap.add_key(command, key_code),
+        };
+        for command in newly_changed {
+            if !changed.contains(&command) {
+                changed.push(command);
+            }
+        }
+    }
+}
+/// Writes the keys bound to `commands` to the user's config file.
+fn save_keys(keymap: &Keymap, commands: &[Command]) -> Result<(), ConfigError> {
+    let path = match Config::default_path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    for (name, command) in Keymap::COMMANDS {
+        if commands.contains(&command) {
+            let raw = format!("\"{}\"", keymap.key_names(command));
+            Config::write_value(&path, &format!("keys.{}", name), &raw)?;
+        }
+    }
+    Ok(())
+}
+/// Lets the player edit the board and choose the next pieces while a
+/// practice game is paused, until they resume.
+fn run_editor(game_state: &mut GameState, cli_writer: &mut CliView<io::Stdout>) {
+    let mut cursor = Coord {
+        col: 0,
+        row: TetrisBoard::NUM_ROWS as i16 - 1,
+    };
+    loop {
+        cli_writer
+            .draw_editor(&game_state.board, &cursor, game_state.chosen_pieces())
+            .expect("Failed to draw editor.");
+        match read_editor_input().expect("Failed to read editor input.") {
+            EditorInput::Up => cursor.row = (cursor.row - 1).max(0),
+            EditorInput::Down => {
+                cursor.row = (cursor.row + 1).min(TetrisBoard::NUM_ROWS as i16 - 1)
+            }
+            EditorInput::Left => cursor.col = (cursor.col - 1).max(0),
+            EditorInput::Right => {
+                cursor.col = (cursor.col + 1).min(TetrisBoard::NUM_COLS as i16 - 1)
+            }
+            EditorInput::Toggle => game_state.board.toggle_cell(&cursor),
+            EditorInput::Piece(letter) => {
+                let piece_shape = game_state
+                    .settings
+                    .piece_set
+                    .shapes()
+                    .iter()
+                    .find(|piece_shape| piece_shape.name().starts_with(letter));
+                if let Some(piece_shape) = piece_shape {
+                    game_state.choose_piece(*piece_shape);
+                }
+            }
+            EditorInput::Unchoose => game_state.unchoose_piece(),
+            EditorInput::Resume => break,
+        }
+    }
+    cli_writer.clear_editor().expect("Failed to clear editor.");
+}
 struct EndGameError;
 
 impl fmt::Display for EndGameError {
@@ -0,0 +1,116 @@
+/// The ways the game can be played, chosen from the start menu or with the
+/// `--mode` flag.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameMode {
+    /// The standard endless game.
+    Endless,
+    /// Clear 150 lines, levelling up every 10.
+    Marathon,
+    /// Locked pieces fade from view, only reappearing when lines clear.
+    Invisible,
+    /// Every mino is twice the size on the same board.
+    Big,
+    /// Clearing lines earns items that trigger on their own.
+    Party,
+    /// Dig through rows of garbage as fast as possible.
+    Cheese,
+    /// Reach a goal from a preset board with a fixed set of pieces.
+    Puzzle,
+    /// Clear 40 lines as fast as possible.
+    Sprint,
+    /// Endless play where topping out clears half the board instead of
+    /// ending the game.
+    Zen,
+    /// Pieces fall at 20G from the start.
+    Master,
+    /// Garbage rises from the bottom on a timer that speeds up.
+    Survival,
+    /// Dig out as much regenerating garbage as possible in two minutes.
+    DigRace,
+    /// Pause to edit the board and choose the next pieces.
+    Practice,
+    /// The same pieces for everyone, changing every day.
+    Daily,
+}
+impl GameMode {
+    pub const ALL: [GameMode; 14] = [
+        GameMode::Endless,
+        GameMode::Marathon,
+        GameMode::Invisible,
+        GameMode::Big,
+        GameMode::Party,
+        GameMode::Cheese,
+        GameMode::Puzzle,
+        GameMode::Sprint,
+        GameMode::Zen,
+        GameMode::Master,
+        GameMode::Survival,
+        GameMode::DigRace,
+        GameMode::Practice,
+        GameMode::Daily,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Endless => "endless",
+            GameMode::Marathon => "marathon",
+            GameMode::Invisible => "invisible",
+            GameMode::Big => "big",
+            GameMode::Party => "party",
+            GameMode::Cheese => "cheese",
+            GameMode::Puzzle => "puzzle",
+            GameMode::Sprint => "sprint",
+            GameMode::Zen => "zen",
+            GameMode::Master => "master",
+            GameMode::Survival => "survival",
+            GameMode::DigRace => "dig-race",
+            GameMode::Practice => "practice",
+            GameMode::Daily => "daily",
+        }
+    }
+    pub fn description(self) -> &'static str {
+        match self {
+            GameMode::Endless => "Clear lines and level up for as long as you can.",
+            GameMode::Marathon => "Clear 150 lines to beat level 15.",
+            GameMode::Invisible => "The stack fades from view. Remember where it is.",
+            GameMode::Big => "Every block is doubled in size on the same board.",
+            GameMode::Party => "Clearing lines drops items that trigger on their own.",
+            GameMode::Cheese => "Race to dig out every row of garbage.",
+            GameMode::Puzzle => "Solve a preset board with the pieces given.",
+            GameMode::Sprint => "Clear 40 lines against the clock.",
+            GameMode::Zen => "Stack without pressure. Topping out clears half the board.",
+            GameMode::Master => "Pieces fall instantly. Lock delay is all you get.",
+            GameMode::Survival => "Garbage rises faster and faster. Hold out.",
+            GameMode::DigRace => "Dig out as much garbage as you can in 2 minutes.",
+            GameMode::Practice => "Pause to edit the board and pick the next pieces.",
+            GameMode::Daily => "Today's pieces are the same for everyone. Beat your best.",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+    /// Width in cells of each mino of a piece.
+    pub fn piece_scale(self) -> i16 {
+        match self {
+            GameMode::Big => 2,
+            _other => 1,
+        }
+    }
+    /// Name and mode of every mode, for reading a mode from the config.
+    pub fn choices() -> Vec<(&'static str, Self)> {
+        Self::ALL.iter().map(|mode| (mode.name(), *mode)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modes_are_found_by_name() {
+        for mode in GameMode::ALL {
+            assert_eq!(GameMode::from_name(mode.name()), Some(mode));
+        }
+        assert_eq!(GameMode::from_name("versus"), None);
+    }
+}
@@ -0,0 +1,811 @@
+use std::slice::Iter;
+
+use crate::board::{PiecePositionValidity, TetrisBoard};
+use crate::models::{Command, Coord, TurnEvent};
+use crate::rotation::{KickTable, RotationSystem};
+
+/// The seven tetrominoes and twelve pentominoes, named after the letters
+/// they resemble. Pentominoes that share a letter with a tetromino are
+/// suffixed with 5.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PieceShape {
+    I,
+    O,
+    T,
+    S,
+    Z,
+    J,
+    L,
+    F,
+    I5,
+    L5,
+    N,
+    P,
+    T5,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z5,
+}
+impl PieceShape {
+    /// Cells of the piece in its spawn orientation, relative to the top left
+    /// of its bounding box.
+    pub fn shape(&self) -> Vec<Coord> {
+        match *self {
+            PieceShape::I => vec![
+                Coord { col: 0, row: 1 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 2, row: 1 },
+                Coord { col: 3, row: 1 },
+            ],
+            PieceShape::O => vec![
+                Coord { col: 0, row: 0 },
+                Coord { col: 1, row: 0 },
+                Coord { col: 0, row: 1 },
+                Coord { col: 1, row: 1 },
+            ],
+            PieceShape::T => vec![
+                Coord { col: 1, row: 0 },
+                Coord { col: 0, row: 1 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 2, row: 1 },
+            ],
+            PieceShape::S => vec![
+                Coord { col: 1, row: 0 },
+                Coord { col: 2, row: 0 },
+                Coord { col: 0, row: 1 },
+                Coord { col: 1, row: 1 },
+            ],
+            PieceShape::Z => vec![
+                Coord { col: 0, row: 0 },
+                Coord { col: 1, row: 0 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 2, row: 1 },
+            ],
+            PieceShape::J => vec![
+                Coord { col: 0, row: 0 },
+                Coord { col: 0, row: 1 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 2, row: 1 },
+            ],
+            PieceShape::L => vec![
+                Coord { col: 2, row: 0 },
+                Coord { col: 0, row: 1 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 2, row: 1 },
+            ],
+            PieceShape::F => vec![
+                Coord { col: 1, row: 0 },
+                Coord { col: 2, row: 0 },
+                Coord { col: 0, row: 1 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 1, row: 2 },
+            ],
+            PieceShape::I5 => vec![
+                Coord { col: 0, row: 2 },
+                Coord { col: 1, row: 2 },
+                Coord { col: 2, row: 2 },
+                Coord { col: 3, row: 2 },
+                Coord { col: 4, row: 2 },
+            ],
+            PieceShape::L5 => vec![
+                Coord { col: 3, row: 0 },
+                Coord { col: 0, row: 1 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 2, row: 1 },
+                Coord { col: 3, row: 1 },
+            ],
+            PieceShape::N => vec![
+                Coord { col: 2, row: 0 },
+                Coord { col: 3, row: 0 },
+                Coord { col: 0, row: 1 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 2, row: 1 },
+            ],
+            PieceShape::P => vec![
+                Coord { col: 0, row: 0 },
+                Coord { col: 1, row: 0 },
+                Coord { col: 2, row: 0 },
+                Coord { col: 0, row: 1 },
+                Coord { col: 1, row: 1 },
+            ],
+            PieceShape::T5 => vec![
+                Coord { col: 0, row: 0 },
+                Coord { col: 1, row: 0 },
+                Coord { col: 2, row: 0 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 1, row: 2 },
+            ],
+            PieceShape::U => vec![
+                Coord { col: 0, row: 0 },
+                Coord { col: 2, row: 0 },
+                Coord { col: 0, row: 1 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 2, row: 1 },
+            ],
+            PieceShape::V => vec![
+                Coord { col: 0, row: 0 },
+                Coord { col: 0, row: 1 },
+                Coord { col: 0, row: 2 },
+                Coord { col: 1, row: 2 },
+                Coord { col: 2, row: 2 },
+            ],
+            PieceShape::W => vec![
+                Coord { col: 0, row: 0 },
+                Coord { col: 0, row: 1 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 1, row: 2 },
+                Coord { col: 2, row: 2 },
+            ],
+            PieceShape::X => vec![
+                Coord { col: 1, row: 0 },
+                Coord { col: 0, row: 1 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 2, row: 1 },
+                Coord { col: 1, row: 2 },
+            ],
+            PieceShape::Y => vec![
+                Coord { col: 2, row: 0 },
+                Coord { col: 0, row: 1 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 2, row: 1 },
+                Coord { col: 3, row: 1 },
+            ],
+            PieceShape::Z5 => vec![
+                Coord { col: 0, row: 0 },
+                Coord { col: 1, row: 0 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 1, row: 2 },
+                Coord { col: 2, row: 2 },
+            ],
+        }
+    }
+    /// Width (and height) of the square box the piece rotates within.
+    pub fn box_size(&self) -> i16 {
+        match *self {
+            PieceShape::I5 => 5,
+            PieceShape::I | PieceShape::L5 | PieceShape::N | PieceShape::Y => 4,
+            PieceShape::O => 2,
+            _other => 3,
+        }
+    }
+    /// Board position of the top left of the bounding box when the piece
+    /// enters play, centred on the board with odd width boxes biased left.
+    pub fn spawn_position(&self) -> Coord {
+        Coord {
+            col: (TetrisBoard::NUM_COLS as i16 - self.box_size()) / 2,
+            row: 0,
+        }
+    }
+    /// The seven tetrominoes.
+    pub fn iterator() -> Iter<'static, Self> {
+        PieceSet::Tetrominoes.shapes().iter()
+    }
+    /// The piece's letter, e.g. `T`, or `T5` for the T pentomino.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            PieceShape::I => "I",
+            PieceShape::O => "O",
+            PieceShape::T => "T",
+            PieceShape::S => "S",
+            PieceShape::Z => "Z",
+            PieceShape::J => "J",
+            PieceShape::L => "L",
+            PieceShape::F => "F",
+            PieceShape::I5 => "I5",
+            PieceShape::L5 => "L5",
+            PieceShape::N => "N",
+            PieceShape::P => "P",
+            PieceShape::T5 => "T5",
+            PieceShape::U => "U",
+            PieceShape::V => "V",
+            PieceShape::W => "W",
+            PieceShape::X => "X",
+            PieceShape::Y => "Y",
+            PieceShape::Z5 => "Z5",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        PieceSet::ALL
+            .iter()
+            .flat_map(|piece_set| piece_set.shapes())
+            .find(|piece_shape| piece_shape.name() == name)
+            .copied()
+    }
+}
+
+/// The pieces a game is played with, chosen when the game starts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PieceSet {
+    Tetrominoes,
+    Pentominoes,
+}
+impl PieceSet {
+    pub const ALL: [PieceSet; 2] = [PieceSet::Tetrominoes, PieceSet::Pentominoes];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PieceSet::Tetrominoes => "tetrominoes",
+            PieceSet::Pentominoes => "pentominoes",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|piece_set| piece_set.name() == name)
+    }
+    /// Name and piece set of every piece set, for reading one from the config.
+    pub fn choices() -> Vec<(&'static str, Self)> {
+        Self::ALL
+            .iter()
+            .map(|piece_set| (piece_set.name(), *piece_set))
+            .collect()
+    }
+    pub fn shapes(self) -> &'static [PieceShape] {
+        match self {
+            PieceSet::Tetrominoes => &[
+                PieceShape::I,
+                PieceShape::O,
+                PieceShape::T,
+                PieceShape::S,
+                PieceShape::Z,
+                PieceShape::J,
+                PieceShape::L,
+            ],
+            PieceSet::Pentominoes => &[
+                PieceShape::F,
+                PieceShape::I5,
+                PieceShape::L5,
+                PieceShape::N,
+                PieceShape::P,
+                PieceShape::T5,
+                PieceShape::U,
+                PieceShape::V,
+                PieceShape::W,
+                PieceShape::X,
+                PieceShape::Y,
+                PieceShape::Z5,
+            ],
+        }
+    }
+    /// Columns and rows needed to preview any piece of the set in its
+    /// spawn orientation.
+    pub fn preview_size(self) -> (usize, usize) {
+        match self {
+            PieceSet::Tetrominoes => (4, 2),
+            PieceSet::Pentominoes => (5, 3),
+        }
+    }
+}
+
+/// The four rotation states of a piece, starting from the way it spawns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    Spawn,
+    Right,
+    Reverse,
+    Left,
+}
+impl Orientation {
+    pub fn rotate(self, rotation: Rotation) -> Self {
+        match (self, rotation) {
+            (Orientation::Spawn, Rotation::Clockwise) => Orientation::Right,
+            (Orientation::Right, Rotation::Clockwise) => Orientation::Reverse,
+            (Orientation::Reverse, Rotation::Clockwise) => Orientation::Left,
+            (Orientation::Left, Rotation::Clockwise) => Orientation::Spawn,
+            (Orientation::Spawn, Rotation::Anticlockwise) => Orientation::Left,
+            (Orientation::Left, Rotation::Anticlockwise) => Orientation::Reverse,
+            (Orientation::Reverse, Rotation::Anticlockwise) => Orientation::Right,
+            (Orientation::Right, Rotation::Anticlockwise) => Orientation::Spawn,
+            (Orientation::Spawn, Rotation::Half) => Orientation::Reverse,
+            (Orientation::Right, Rotation::Half) => Orientation::Left,
+            (Orientation::Reverse, Rotation::Half) => Orientation::Spawn,
+            (Orientation::Left, Rotation::Half) => Orientation::Right,
+        }
+    }
+    /// Number of clockwise quarter turns from the spawn orientation.
+    fn quarter_turns(self) -> usize {
+        match self {
+            Orientation::Spawn => 0,
+            Orientation::Right => 1,
+            Orientation::Reverse => 2,
+            Orientation::Left => 3,
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rotation {
+    Clockwise,
+    Anticlockwise,
+    Half,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TetrisPiece {
+    piece_shape: PieceShape,
+    orientation: Orientation,
+    position: Coord,
+    rotation_system: RotationSystem,
+    /// Width in cells of each mino, 2 in big mode.
+    scale: i16,
+}
+impl TetrisPiece {
+    pub fn new(piece_shape: &PieceShape) -> Self {
+        Self::with_rotation_system(piece_shape, RotationSystem::Srs)
+    }
+    /// A piece at its spawn position, turning by the given rotation system.
+    /// Nintendo pieces spawn flat side up against the top of the board.
+    pub fn with_rotation_system(piece_shape: &PieceShape, rotation_system: RotationSystem) -> Self {
+        let mut piece = Self {
+            piece_shape: *piece_shape,
+            orientation: rotation_system.spawn_orientation(*piece_shape),
+            position: piece_shape.spawn_position(),
+            rotation_system,
+            scale: 1,
+        };
+        if rotation_system == RotationSystem::Nintendo {
+            let top_row = piece.shape().iter().map(|coord| coord.row).min();
+            piece.position.row -= top_row.unwrap_or(0);
+        }
+        return piece;
+    }
+    /// Returns the piece back at its spawn position with each mino drawn
+    /// as a `scale` by `scale` block of cells, for big mode.
+    pub fn scaled(&self, scale: i16) -> Self {
+        let spawned = Self::with_rotation_system(&self.piece_shape, self.rotation_system);
+        let box_width = self.piece_shape.box_size() * scale;
+        Self {
+            position: Coord {
+                col: (TetrisBoard::NUM_COLS as i16 - box_width) / 2,
+                row: spawned.position.row * scale,
+            },
+            scale,
+            ..spawned
+        }
+    }
+    pub fn scale(&self) -> i16 {
+        self.scale
+    }
+    pub fn piece_shape(&self) -> PieceShape {
+        self.piece_shape
+    }
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+    /// Board position of the top left of the piece's bounding box.
+    pub fn position(&self) -> Coord {
+        self.position.clone()
+    }
+    /// Cells of the piece in its current orientation, relative to the top
+    /// left of its bounding box.
+    pub fn shape(&self) -> Vec<Coord> {
+        let box_size = self.piece_shape.box_size();
+        let mut shape = self.piece_shape.shape();
+        for _ in 0..self.orientation.quarter_turns() {
+            shape = shape
+                .iter()
+                .map(|coord| Coord {
+                    col: box_size - 1 - coord.row,
+                    row: coord.col,
+                })
+                .collect();
+        }
+        return shape;
+    }
+    /// Board cells covered by the piece. Each mino of the shape covers a
+    /// square of `scale` cells along each side.
+    pub fn coordinates(&self) -> Vec<Coord> {
+        let mut coordinates = Vec::new();
+        for coord in self.shape() {
+            for row in 0..self.scale {
+                for col in 0..self.scale {
+                    coordinates.push(Coord {
+                        col: coord.col * self.scale + col + self.position.col,
+                        row: coord.row * self.scale + row + self.position.row,
+                    });
+                }
+            }
+        }
+        return coordinates;
+    }
+    /// Returns a copy of the piece moved by the given number of columns and
+    /// rows. The result is not checked against any board.
+    pub fn shifted(&self, col: i16, row: i16) -> Self {
+        Self {
+            position: Coord {
+                col: self.position.col + col,
+                row: self.position.row + row,
+            },
+            ..self.clone()
+        }
+    }
+    /// Returns a copy of the piece turned about the centre of its bounding
+    /// box. The result is not checked against any board.
+    pub fn rotated(&self, rotation: Rotation) -> Self {
+        Self {
+            orientation: self.rotation_system.next_orientation(
+                self.piece_shape,
+                self.orientation,
+                rotation,
+            ),
+            ..self.clone()
+        }
+    }
+    /// Rotates the piece on the board using its rotation system. The wall
+    /// kicks for the rotation are tried in order and the first position
+    /// that fits is taken. Returns the index of the kick used, or `None` if
+    /// the piece could not rotate.
+    pub fn rotate(&mut self, board: &TetrisBoard, rotation: Rotation) -> Option<usize> {
+        self.rotate_with_kicks(board, rotation, None)
+    }
+    /// Rotates the piece as for `rotate`, using the given kicks in place of
+    /// the rotation system's own, if there are any.
+    pub fn rotate_with_kicks(
+        &mut self,
+        board: &TetrisBoard,
+        rotation: Rotation,
+        kick_table: Option<&KickTable>,
+    ) -> Option<usize> {
+        let rotated_piece = self.rotated(rotation);
+        let kicks = match kick_table {
+            Some(kick_table) => kick_table.kicks(
+                self.piece_shape,
+                self.orientation,
+                rotated_piece.orientation,
+            ),
+            None => self.rotation_system.kicks(
+                self.piece_shape,
+                self.orientation,
+                rotated_piece.orientation,
+            ),
+        };
+        for (kick_index, kick) in kicks.iter().enumerate() {
+            let kicked_piece = rotated_piece.shifted(kick.col * self.scale, kick.row * self.scale);
+            if let PiecePositionValidity::Valid =
+                board.check_is_valid_position(&kicked_piece.coordinates())
+            {
+                *self = kicked_piece;
+                return Some(kick_index);
+            }
+        }
+        return None;
+    }
+    pub fn move_peice(&mut self, board: &TetrisBoard, direction: Command) -> Option<TurnEvent> {
+        let moved_piece = match direction {
+            Command::MoveLeft => self.shifted(-self.scale, 0),
+            Command::MoveRight => self.shifted(self.scale, 0),
+            Command::MoveDown => return Some(TurnEvent::EndTurn),
+            Command::RotateClockwise => {
+                self.rotate(board, Rotation::Clockwise);
+                return None;
+            }
+            Command::RotateAnticlockwise => {
+                self.rotate(board, Rotation::Anticlockwise);
+                return None;
+            }
+            Command::Rotate180 => {
+                self.rotate(board, Rotation::Half);
+                return None;
+            }
+            _other => return None,
+        };
+        if let PiecePositionValidity::Valid =
+            board.check_is_valid_position(&moved_piece.coordinates())
+        {
+            *self = moved_piece;
+        }
+        return None;
+    }
+
+    /// Number of rows the piece can fall before it lands on the stack or
+    /// the floor.
+    pub fn drop_distance(&self, board: &TetrisBoard) -> i16 {
+        let mut distance = 0;
+        while let PiecePositionValidity::Valid =
+            board.check_is_valid_position(&self.shifted(0, distance + 1).coordinates())
+        {
+            distance += 1;
+        }
+        return distance;
+    }
+
+    pub fn move_down(self, board: &mut TetrisBoard) -> Option<Self> {
+        let moved_piece = self.shifted(0, 1);
+        match board.check_is_valid_position(&moved_piece.coordinates()) {
+            PiecePositionValidity::Valid => {
+                return Some(moved_piece);
+            }
+            _other => {
+                board.place_cells(&self.coordinates());
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_piece_creation_matches_piece() {
+        for piece_shape in PieceShape::iterator() {
+            let tetris_piece = TetrisPiece::new(piece_shape);
+            assert_eq!(tetris_piece.shape(), piece_shape.shape());
+            assert_eq!(tetris_piece.shape().len(), 4);
+        }
+    }
+
+    #[test]
+    fn test_pentominoes_have_five_cells_within_their_box_and_preview() {
+        let (preview_cols, preview_rows) = PieceSet::Pentominoes.preview_size();
+        for piece_shape in PieceSet::Pentominoes.shapes() {
+            let shape = piece_shape.shape();
+            assert_eq!(shape.len(), 5);
+            let box_size = piece_shape.box_size();
+            assert!(shape
+                .iter()
+                .all(|coord| (0..box_size).contains(&coord.col)
+                    && (0..box_size).contains(&coord.row)));
+            let width = shape.iter().map(|coord| coord.col).max().unwrap()
+                - shape.iter().map(|coord| coord.col).min().unwrap();
+            let height = shape.iter().map(|coord| coord.row).max().unwrap()
+                - shape.iter().map(|coord| coord.row).min().unwrap();
+            assert!(width < preview_cols as i16 && height < preview_rows as i16);
+        }
+    }
+
+    #[test]
+    fn test_piece_coordinates_generated() {
+        for piece_shape in PieceShape::iterator() {
+            let tetris_piece = TetrisPiece::new(piece_shape);
+            let piece_coordinates = tetris_piece.coordinates();
+            for (coord, offset) in piece_coordinates.iter().zip(piece_shape.shape()) {
+                assert_eq!(coord.col, offset.col + tetris_piece.position.col);
+                assert_eq!(coord.row, offset.row + tetris_piece.position.row);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pieces_spawn_in_centre_columns() {
+        let expected_columns = [
+            (PieceShape::I, vec![3, 4, 5, 6]),
+            (PieceShape::O, vec![4, 5]),
+            (PieceShape::T, vec![3, 4, 5]),
+            (PieceShape::L, vec![3, 4, 5]),
+        ];
+        for (piece_shape, columns) in expected_columns {
+            let mut piece_columns: Vec<i16> = TetrisPiece::new(&piece_shape)
+                .coordinates()
+                .iter()
+                .map(|coord| coord.col)
+                .collect();
+            piece_columns.sort();
+            piece_columns.dedup();
+            assert_eq!(piece_columns, columns);
+        }
+    }
+
+    #[test]
+    fn test_shifted_does_not_move_original() {
+        let tetris_piece = TetrisPiece::new(&PieceShape::T);
+        let shifted_piece = tetris_piece.shifted(1, 2);
+        assert_eq!(
+            shifted_piece.position,
+            Coord {
+                col: tetris_piece.position.col + 1,
+                row: tetris_piece.position.row + 2
+            }
+        );
+        assert_eq!(tetris_piece.position, PieceShape::T.spawn_position());
+    }
+
+    #[test]
+    fn test_piece_cannot_go_off_board() {
+        let tetris_board = TetrisBoard::new();
+        let mut tetris_piece = TetrisPiece::new(&PieceShape::I);
+        for _ in 0..20 {
+            tetris_piece.move_peice(&tetris_board, Command::MoveRight);
+            for coord in tetris_piece.coordinates() {
+                assert!(coord.col < TetrisBoard::NUM_COLS as i16);
+            }
+        }
+        for _ in 0..20 {
+            tetris_piece.move_peice(&tetris_board, Command::MoveLeft);
+            for coord in tetris_piece.coordinates() {
+                assert!(coord.col >= 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_piece_moves_down_if_no_collision() {
+        let mut tetris_board = TetrisBoard::new();
+        let mut tetris_piece = TetrisPiece::new(&PieceShape::I);
+        let start_position = tetris_piece.position.clone();
+        let expected_position = Coord {
+            row: start_position.row + 1,
+            ..start_position
+        };
+        tetris_piece = tetris_piece.move_down(&mut tetris_board).unwrap();
+        assert_eq!(tetris_piece.position, expected_position);
+    }
+
+    #[test]
+    fn piece_is_fixed_if_down_is_collision() {
+        let mut tetris_board = TetrisBoard::new();
+        let mut tetris_piece = TetrisPiece::new(&PieceShape::I);
+        tetris_piece.position = Coord { row: -1, col: 2 };
+        tetris_board.board[1][3] = true;
+        tetris_piece.move_down(&mut tetris_board);
+        for i in 2..6 {
+            assert!(tetris_board.board[0][i as usize]);
+        }
+    }
+    #[test]
+    fn piece_is_fixed_if_down_is_end_of_board() {
+        let mut tetris_board = TetrisBoard::new();
+        let mut tetris_piece = TetrisPiece::new(&PieceShape::I);
+        tetris_piece.position = Coord { row: 18, col: 2 };
+        tetris_piece.move_down(&mut tetris_board);
+        for i in 2..6 {
+            assert!(tetris_board.board[19][i as usize]);
+        }
+    }
+    #[test]
+    fn piece_rotates_clockwise() {
+        let mut tetris_piece = TetrisPiece::new(&PieceShape::I);
+        let expected_coordiantes = vec![
+            vec![
+                Coord { col: 2, row: 0 },
+                Coord { col: 2, row: 1 },
+                Coord { col: 2, row: 2 },
+                Coord { col: 2, row: 3 },
+            ],
+            vec![
+                Coord { col: 3, row: 2 },
+                Coord { col: 2, row: 2 },
+                Coord { col: 1, row: 2 },
+                Coord { col: 0, row: 2 },
+            ],
+            vec![
+                Coord { col: 1, row: 3 },
+                Coord { col: 1, row: 2 },
+                Coord { col: 1, row: 1 },
+                Coord { col: 1, row: 0 },
+            ],
+            PieceShape::I.shape(),
+        ];
+        for expected_shape in expected_coordiantes {
+            tetris_piece = tetris_piece.rotated(Rotation::Clockwise);
+            assert_eq!(expected_shape, tetris_piece.shape())
+        }
+        assert_eq!(tetris_piece.orientation(), Orientation::Spawn);
+    }
+    #[test]
+    fn piece_rotates_anticlockwise() {
+        let mut tetris_piece = TetrisPiece::new(&PieceShape::T);
+        let expected_orientations = [
+            Orientation::Left,
+            Orientation::Reverse,
+            Orientation::Right,
+            Orientation::Spawn,
+        ];
+        for expected_orientation in expected_orientations {
+            let clockwise_equivalent = (0..3).fold(tetris_piece.clone(), |piece, _| {
+                piece.rotated(Rotation::Clockwise)
+            });
+            tetris_piece = tetris_piece.rotated(Rotation::Anticlockwise);
+            assert_eq!(tetris_piece.orientation(), expected_orientation);
+            assert_eq!(tetris_piece.shape(), clockwise_equivalent.shape());
+        }
+    }
+    #[test]
+    fn o_piece_is_unchanged_by_rotation() {
+        let tetris_piece = TetrisPiece::new(&PieceShape::O);
+        let rotated_coordinates = tetris_piece.rotated(Rotation::Clockwise).coordinates();
+        for coord in tetris_piece.coordinates() {
+            assert!(rotated_coordinates.contains(&coord));
+        }
+    }
+    #[test]
+    fn piece_kicks_off_wall_when_rotating() {
+        let tetris_board = TetrisBoard::new();
+        let mut tetris_piece = TetrisPiece::new(&PieceShape::I).rotated(Rotation::Clockwise);
+        // Vertical I piece flush against the left wall.
+        tetris_piece.position = Coord { col: -2, row: 5 };
+        assert!(tetris_piece
+            .rotate(&tetris_board, Rotation::Clockwise)
+            .is_some());
+        assert_eq!(tetris_piece.orientation(), Orientation::Reverse);
+        for coord in tetris_piece.coordinates() {
+            assert!(coord.col >= 0);
+        }
+    }
+    #[test]
+    fn piece_kicks_off_stack_when_rotating() {
+        let mut tetris_board = TetrisBoard::new();
+        let mut tetris_piece = TetrisPiece::new(&PieceShape::T);
+        tetris_piece.position = Coord { col: 3, row: 17 };
+        // Block the cell the unkicked rotation would need.
+        tetris_board.board[19][4] = true;
+        assert!(tetris_piece
+            .rotate(&tetris_board, Rotation::Clockwise)
+            .is_some());
+        assert_eq!(tetris_piece.orientation(), Orientation::Right);
+        assert_ne!(tetris_piece.position, Coord { col: 3, row: 17 });
+    }
+    #[test]
+    fn half_turn_kicks_piece_up_off_floor() {
+        let tetris_board = TetrisBoard::new();
+        let mut tetris_piece = TetrisPiece::new(&PieceShape::T).shifted(0, 18);
+        assert_eq!(tetris_piece.rotate(&tetris_board, Rotation::Half), Some(1));
+        assert_eq!(tetris_piece.orientation(), Orientation::Reverse);
+        assert_eq!(
+            tetris_piece.shape(),
+            TetrisPiece::new(&PieceShape::T)
+                .rotated(Rotation::Clockwise)
+                .rotated(Rotation::Clockwise)
+                .shape()
+        );
+        for coord in tetris_piece.coordinates() {
+            assert!(coord.row < TetrisBoard::NUM_ROWS as i16);
+        }
+    }
+    #[test]
+    fn nintendo_piece_spawns_flat_side_up_and_does_not_kick() {
+        let tetris_board = TetrisBoard::new();
+        let mut tetris_piece =
+            TetrisPiece::with_rotation_system(&PieceShape::T, RotationSystem::Nintendo);
+        let mut top_row = tetris_piece.coordinates();
+        top_row.retain(|coord| coord.row == 0);
+        assert_eq!(top_row.len(), 3);
+        // Standing against the left wall, a clockwise turn would need a kick.
+        tetris_piece.orientation = Orientation::Right;
+        tetris_piece.position = Coord { col: -1, row: 5 };
+        assert_eq!(
+            tetris_piece.rotate(&tetris_board, Rotation::Clockwise),
+            None
+        );
+    }
+    #[test]
+    fn big_piece_covers_two_by_two_blocks() {
+        let tetris_board = TetrisBoard::new();
+        let mut tetris_piece = TetrisPiece::new(&PieceShape::O).scaled(2);
+        let coordinates = tetris_piece.coordinates();
+        assert_eq!(coordinates.len(), 16);
+        let cols: Vec<i16> = coordinates.iter().map(|coord| coord.col).collect();
+        assert_eq!(*cols.iter().min().unwrap(), 3);
+        assert_eq!(*cols.iter().max().unwrap(), 6);
+        tetris_piece.move_peice(&tetris_board, Command::MoveLeft);
+        assert_eq!(tetris_piece.position().col, 1);
+        assert_eq!(tetris_piece.drop_distance(&tetris_board), 16);
+    }
+    #[test]
+    fn piece_does_not_rotate_when_every_kick_fails() {
+        let mut tetris_board = TetrisBoard::new();
+        for row in 0..TetrisBoard::NUM_ROWS {
+            tetris_board.board[row] = vec![true; TetrisBoard::NUM_COLS];
+        }
+        let mut tetris_piece = TetrisPiece::new(&PieceShape::I).shifted(0, 4);
+        for coord in tetris_piece.coordinates() {
+            tetris_board.board[coord.row as usize][coord.col as usize] = false;
+        }
+        let start_piece = tetris_piece.clone();
+        assert_eq!(
+            tetris_piece.rotate(&tetris_board, Rotation::Clockwise),
+            None
+        );
+        assert_eq!(tetris_piece, start_piece);
+    }
+    #[test]
+    fn drop_distance_stops_at_stack() {
+        let mut tetris_board = TetrisBoard::new();
+        let tetris_piece = TetrisPiece::new(&PieceShape::I);
+        assert_eq!(tetris_piece.drop_distance(&tetris_board), 18);
+        tetris_board.board[10][5] = true;
+        assert_eq!(tetris_piece.drop_distance(&tetris_board), 8);
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::board::TetrisBoard;
+use crate::piece::PieceShape;
+use crate::randomizer::PieceGenerator;
+
+/// Puzzles that come with the game, embedded from the `puzzles` directory.
+const BUILT_IN: [&str; 4] = [
+    include_str!("../puzzles/tetris_ready.txt"),
+    include_str!("../puzzles/t_slot.txt"),
+    include_str!("../puzzles/two_o_clear.txt"),
+    include_str!("../puzzles/pc_in_three.txt"),
+];
+
+/// What the player must do to solve a puzzle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Goal {
+    /// Empty the board with a line clear.
+    PerfectClear,
+    /// Clear at least this many lines.
+    Lines(u64),
+}
+
+/// A fixed starting board and piece sequence, and a goal to reach before
+/// the pieces run out. Puzzle files are made of `key: value` lines giving
+/// the `name`, the `goal` (`perfect_clear` or `lines N`) and the `pieces`
+/// by letter, followed by a `board:` line and then the rows of the board,
+/// using `#` for filled and `.` for empty cells. The rows sit at the bottom
+/// of the board. Lines starting with `#` above the board are comments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Puzzle {
+    pub name: String,
+    pub goal: Goal,
+    pub pieces: Vec<PieceShape>,
+    /// Rows of the board from the top down, ending at the bottom row.
+    pub rows: Vec<Vec<bool>>,
+}
+impl Puzzle {
+    pub fn parse(text: &str) -> Result<Self, PuzzleError> {
+        let mut name = None;
+        let mut goal = None;
+        let mut pieces = None;
+        let mut rows = Vec::new();
+        let mut in_board = false;
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if in_board {
+                if line.is_empty() {
+                    continue;
+                }
+                rows.push(parse_row(line).ok_or_else(|| {
+                    PuzzleError::at_line(
+                        line_number,
+                        &format!(
+                            "board rows must be {} cells of `#` or `.`",
+                            TetrisBoard::NUM_COLS
+                        ),
+                    )
+                })?);
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line == "board:" {
+                in_board = true;
+                continue;
+            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| PuzzleError::at_line(line_number, "expected `key: value`"))?;
+            let value = value.trim();
+            match key.trim() {
+                "name" => name = Some(value.to_string()),
+                "goal" => {
+                    goal = Some(parse_goal(value).ok_or_else(|| {
+                        PuzzleError::at_line(
+                            line_number,
+                            "goal must be `perfect_clear` or `lines N`",
+                        )
+                    })?)
+                }
+                "pieces" => {
+                    let shapes: Option<Vec<PieceShape>> = value
+                        .split_whitespace()
+                        .map(PieceShape::from_name)
+                        .collect();
+                    pieces = Some(shapes.filter(|shapes| !shapes.is_empty()).ok_or_else(|| {
+                        PuzzleError::at_line(line_number, "pieces must be piece letters")
+                    })?);
+                }
+                other => {
+                    return Err(PuzzleError::at_line(
+                        line_number,
+                        &format!("unknown key `{}`", other),
+                    ))
+                }
+            }
+        }
+        if rows.len() > TetrisBoard::NUM_ROWS {
+            return Err(PuzzleError::new("the board has too many rows"));
+        }
+        Ok(Self {
+            name: name.ok_or_else(|| PuzzleError::new("missing `name`"))?,
+            goal: goal.ok_or_else(|| PuzzleError::new("missing `goal`"))?,
+            pieces: pieces.ok_or_else(|| PuzzleError::new("missing `pieces`"))?,
+            rows,
+        })
+    }
+    pub fn load(path: &Path) -> Result<Self, PuzzleError> {
+        let text = fs::read_to_string(path).map_err(|error| {
+            PuzzleError::new(&format!("could not read {}: {}", path.display(), error))
+        })?;
+        Self::parse(&text)
+    }
+    /// The puzzles that come with the game.
+    pub fn built_in() -> Vec<Self> {
+        BUILT_IN
+            .iter()
+            .map(|text| Self::parse(text).expect("Built-in puzzle is invalid."))
+            .collect()
+    }
+    /// Describes the goal and pieces, e.g. "Perfect clear with: I L L".
+    pub fn description(&self) -> String {
+        let goal = match self.goal {
+            Goal::PerfectClear => String::from("Perfect clear"),
+            Goal::Lines(lines) => format!("Clear {} lines", lines),
+        };
+        let pieces: Vec<&str> = self.pieces.iter().map(|shape| shape.name()).collect();
+        format!("{} with: {}", goal, pieces.join(" "))
+    }
+    /// Fills the bottom of the board with the puzzle's starting stack.
+    pub fn fill_board(&self, board: &mut TetrisBoard) {
+        let first_row = TetrisBoard::NUM_ROWS - self.rows.len();
+        for (offset, row) in self.rows.iter().enumerate() {
+            board.board[first_row + offset] = row.clone();
+        }
+    }
+}
+
+fn parse_row(line: &str) -> Option<Vec<bool>> {
+    let row: Option<Vec<bool>> = line
+        .chars()
+        .map(|cell| match cell {
+            '#' => Some(true),
+            '.' => Some(false),
+            _other => None,
+        })
+        .collect();
+    row.filter(|row| row.len() == TetrisBoard::NUM_COLS)
+}
+
+fn parse_goal(value: &str) -> Option<Goal> {
+    match value.split_whitespace().collect::<Vec<&str>>()[..] {
+        ["perfect_clear"] => Some(Goal::PerfectClear),
+        ["lines", lines] => lines.parse().ok().map(Goal::Lines),
+        _ => None,
+    }
+}
+
+/// Deals a puzzle's pieces in order, starting again from the first once
+/// they run out.
+pub struct FixedSequence {
+    pieces: Vec<PieceShape>,
+    upcoming: VecDeque<PieceShape>,
+}
+impl FixedSequence {
+    pub fn new(pieces: Vec<PieceShape>) -> Self {
+        Self {
+            upcoming: pieces.iter().copied().collect(),
+            pieces,
+        }
+    }
+}
+impl PieceGenerator for FixedSequence {
+    fn next_piece(&mut self) -> PieceShape {
+        if self.upcoming.is_empty() {
+            self.upcoming = self.pieces.iter().copied().collect();
+        }
+        self.upcoming.pop_front().unwrap_or(PieceShape::O)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PuzzleError {
+    line: Option<usize>,
+    message: String,
+}
+impl PuzzleError {
+    fn new(message: &str) -> Self {
+        Self {
+            line: None,
+            message: message.to_string(),
+        }
+    }
+    fn at_line(line: usize, message: &str) -> Self {
+        Self {
+            line: Some(line),
+            message: message.to_string(),
+        }
+    }
+}
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "Puzzle error on line {}: {}", line, self.message),
+            None => write!(f, "Puzzle error: {}", self.message),
+        }
+    }
+}
+impl std::error::Error for PuzzleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in_puzzles_parse() {
+        let puzzles = Puzzle::built_in();
+        assert_eq!(puzzles.len(), BUILT_IN.len());
+        assert_eq!(puzzles[0].name, "Tetris Ready");
+        assert_eq!(puzzles[0].goal, Goal::Lines(4));
+        assert_eq!(puzzles[0].pieces, vec![PieceShape::I]);
+        assert_eq!(puzzles[0].rows.len(), 4);
+        assert_eq!(puzzles[3].description(), "Perfect clear with: I L L");
+    }
+
+    #[test]
+    fn test_board_rows_sit_at_the_bottom() {
+        let puzzle =
+            Puzzle::parse("name: a\ngoal: lines 1\npieces: T\nboard:\n#.........\n##########")
+                .unwrap();
+        let mut tetris_board = TetrisBoard::new();
+        puzzle.fill_board(&mut tetris_board);
+        assert_eq!(tetris_board.occupied_rows(), vec![18, 19]);
+        assert!(tetris_board.board[18][0]);
+        assert!(!tetris_board.board[18][1]);
+    }
+
+    #[test]
+    fn test_puzzle_errors_report_line_number() {
+        let error = Puzzle::parse("name: a\ngoal: win\npieces: T\nboard:").unwrap_err();
+        assert_eq!(error.line, Some(2));
+        let error = Puzzle::parse("name: a\ngoal: lines 1\npieces: T\nboard:\n#..").unwrap_err();
+        assert_eq!(error.line, Some(5));
+        assert!(Puzzle::parse("name: a\ngoal: lines 1\nboard:").is_err());
+    }
+
+    #[test]
+    fn test_fixed_sequence_deals_pieces_in_order() {
+        let mut generator = FixedSequence::new(vec![PieceShape::I, PieceShape::O]);
+        assert_eq!(generator.next_piece(), PieceShape::I);
+        assert_eq!(generator.next_piece(), PieceShape::O);
+        assert_eq!(generator.next_piece(), PieceShape::I);
+    }
+}
@@ -0,0 +1,212 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::VecDeque;
+
+use crate::piece::{PieceSet, PieceShape};
+
+/// Decides the order that pieces are dealt in.
+pub trait PieceGenerator {
+    fn next_piece(&mut self) -> PieceShape;
+}
+
+/// The randomizers a game can be played with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Randomizer {
+    SevenBag,
+    TgmHistory,
+    PureRandom,
+}
+impl Randomizer {
+    /// A generator dealing pieces from `piece_set`.
+    pub fn generator(self, piece_set: PieceSet) -> Box<dyn PieceGenerator> {
+        self.generator_with_rng(piece_set, StdRng::from_entropy())
+    }
+    /// A generator dealing pieces from `piece_set` that always deals the
+    /// same pieces for the same seed.
+    pub fn seeded_generator(self, piece_set: PieceSet, seed: u64) -> Box<dyn PieceGenerator> {
+        self.generator_with_rng(piece_set, StdRng::seed_from_u64(seed))
+    }
+    fn generator_with_rng(self, piece_set: PieceSet, rng: StdRng) -> Box<dyn PieceGenerator> {
+        match self {
+            Randomizer::SevenBag => Box::new(SevenBag::with_rng(piece_set, rng)),
+            Randomizer::TgmHistory => Box::new(TgmHistory::with_rng(piece_set, rng)),
+            Randomizer::PureRandom => Box::new(PureRandom::with_rng(piece_set, rng)),
+        }
+    }
+}
+
+/// The guideline randomizer: deals all seven pieces in a random order, then
+/// shuffles them again. With pentominoes the bag holds all twelve.
+pub struct SevenBag {
+    piece_set: PieceSet,
+    bag: Vec<PieceShape>,
+    rng: StdRng,
+}
+impl Default for SevenBag {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl SevenBag {
+    pub fn new() -> Self {
+        Self::with_piece_set(PieceSet::Tetrominoes)
+    }
+    pub fn with_piece_set(piece_set: PieceSet) -> Self {
+        Self::with_rng(piece_set, StdRng::from_entropy())
+    }
+    fn with_rng(piece_set: PieceSet, rng: StdRng) -> Self {
+        Self {
+            piece_set,
+            bag: Vec::new(),
+            rng,
+        }
+    }
+}
+impl PieceGenerator for SevenBag {
+    fn next_piece(&mut self) -> PieceShape {
+        if self.bag.is_empty() {
+            self.bag = self.piece_set.shapes().to_vec();
+            self.bag.shuffle(&mut self.rng);
+        }
+        self.bag.pop().unwrap()
+    }
+}
+
+/// The Tetris The Grand Master 2 randomizer: each roll is retried up to
+/// six times if it matches one of the last four pieces dealt, which makes
+/// droughts and repeats rare. The first tetromino is always an I, J, L or T.
+pub struct TgmHistory {
+    piece_set: PieceSet,
+    history: VecDeque<PieceShape>,
+    first_piece: bool,
+    rng: StdRng,
+}
+impl Default for TgmHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl TgmHistory {
+    const ROLLS: usize = 6;
+
+    pub fn new() -> Self {
+        Self::with_piece_set(PieceSet::Tetrominoes)
+    }
+    pub fn with_piece_set(piece_set: PieceSet) -> Self {
+        Self::with_rng(piece_set, StdRng::from_entropy())
+    }
+    fn with_rng(piece_set: PieceSet, rng: StdRng) -> Self {
+        Self {
+            piece_set,
+            history: VecDeque::from([PieceShape::Z, PieceShape::S, PieceShape::Z, PieceShape::S]),
+            first_piece: true,
+            rng,
+        }
+    }
+    fn roll(&mut self) -> PieceShape {
+        *self.piece_set.shapes().choose(&mut self.rng).unwrap()
+    }
+}
+impl PieceGenerator for TgmHistory {
+    fn next_piece(&mut self) -> PieceShape {
+        let piece_shape = if self.first_piece && self.piece_set == PieceSet::Tetrominoes {
+            self.first_piece = false;
+            *[PieceShape::I, PieceShape::J, PieceShape::L, PieceShape::T]
+                .choose(&mut self.rng)
+                .unwrap()
+        } else {
+            let mut piece_shape = self.roll();
+            for _ in 1..Self::ROLLS {
+                if !self.history.contains(&piece_shape) {
+                    break;
+                }
+                piece_shape = self.roll();
+            }
+            piece_shape
+        };
+        self.history.pop_front();
+        self.history.push_back(piece_shape);
+        piece_shape
+    }
+}
+
+/// Every piece is equally likely, independent of the pieces before it.
+pub struct PureRandom {
+    piece_set: PieceSet,
+    rng: StdRng,
+}
+impl PureRandom {
+    pub fn with_piece_set(piece_set: PieceSet) -> Self {
+        Self::with_rng(piece_set, StdRng::from_entropy())
+    }
+    fn with_rng(piece_set: PieceSet, rng: StdRng) -> Self {
+        Self { piece_set, rng }
+    }
+}
+impl PieceGenerator for PureRandom {
+    fn next_piece(&mut self) -> PieceShape {
+        *self.piece_set.shapes().choose(&mut self.rng).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seven_bag_deals_each_piece_once_per_bag() {
+        let mut generator = SevenBag::new();
+        for _ in 0..3 {
+            let mut bag: Vec<PieceShape> = (0..7).map(|_| generator.next_piece()).collect();
+            for piece_shape in PieceShape::iterator() {
+                assert!(bag.contains(piece_shape));
+                bag.retain(|shape| shape != piece_shape);
+            }
+            assert!(bag.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_tgm_first_piece_is_never_s_z_or_o() {
+        for _ in 0..50 {
+            let piece_shape = TgmHistory::new().next_piece();
+            assert!(!matches!(
+                piece_shape,
+                PieceShape::S | PieceShape::Z | PieceShape::O
+            ));
+        }
+    }
+
+    #[test]
+    fn test_generators_deal_from_their_piece_set() {
+        for randomizer in [
+            Randomizer::SevenBag,
+            Randomizer::TgmHistory,
+            Randomizer::PureRandom,
+        ] {
+            let mut generator = randomizer.generator(PieceSet::Pentominoes);
+            for _ in 0..24 {
+                assert!(PieceSet::Pentominoes
+                    .shapes()
+                    .contains(&generator.next_piece()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_seeded_generators_deal_the_same_pieces() {
+        for randomizer in [
+            Randomizer::SevenBag,
+            Randomizer::TgmHistory,
+            Randomizer::PureRandom,
+        ] {
+            let deal = |seed: u64| -> Vec<PieceShape> {
+                let mut generator = randomizer.seeded_generator(PieceSet::Tetrominoes, seed);
+                (0..21).map(|_| generator.next_piece()).collect()
+            };
+            assert_eq!(deal(20376), deal(20376));
+            assert_ne!(deal(20376), deal(20377));
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::keymap::Keymap;
+use crate::mode::GameMode;
+use crate::models::{Command, KeyInput, KeyState};
+
+/// Every input of a game and when it came, with the seed its pieces were
+/// dealt from, so that the game can be played back. Recording files are
+/// made of `key: value` lines giving the `seed` and `mode`, followed by an
+/// `inputs:` line and then one line per input: the tick it came on, in
+/// milliseconds since play started, `p`, `r` or `t` for a press, release
+/// or tap, and the command by its name in the config, e.g. `1200 t
+/// hard_drop`. Steering to a column with the mouse is `column` and the
+/// column. Other settings are taken from the config when played back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub inputs: Vec<RecordedInput>,
+}
+
+/// An input and the tick it came on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecordedInput {
+    /// Milliseconds since play started.
+    pub tick: u64,
+    pub input: KeyInput,
+}
+
+impl Recording {
+    pub fn new(seed: u64, mode: GameMode) -> Self {
+        Self {
+            seed,
+            mode,
+            inputs: Vec::new(),
+        }
+    }
+    pub fn parse(text: &str) -> Result<Self, RecordingError> {
+        let mut seed = None;
+        let mut mode = None;
+        let mut inputs = Vec::new();
+        let mut in_inputs = false;
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if in_inputs {
+                inputs.push(parse_input(line).ok_or_else(|| {
+                    RecordingError::at_line(line_number, "expected `tick state command`")
+                })?);
+                continue;
+            }
+            if line == "inputs:" {
+                in_inputs = true;
+                continue;
+            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| RecordingError::at_line(line_number, "expected `key: value`"))?;
+            let value = value.trim();
+            match key.trim() {
+                "seed" => {
+                    seed = Some(value.parse().map_err(|_| {
+                        RecordingError::at_line(line_number, "seed must be a whole number")
+                    })?)
+                }
+                "mode" => {
+                    mode = Some(GameMode::from_name(value).ok_or_else(|| {
+                        RecordingError::at_line(line_number, &format!("unknown mode `{}`", value))
+                    })?)
+                }
+                other => {
+                    return Err(RecordingError::at_line(
+                        line_number,
+                        &format!("unknown key `{}`", other),
+                    ))
+                }
+            }
+        }
+        Ok(Self {
+            seed: seed.ok_or_else(|| RecordingError::new("missing `seed`"))?,
+            mode: mode.ok_or_else(|| RecordingError::new("missing `mode`"))?,
+            inputs,
+        })
+    }
+    pub fn load(path: &Path) -> Result<Self, RecordingError> {
+        let text = fs::read_to_string(path).map_err(|error| {
+            RecordingError::new(&format!("could not read {}: {}", path.display(), error))
+        })?;
+        Self::parse(&text)
+    }
+    pub fn save(&self, path: &Path) -> Result<(), RecordingError> {
+        fs::write(path, self.to_text()).map_err(|error| {
+            RecordingError::new(&format!("could not write {}: {}", path.display(), error))
+        })
+    }
+    pub fn to_text(&self) -> String {
+        let mut text = format!("seed: {}\nmode: {}\ninputs:\n", self.seed, self.mode.name());
+        for recorded in &self.inputs {
+            let state = match recorded.input.state {
+                KeyState::Pressed => "p",
+                KeyState::Released => "r",
+                KeyState::Tapped => "t",
+                KeyState::Repeated => "a",
+            };
+            text.push_str(&format!(
+                "{} {} {}\n",
+                recorded.tick,
+                state,
+                command_text(recorded.input.command)
+            ));
+        }
+        text
+    }
+}
+
+/// Records inputs into a recording as they come, timed from when play
+/// started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recorder {
+    recording: Recording,
+    start: Instant,
+}
+impl Recorder {
+    /// Starts recording a game that starts now.
+    pub fn start(seed: u64, mode: GameMode) -> Self {
+        Self {
+            recording: Recording::new(seed, mode),
+            start: Instant::now(),
+        }
+    }
+    pub fn record(&mut self, input: KeyInput, now: Instant) {
+        let tick = now.duration_since(self.start).as_millis() as u64;
+        self.recording.inputs.push(RecordedInput { tick, input });
+    }
+    /// Leaves time spent paused out of the ticks.
+    pub fn skip(&mut self, paused_for: Duration) {
+        self.start += paused_for;
+    }
+    pub fn finish(self) -> Recording {
+        self.recording
+    }
+}
+
+fn command_text(command: Command) -> String {
+    if let Command::MoveToColumn(col) = command {
+        return format!("column {}", col);
+    }
+    Keymap::COMMANDS
+        .iter()
+        .find(|(_, named)| *named == command)
+        .map_or(String::new(), |(name, _)| name.to_string())
+}
+
+fn parse_input(line: &str) -> Option<RecordedInput> {
+    let mut parts = line.split_whitespace();
+    let tick = parts.next()?.parse().ok()?;
+    let state = match parts.next()? {
+        "p" => KeyState::Pressed,
+        "r" => KeyState::Released,
+        "t" => KeyState::Tapped,
+        "a" => KeyState::Repeated,
+        _other => return None,
+    };
+    let command = match parts.next()? {
+        "column" => Command::MoveToColumn(parts.next()?.parse().ok()?),
+        name => Keymap::COMMANDS
+            .iter()
+            .find(|(command_name, _)| *command_name == name)
+            .map(|(_, command)| *command)?,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(RecordedInput {
+        tick,
+        input: KeyInput { command, state },
+    })
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RecordingError {
+    line: Option<usize>,
+    message: String,
+}
+impl RecordingError {
+    fn new(message: &str) -> Self {
+        Self {
+            line: None,
+            message: message.to_string(),
+        }
+    }
+    fn at_line(line: usize, message: &str) -> Self {
+        Self {
+            line: Some(line),
+            message: message.to_string(),
+        }
+    }
+}
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "Recording error on line {}: {}", line, self.message),
+            None => write!(f, "Recording error: {}", self.message),
+        }
+    }
+}
+impl std::error::Error for RecordingError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recordings_round_trip_through_text() {
+        let mut recording = Recording::new(42, GameMode::Sprint);
+        for (tick, command, state) in [
+            (0, Command::MoveLeft, KeyState::Pressed),
+            (90, Command::MoveLeft, KeyState::Released),
+            (400, Command::MoveToColumn(-1), KeyState::Tapped),
+            (1200, Command::HardDrop, KeyState::Tapped),
+        ] {
+            recording.inputs.push(RecordedInput {
+                tick,
+                input: KeyInput { command, state },
+            });
+        }
+        let text = recording.to_text();
+        assert!(text.contains("\n1200 t hard_drop\n"));
+        assert_eq!(Recording::parse(&text).unwrap(), recording);
+    }
+
+    #[test]
+    fn test_bad_recordings_are_errors() {
+        assert!(Recording::parse("mode: sprint\ninputs:\n").is_err());
+        let error = Recording::parse("seed: 1\nmode: sprint\ninputs:\n10 t teleport").unwrap_err();
+        assert_eq!(error.line, Some(4));
+    }
+}
@@ -0,0 +1,101 @@
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::game::GameState;
+use crate::mode::GameMode;
+use crate::models::KeyInput;
+use crate::views::CliView;
+
+/// Draws a game as it is played, from its `GameState`, so that how the game
+/// looks can change without touching how it runs. `CliView` draws straight
+/// to the terminal with crossterm, and is the only renderer so far: a
+/// ratatui backend behind a feature flag has not been written, as ratatui
+/// is not yet a dependency.
+pub trait Renderer {
+    /// Draws the board with the active piece, and whatever beside it changes
+    /// as the piece moves: the held piece, the pieces to come, the zone
+    /// meter and the inputs.
+    fn draw_frame(&mut self, game_state: &GameState) -> io::Result<()>;
+    /// Draws the panel beside the board of the score, level, lines cleared
+    /// and time played, `elapsed`, every frame.
+    fn draw_panel(&mut self, game_state: &GameState, elapsed: Duration) -> io::Result<()>;
+    /// Draws whatever else the mode shows beside the board, `elapsed` into
+    /// the game, which changes as pieces lock.
+    fn draw_hud(&mut self, game_state: &GameState, elapsed: Duration) -> io::Result<()>;
+    /// Lays the screen out again for a terminal `cols` wide and `rows`
+    /// tall, clearing it so that everything is drawn afresh. Returns whether
+    /// the playfield fits in it.
+    fn resize(&mut self, cols: u16, rows: u16) -> io::Result<bool>;
+    /// Whether an effect drawn with the board, such as a hard drop's trail,
+    /// is still playing out, so that the board is drawn again even if
+    /// nothing on it changed.
+    fn is_animating(&self) -> bool {
+        false
+    }
+    /// Notes an input, for renderers that show them.
+    fn observe_input(&mut self, input: KeyInput);
+}
+
+impl<W: Write> Renderer for CliView<W> {
+    fn draw_frame(&mut self, game_state: &GameState) -> io::Result<()> {
+        self.draw_piece_and_board(
+            &game_state.piece,
+            &game_state.board,
+            game_state.last_hard_drop.as_ref(),
+        )?;
+        self.draw_hold(
+            game_state.held_piece,
+            game_state.settings.piece_set,
+            game_state.can_hold(),
+        )?;
+        self.draw_next(&game_state.next_pieces(), game_state.settings.piece_set)?;
+        if game_state.settings.zone {
+            self.draw_zone(&game_state.zone, game_state.board.banked_rows())?;
+        }
+        self.draw_input_display()
+    }
+    fn draw_panel(&mut self, game_state: &GameState, elapsed: Duration) -> io::Result<()> {
+        let time_per_turn = game_state.gravity().interval;
+        self.draw_score(&game_state.score, time_per_turn, elapsed)
+    }
+    fn draw_hud(&mut self, game_state: &GameState, elapsed: Duration) -> io::Result<()> {
+        self.draw_clear_announcement(game_state.last_clear)?;
+        self.draw_finesse(&game_state.finesse)?;
+        self.draw_input_display()?;
+        match (game_state.settings.mode, &game_state.settings.puzzle) {
+            (GameMode::Party, _) => self.draw_items(&game_state.items.queued())?,
+            (GameMode::Puzzle, Some(puzzle)) => self.draw_puzzle(puzzle)?,
+            (GameMode::Cheese, _) => {
+                self.draw_dig_progress(game_state.board.garbage_rows(), elapsed)?
+            }
+            (GameMode::Sprint, _) => {
+                let lines_left = GameState::SPRINT_LINES.saturating_sub(game_state.score.lines);
+                self.draw_sprint_progress(lines_left, elapsed)?
+            }
+            (GameMode::Survival, _) => {
+                if let Some(garbage_timer) = &game_state.garbage_timer {
+                    self.draw_survival(garbage_timer.until_next(), elapsed)?
+                }
+            }
+            (GameMode::Practice, _) => self.draw_chosen_pieces(game_state.chosen_pieces())?,
+            (GameMode::DigRace, _) => {
+                if let Some(time_left) = game_state.time_left() {
+                    self.draw_dig_race(game_state.board.garbage_cleared(), time_left)?
+                }
+            }
+            _other => {}
+        }
+        Ok(())
+    }
+    fn resize(&mut self, cols: u16, rows: u16) -> io::Result<bool> {
+        self.set_size(cols, rows)?;
+        self.clear()?;
+        Ok(self.fits(cols, rows))
+    }
+    fn is_animating(&self) -> bool {
+        CliView::is_animating(self)
+    }
+    fn observe_input(&mut self, input: KeyInput) {
+        CliView::observe_input(self, input);
+    }
+}
@@ -0,0 +1,318 @@
+use crate::config::{Config, ConfigError};
+use crate::models::Coord;
+use crate::piece::{Orientation, PieceShape, Rotation};
+
+/// The rules for how pieces spawn and turn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RotationSystem {
+    /// The guideline Super Rotation System, with wall kicks.
+    Srs,
+    /// Classic NES style right-handed rotation. Pieces spawn flat side up,
+    /// I, S and Z only have two states, and nothing kicks.
+    Nintendo,
+}
+impl RotationSystem {
+    pub fn spawn_orientation(self, piece_shape: PieceShape) -> Orientation {
+        match (self, piece_shape) {
+            (RotationSystem::Nintendo, shape) if shape != PieceShape::O => Orientation::Reverse,
+            _other => Orientation::Spawn,
+        }
+    }
+    /// The orientation a piece turns to from `from`.
+    pub fn next_orientation(
+        self,
+        piece_shape: PieceShape,
+        from: Orientation,
+        rotation: Rotation,
+    ) -> Orientation {
+        if self == RotationSystem::Srs {
+            return from.rotate(rotation);
+        }
+        match piece_shape {
+            PieceShape::O => from,
+            PieceShape::I | PieceShape::S | PieceShape::Z if rotation == Rotation::Half => from,
+            // Two state pieces flip between lying flat and standing on the
+            // right hand side of their box, whichever way they are turned.
+            PieceShape::I | PieceShape::S | PieceShape::Z => match from {
+                Orientation::Reverse => Orientation::Right,
+                _other => Orientation::Reverse,
+            },
+            _other => from.rotate(rotation),
+        }
+    }
+    /// Board offsets to try, in order, when turning a piece.
+    pub fn kicks(self, piece_shape: PieceShape, from: Orientation, to: Orientation) -> Vec<Coord> {
+        match self {
+            RotationSystem::Srs => wall_kicks(piece_shape, from, to),
+            RotationSystem::Nintendo => vec![Coord { col: 0, row: 0 }],
+        }
+    }
+}
+
+// Super Rotation System wall kicks, written as (x, y) offsets with y pointing
+// up as in the guideline. Each row is one rotation, in the order given by
+// `transition_index`, and the offsets are tried in order until one fits.
+const JLSTZ_KICKS: [[(i16, i16); 5]; 8] = [
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)], // Spawn -> Right
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],     // Right -> Spawn
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],     // Right -> Reverse
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)], // Reverse -> Right
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],    // Reverse -> Left
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],  // Left -> Reverse
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],  // Left -> Spawn
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],    // Spawn -> Left
+];
+const I_KICKS: [[(i16, i16); 5]; 8] = [
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)], // Spawn -> Right
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)], // Right -> Spawn
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)], // Right -> Reverse
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)], // Reverse -> Right
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)], // Reverse -> Left
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)], // Left -> Reverse
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)], // Left -> Spawn
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)], // Spawn -> Left
+];
+
+// 180 degree kicks as used by modern guideline clients, shared by every
+// piece. Rows are indexed by the orientation being rotated from.
+const HALF_TURN_KICKS: [[(i16, i16); 6]; 4] = [
+    [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)], // Spawn -> Reverse
+    [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)],   // Right -> Left
+    [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)], // Reverse -> Spawn
+    [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)], // Left -> Right
+];
+
+fn half_turn_index(from: Orientation, to: Orientation) -> Option<usize> {
+    match (from, to) {
+        (Orientation::Spawn, Orientation::Reverse) => Some(0),
+        (Orientation::Right, Orientation::Left) => Some(1),
+        (Orientation::Reverse, Orientation::Spawn) => Some(2),
+        (Orientation::Left, Orientation::Right) => Some(3),
+        _other => None,
+    }
+}
+
+fn transition_index(from: Orientation, to: Orientation) -> Option<usize> {
+    match (from, to) {
+        (Orientation::Spawn, Orientation::Right) => Some(0),
+        (Orientation::Right, Orientation::Spawn) => Some(1),
+        (Orientation::Right, Orientation::Reverse) => Some(2),
+        (Orientation::Reverse, Orientation::Right) => Some(3),
+        (Orientation::Reverse, Orientation::Left) => Some(4),
+        (Orientation::Left, Orientation::Reverse) => Some(5),
+        (Orientation::Left, Orientation::Spawn) => Some(6),
+        (Orientation::Spawn, Orientation::Left) => Some(7),
+        _other => None,
+    }
+}
+
+// Config names of the rotations in `transition_index` and `half_turn_index`
+// order, using 0, R, 2 and L for the spawn, right, reverse and left states.
+const TRANSITION_NAMES: [&str; 8] = [
+    "0->R", "R->0", "R->2", "2->R", "2->L", "L->2", "L->0", "0->L",
+];
+const HALF_TURN_NAMES: [&str; 4] = ["0->2", "R->L", "2->0", "L->R"];
+
+fn to_board_offsets(kicks: &[(i16, i16)]) -> Vec<Coord> {
+    kicks
+        .iter()
+        .map(|(x, y)| Coord { col: *x, row: -*y })
+        .collect()
+}
+
+/// Wall kicks as data, so that they can be replaced from the config file.
+/// Offsets are board offsets, tried in order for each rotation. The default
+/// is the Super Rotation System.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KickTable {
+    /// Quarter turn kicks for the J, L, S, T and Z pieces, and every
+    /// pentomino but the I.
+    pub jlstz: Vec<Vec<Coord>>,
+    /// Quarter turn kicks for the I pieces.
+    pub i: Vec<Vec<Coord>>,
+    /// Half turn kicks, shared by every piece.
+    pub half_turn: Vec<Vec<Coord>>,
+}
+impl Default for KickTable {
+    fn default() -> Self {
+        Self {
+            jlstz: JLSTZ_KICKS
+                .iter()
+                .map(|kicks| to_board_offsets(kicks))
+                .collect(),
+            i: I_KICKS
+                .iter()
+                .map(|kicks| to_board_offsets(kicks))
+                .collect(),
+            half_turn: HALF_TURN_KICKS
+                .iter()
+                .map(|kicks| to_board_offsets(kicks))
+                .collect(),
+        }
+    }
+}
+impl KickTable {
+    /// Returns the board offsets to try, in order, when rotating a piece
+    /// from one orientation to another, either a quarter or a half turn.
+    /// The long I pieces use the I kicks and the O piece never kicks.
+    pub fn kicks(&self, piece_shape: PieceShape, from: Orientation, to: Orientation) -> Vec<Coord> {
+        let table = match piece_shape {
+            PieceShape::O => return vec![Coord { col: 0, row: 0 }],
+            PieceShape::I | PieceShape::I5 => &self.i,
+            _other => &self.jlstz,
+        };
+        if let Some(index) = half_turn_index(from, to) {
+            return self.half_turn[index].clone();
+        }
+        match transition_index(from, to) {
+            Some(index) => table[index].clone(),
+            None => vec![Coord { col: 0, row: 0 }],
+        }
+    }
+    /// Returns the default kicks with any overrides from the `[kicks.jlstz]`,
+    /// `[kicks.i]` and `[kicks.half_turn]` sections of the config, or `None`
+    /// if the config has no kicks. Each key names a rotation, e.g. `0->R`,
+    /// and its value lists the offsets in guideline coordinates with y
+    /// pointing up, e.g. `"(0, 0) (-1, 0) (-1, 1)"`.
+    pub fn from_config(config: &Config) -> Result<Option<Self>, ConfigError> {
+        if !config.has_section("kicks") {
+            return Ok(None);
+        }
+        let mut kick_table = Self::default();
+        let tables = [
+            ("jlstz", &TRANSITION_NAMES[..], &mut kick_table.jlstz),
+            ("i", &TRANSITION_NAMES[..], &mut kick_table.i),
+            ("half_turn", &HALF_TURN_NAMES[..], &mut kick_table.half_turn),
+        ];
+        for (table_name, rotation_names, table) in tables {
+            for (index, rotation_name) in rotation_names.iter().enumerate() {
+                let key = format!("kicks.{}.{}", table_name, rotation_name);
+                let mut value = String::new();
+                config.read(&key, &mut value)?;
+                if value.is_empty() {
+                    continue;
+                }
+                table[index] = parse_offsets(&value).ok_or_else(|| {
+                    config.error_at(&key, "expected offsets like \"(0, 0) (-1, 0)\"")
+                })?;
+            }
+        }
+        Ok(Some(kick_table))
+    }
+}
+
+/// Parses `(x, y)` pairs in guideline coordinates into board offsets.
+fn parse_offsets(value: &str) -> Option<Vec<Coord>> {
+    let mut offsets = Vec::new();
+    for pair in value.split(')') {
+        let pair = pair.trim().trim_start_matches(',').trim();
+        if pair.is_empty() {
+            continue;
+        }
+        let (x, y) = pair.strip_prefix('(')?.split_once(',')?;
+        let x: i16 = x.trim().parse().ok()?;
+        let y: i16 = y.trim().parse().ok()?;
+        offsets.push(Coord { col: x, row: -y });
+    }
+    if offsets.is_empty() {
+        return None;
+    }
+    Some(offsets)
+}
+
+/// Returns the Super Rotation System board offsets to try, in order, when
+/// rotating a piece from one orientation to another, either a quarter or a
+/// half turn. The first offset is always no movement, and the O piece never
+/// kicks.
+pub fn wall_kicks(piece_shape: PieceShape, from: Orientation, to: Orientation) -> Vec<Coord> {
+    KickTable::default().kicks(piece_shape, from, to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_kick_is_no_movement() {
+        for piece_shape in PieceShape::iterator() {
+            let kicks = wall_kicks(*piece_shape, Orientation::Spawn, Orientation::Right);
+            assert_eq!(kicks[0], Coord { col: 0, row: 0 });
+        }
+    }
+
+    #[test]
+    fn test_kicks_are_converted_to_board_rows() {
+        let kicks = wall_kicks(PieceShape::T, Orientation::Spawn, Orientation::Right);
+        // (-1, +1) in guideline coordinates is one left and one row up.
+        assert_eq!(kicks[2], Coord { col: -1, row: -1 });
+    }
+
+    #[test]
+    fn test_reverse_rotation_kicks_are_opposite() {
+        let orientations = [
+            Orientation::Spawn,
+            Orientation::Right,
+            Orientation::Reverse,
+            Orientation::Left,
+        ];
+        for piece_shape in [PieceShape::T, PieceShape::I] {
+            for i in 0..orientations.len() {
+                let from = orientations[i];
+                let to = orientations[(i + 1) % orientations.len()];
+                let forward = wall_kicks(piece_shape, from, to);
+                let back = wall_kicks(piece_shape, to, from);
+                for (forward_kick, back_kick) in forward.iter().zip(back) {
+                    assert_eq!(forward_kick.col, -back_kick.col);
+                    assert_eq!(forward_kick.row, -back_kick.row);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_half_turn_kicks() {
+        let kicks = wall_kicks(PieceShape::I, Orientation::Spawn, Orientation::Reverse);
+        assert_eq!(kicks.len(), 6);
+        // (0, +1) in guideline coordinates is one row up.
+        assert_eq!(kicks[1], Coord { col: 0, row: -1 });
+        let o_kicks = wall_kicks(PieceShape::O, Orientation::Right, Orientation::Left);
+        assert_eq!(o_kicks, vec![Coord { col: 0, row: 0 }]);
+    }
+
+    #[test]
+    fn test_nintendo_two_state_pieces_flip() {
+        let nintendo = RotationSystem::Nintendo;
+        let spawn = nintendo.spawn_orientation(PieceShape::S);
+        let turned = nintendo.next_orientation(PieceShape::S, spawn, Rotation::Clockwise);
+        assert_eq!(turned, Orientation::Right);
+        assert_eq!(
+            nintendo.next_orientation(PieceShape::S, turned, Rotation::Clockwise),
+            spawn
+        );
+        assert_eq!(
+            nintendo.next_orientation(PieceShape::O, Orientation::Spawn, Rotation::Clockwise),
+            Orientation::Spawn
+        );
+        assert_eq!(
+            nintendo.kicks(PieceShape::T, Orientation::Reverse, Orientation::Left),
+            vec![Coord { col: 0, row: 0 }]
+        );
+    }
+
+    #[test]
+    fn test_kicks_can_be_overridden_from_config() {
+        assert_eq!(KickTable::from_config(&Config::default()).unwrap(), None);
+        let config = Config::parse("[kicks.jlstz]\n\"0->R\" = \"(0, 0), (1, 1)\"").unwrap();
+        let kick_table = KickTable::from_config(&config).unwrap().unwrap();
+        assert_eq!(
+            kick_table.kicks(PieceShape::T, Orientation::Spawn, Orientation::Right),
+            vec![Coord { col: 0, row: 0 }, Coord { col: 1, row: -1 }]
+        );
+        assert_eq!(
+            kick_table.kicks(PieceShape::I, Orientation::Spawn, Orientation::Right),
+            wall_kicks(PieceShape::I, Orientation::Spawn, Orientation::Right)
+        );
+        let config = Config::parse("[kicks.i]\n0->R = \"(0 0)\"").unwrap();
+        assert!(KickTable::from_config(&config).is_err());
+    }
+}
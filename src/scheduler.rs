@@ -0,0 +1,99 @@
+/// Timed events, each registered with the deadline it is due at, so that
+/// one loop can run every timer of the game by taking whatever is due in
+/// deadline order. An event is scheduled at most once: scheduling it again
+/// moves its deadline. Time is whatever `T` the owner counts in, e.g. an
+/// `Instant`, or a `Duration` of game time that stops while paused.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scheduler<E, T> {
+    /// Events and their deadlines, earliest first. Events due at the same
+    /// time keep the order they were scheduled in.
+    deadlines: Vec<(T, E)>,
+}
+impl<E, T> Default for Scheduler<E, T> {
+    fn default() -> Self {
+        Self {
+            deadlines: Vec::new(),
+        }
+    }
+}
+impl<E: PartialEq, T: Ord + Copy> Scheduler<E, T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Schedules `event` for `at`, in place of any deadline it already had.
+    pub fn schedule(&mut self, event: E, at: T) {
+        self.cancel(&event);
+        let index = self
+            .deadlines
+            .partition_point(|(deadline, _)| *deadline <= at);
+        self.deadlines.insert(index, (at, event));
+    }
+    /// Removes `event`, returning whether it was scheduled.
+    pub fn cancel(&mut self, event: &E) -> bool {
+        let before = self.deadlines.len();
+        self.deadlines.retain(|(_, scheduled)| scheduled != event);
+        self.deadlines.len() != before
+    }
+    /// Removes every event.
+    pub fn clear(&mut self) {
+        self.deadlines.clear();
+    }
+    /// When `event` is due, if it is scheduled.
+    pub fn deadline(&self, event: &E) -> Option<T> {
+        self.deadlines
+            .iter()
+            .find(|(_, scheduled)| scheduled == event)
+            .map(|(deadline, _)| *deadline)
+    }
+    /// The earliest deadline of any event, e.g. to wait until.
+    pub fn next_deadline(&self) -> Option<T> {
+        self.deadlines.first().map(|(deadline, _)| *deadline)
+    }
+    /// Removes and returns the earliest event due by `now`, with its
+    /// deadline.
+    pub fn pop_due(&mut self, now: T) -> Option<(E, T)> {
+        match self.deadlines.first() {
+            Some((deadline, _)) if *deadline <= now => {
+                let (deadline, event) = self.deadlines.remove(0);
+                Some((event, deadline))
+            }
+            _ => None,
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.deadlines.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_due_events_are_taken_in_deadline_order() {
+        let mut scheduler = Scheduler::new();
+        scheduler.schedule("lock", 30);
+        scheduler.schedule("gravity", 10);
+        scheduler.schedule("repeat", 10);
+        assert_eq!(scheduler.next_deadline(), Some(10));
+        assert_eq!(scheduler.pop_due(5), None);
+        assert_eq!(scheduler.pop_due(20), Some(("gravity", 10)));
+        assert_eq!(scheduler.pop_due(20), Some(("repeat", 10)));
+        assert_eq!(scheduler.pop_due(20), None);
+        assert_eq!(scheduler.pop_due(30), Some(("lock", 30)));
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_scheduling_again_moves_the_deadline() {
+        let mut scheduler = Scheduler::new();
+        scheduler.schedule("gravity", 10);
+        scheduler.schedule("lock", 20);
+        scheduler.schedule("gravity", 30);
+        assert_eq!(scheduler.deadline(&"gravity"), Some(30));
+        assert_eq!(scheduler.next_deadline(), Some(20));
+        assert!(scheduler.cancel(&"lock"));
+        assert!(!scheduler.cancel(&"lock"));
+        assert_eq!(scheduler.pop_due(30), Some(("gravity", 30)));
+    }
+}
@@ -0,0 +1,512 @@
+use std::cmp;
+
+use crate::config::{Config, ConfigError};
+use crate::spin::SpinKind;
+
+/// The result of locking a piece: how many rows it cleared, whether it was
+/// spun into place and whether the clear left the board empty.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineClear {
+    pub rows: u16,
+    pub spin: SpinKind,
+    pub perfect_clear: bool,
+}
+impl LineClear {
+    /// Name of the clear to announce to the player, or `None` for a plain
+    /// lock that cleared nothing.
+    pub fn name(&self) -> Option<String> {
+        let rows_name = match self.rows {
+            0 => None,
+            1 => Some("Single"),
+            2 => Some("Double"),
+            3 => Some("Triple"),
+            4 => Some("Tetris"),
+            _other => Some("Pentris"),
+        };
+        let spin_name = match self.spin {
+            SpinKind::NoSpin => None,
+            SpinKind::Mini => Some(String::from("T-Spin Mini")),
+            SpinKind::Full => Some(String::from("T-Spin")),
+            SpinKind::Immobile(piece_shape) => Some(format!("{}-Spin", piece_shape.name())),
+        };
+        match (spin_name, rows_name) {
+            (Some(spin_name), Some(rows_name)) => Some(format!("{} {}", spin_name, rows_name)),
+            (Some(spin_name), None) => Some(spin_name),
+            (None, Some(rows_name)) => Some(rows_name.to_string()),
+            (None, None) => None,
+        }
+    }
+    /// Tetrises, pentrises and spins that clear lines are difficult clears,
+    /// which earn a back-to-back bonus when chained.
+    pub fn is_difficult(&self) -> bool {
+        self.rows > 0 && (self.rows >= 4 || self.spin != SpinKind::NoSpin)
+    }
+    /// Bonus for emptying the board at level 1, on top of the points for
+    /// the clear itself.
+    fn perfect_clear_bonus(&self, table: &ScoreTable, back_to_back: bool) -> u64 {
+        if !self.perfect_clear {
+            return 0;
+        }
+        match self.rows {
+            1 => table.perfect_clear_single,
+            2 => table.perfect_clear_double,
+            3 => table.perfect_clear_triple,
+            4 if back_to_back => table.perfect_clear_back_to_back_tetris,
+            4 => table.perfect_clear_tetris,
+            _other => 0,
+        }
+    }
+    /// Points for the clear at level 1.
+    fn value(&self, table: &ScoreTable) -> u64 {
+        match (self.spin, self.rows) {
+            (SpinKind::NoSpin, 1) => table.single,
+            (SpinKind::NoSpin, 2) => table.double,
+            (SpinKind::NoSpin, 3) => table.triple,
+            (SpinKind::NoSpin, 4) => table.tetris,
+            (SpinKind::NoSpin, 5) => table.pentris,
+            (SpinKind::Mini | SpinKind::Immobile(_), 0) => table.t_spin_mini,
+            (SpinKind::Mini | SpinKind::Immobile(_), 1) => table.t_spin_mini_single,
+            (SpinKind::Mini | SpinKind::Immobile(_), 2) => table.t_spin_mini_double,
+            (SpinKind::Full, 0) => table.t_spin,
+            (SpinKind::Full, 1) => table.t_spin_single,
+            (SpinKind::Full, 2) => table.t_spin_double,
+            (SpinKind::Full, 3) => table.t_spin_triple,
+            _other => 0,
+        }
+    }
+}
+
+/// Points awarded for each way of scoring, at level 1. The default is the
+/// guideline scoring, and any value can be overridden in the `[scoring]`
+/// section of the config file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreTable {
+    pub single: u64,
+    pub double: u64,
+    pub triple: u64,
+    pub tetris: u64,
+    /// Five rows at once, which only a pentomino can clear.
+    pub pentris: u64,
+    pub t_spin_mini: u64,
+    pub t_spin_mini_single: u64,
+    pub t_spin_mini_double: u64,
+    pub t_spin: u64,
+    pub t_spin_single: u64,
+    pub t_spin_double: u64,
+    pub t_spin_triple: u64,
+    pub perfect_clear_single: u64,
+    pub perfect_clear_double: u64,
+    pub perfect_clear_triple: u64,
+    pub perfect_clear_tetris: u64,
+    pub perfect_clear_back_to_back_tetris: u64,
+    /// Points per combo step.
+    pub combo: u64,
+    /// Percentage of the normal points awarded for a back-to-back clear.
+    pub back_to_back_percent: u64,
+    /// Points per row soft dropped.
+    pub soft_drop: u64,
+    /// Points per row hard dropped.
+    pub hard_drop: u64,
+    /// Points for each row cleared when the zone ends, multiplied again by
+    /// the number of rows.
+    pub zone_line: u64,
+}
+impl ScoreTable {
+    /// Overrides values with any set in the `[scoring]` section of the
+    /// config, where keys match the field names.
+    pub fn read_config(&mut self, config: &Config) -> Result<(), ConfigError> {
+        let fields = [
+            ("single", &mut self.single),
+            ("double", &mut self.double),
+            ("triple", &mut self.triple),
+            ("tetris", &mut self.tetris),
+            ("pentris", &mut self.pentris),
+            ("t_spin_mini", &mut self.t_spin_mini),
+            ("t_spin_mini_single", &mut self.t_spin_mini_single),
+            ("t_spin_mini_double", &mut self.t_spin_mini_double),
+            ("t_spin", &mut self.t_spin),
+            ("t_spin_single", &mut self.t_spin_single),
+            ("t_spin_double", &mut self.t_spin_double),
+            ("t_spin_triple", &mut self.t_spin_triple),
+            ("perfect_clear_single", &mut self.perfect_clear_single),
+            ("perfect_clear_double", &mut self.perfect_clear_double),
+            ("perfect_clear_triple", &mut self.perfect_clear_triple),
+            ("perfect_clear_tetris", &mut self.perfect_clear_tetris),
+            (
+                "perfect_clear_back_to_back_tetris",
+                &mut self.perfect_clear_back_to_back_tetris,
+            ),
+            ("combo", &mut self.combo),
+            ("back_to_back_percent", &mut self.back_to_back_percent),
+            ("soft_drop", &mut self.soft_drop),
+            ("hard_drop", &mut self.hard_drop),
+            ("zone_line", &mut self.zone_line),
+        ];
+        for (name, field) in fields {
+            config.read(&format!("scoring.{}", name), field)?;
+        }
+        Ok(())
+    }
+}
+impl Default for ScoreTable {
+    fn default() -> Self {
+        Self {
+            single: 100,
+            double: 300,
+            triple: 500,
+            tetris: 800,
+            pentris: 1200,
+            t_spin_mini: 100,
+            t_spin_mini_single: 200,
+            t_spin_mini_double: 400,
+            t_spin: 400,
+            t_spin_single: 800,
+            t_spin_double: 1200,
+            t_spin_triple: 1600,
+            perfect_clear_single: 800,
+            perfect_clear_double: 1200,
+            perfect_clear_triple: 1800,
+            perfect_clear_tetris: 2000,
+            perfect_clear_back_to_back_tetris: 3200,
+            combo: 50,
+            back_to_back_percent: 150,
+            soft_drop: 1,
+            hard_drop: 2,
+            zone_line: 50,
+        }
+    }
+}
+
+/// Running totals for a game, kept for the whole game and shown in the HUD.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Score {
+    pub points: u64,
+    pub level: u64,
+    pub lines: u64,
+    /// Number of consecutive clears after the first in the current run of
+    /// line-clearing locks, or `None` if the last lock cleared nothing.
+    pub combo: Option<u64>,
+    /// Number of difficult clears after the first in the current unbroken
+    /// chain, or `None` if the last line clear was not difficult.
+    pub back_to_back: Option<u64>,
+    /// Level the game started at.
+    pub start_level: u64,
+    /// Lines to clear to advance each level.
+    pub lines_per_level: u64,
+    pub table: ScoreTable,
+}
+impl Default for Score {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Score {
+    const LINES_PER_LEVEL: u64 = 10;
+
+    pub fn new() -> Self {
+        Self::with_rules(Self::LINES_PER_LEVEL, ScoreTable::default())
+    }
+    pub fn with_rules(lines_per_level: u64, table: ScoreTable) -> Self {
+        Self {
+            points: 0,
+            level: 1,
+            start_level: 1,
+            lines: 0,
+            combo: None,
+            back_to_back: None,
+            lines_per_level: lines_per_level.max(1),
+            table,
+        }
+    }
+    /// Starts the game at a later level, which then advances as usual.
+    pub fn start_at_level(&mut self, level: u64) {
+        self.start_level = level.max(1);
+        self.level = self.start_level;
+    }
+    /// Lines still to clear before the level next advances.
+    pub fn lines_to_next_level(&self) -> u64 {
+        ((self.level + 1 - self.start_level) * self.lines_per_level).saturating_sub(self.lines)
+    }
+    /// Advances the level once enough lines have been cleared since the
+    /// start.
+    fn update_level(&mut self) {
+        self.level = cmp::max(
+            self.level,
+            self.start_level + self.lines / self.lines_per_level,
+        );
+    }
+    /// Awards the score from the score table for a locked piece at the current level,
+    /// then advances the level once enough lines have been cleared. Every
+    /// lock should be passed in, including those that clear nothing, so
+    /// that combos are broken. Returns the points awarded.
+    pub fn award_line_clear(&mut self, line_clear: &LineClear) -> u64 {
+        let mut awarded = line_clear.value(&self.table) * self.level;
+        let mut is_back_to_back = false;
+        if line_clear.is_difficult() {
+            if let Some(chain) = self.back_to_back {
+                awarded = awarded * self.table.back_to_back_percent / 100;
+                is_back_to_back = true;
+                self.back_to_back = Some(chain + 1);
+            } else {
+                self.back_to_back = Some(0);
+            }
+        } else if line_clear.rows > 0 {
+            self.back_to_back = None;
+        }
+        awarded += line_clear.perfect_clear_bonus(&self.table, is_back_to_back) * self.level;
+        if line_clear.rows > 0 {
+            let combo = self.combo.map_or(0, |combo| combo + 1);
+            awarded += self.table.combo * combo * self.level;
+            self.combo = Some(combo);
+        } else {
+            self.combo = None;
+        }
+        self.points += awarded;
+        self.lines += line_clear.rows as u64;
+        self.update_level();
+        return awarded;
+    }
+    /// Awards the score for rows cleared by a cascade after a lock, where
+    /// `step` is 1 for the first cascade, 2 for the next, and so on. Each
+    /// cascade scores as a plain line clear multiplied by one more than its
+    /// step. Returns the points awarded.
+    pub fn award_cascade(&mut self, step: u64, rows: u16) -> u64 {
+        let cascade = LineClear {
+            rows,
+            spin: SpinKind::NoSpin,
+            perfect_clear: false,
+        };
+        let awarded = cascade.value(&self.table) * (step + 1) * self.level;
+        self.points += awarded;
+        self.lines += rows as u64;
+        self.update_level();
+        return awarded;
+    }
+    /// Awards the score for the rows banked in the zone, cleared together
+    /// when it ends. Points grow with the square of the rows, so that four
+    /// rows score as a Tetris and each further row is worth more. Returns
+    /// the points awarded.
+    pub fn award_zone_clear(&mut self, rows: u16) -> u64 {
+        let rows = rows as u64;
+        let awarded = self.table.zone_line * rows * rows * self.level;
+        self.points += awarded;
+        self.lines += rows;
+        self.update_level();
+        return awarded;
+    }
+    /// Awards points for a piece dropped `rows` rows by the player.
+    pub fn award_drop(&mut self, rows: u64, points_per_row: u64) {
+        self.points += rows * points_per_row;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::PieceShape;
+
+    fn clear(rows: u16) -> LineClear {
+        LineClear {
+            rows,
+            spin: SpinKind::NoSpin,
+            perfect_clear: false,
+        }
+    }
+
+    #[test]
+    fn test_line_clears_score_guideline_values() {
+        let expected_points = [(1, 100), (2, 300), (3, 500), (4, 800), (0, 0)];
+        for (cleared_rows, points) in expected_points {
+            let mut score = Score::new();
+            assert_eq!(score.award_line_clear(&clear(cleared_rows)), points);
+            assert_eq!(score.points, points);
+        }
+    }
+
+    #[test]
+    fn test_pentominoes_clear_five_rows_as_a_pentris() {
+        assert_eq!(clear(5).name(), Some(String::from("Pentris")));
+        assert!(clear(5).is_difficult());
+        let mut score = Score::new();
+        assert_eq!(score.award_line_clear(&clear(5)), 1200);
+        assert_eq!(score.lines, 5);
+    }
+
+    #[test]
+    fn test_line_clears_scale_with_level() {
+        let mut score = Score::new();
+        score.level = 3;
+        assert_eq!(score.award_line_clear(&clear(4)), 2400);
+    }
+
+    #[test]
+    fn test_level_increases_every_ten_lines() {
+        let mut score = Score::new();
+        score.award_line_clear(&clear(4));
+        score.award_line_clear(&clear(4));
+        assert_eq!(score.level, 1);
+        score.award_line_clear(&clear(2));
+        assert_eq!(score.level, 2);
+        assert_eq!(score.lines, 10);
+    }
+    #[test]
+    fn test_lines_per_level_is_configurable() {
+        let mut score = Score::with_rules(5, ScoreTable::default());
+        score.award_line_clear(&clear(4));
+        assert_eq!(score.level, 1);
+        assert_eq!(score.lines_to_next_level(), 1);
+        score.award_line_clear(&clear(1));
+        assert_eq!(score.level, 2);
+        assert_eq!(score.lines_to_next_level(), 5);
+    }
+    #[test]
+    fn test_t_spins_score_guideline_values() {
+        let expected_points = [
+            (SpinKind::Full, 0, 400),
+            (SpinKind::Full, 1, 800),
+            (SpinKind::Full, 2, 1200),
+            (SpinKind::Full, 3, 1600),
+            (SpinKind::Mini, 0, 100),
+            (SpinKind::Mini, 1, 200),
+            (SpinKind::Mini, 2, 400),
+        ];
+        for (spin, rows, points) in expected_points {
+            let mut score = Score::new();
+            assert_eq!(
+                score.award_line_clear(&LineClear {
+                    rows,
+                    spin,
+                    perfect_clear: false,
+                }),
+                points
+            );
+        }
+    }
+
+    #[test]
+    fn test_line_clear_names() {
+        assert_eq!(clear(0).name(), None);
+        assert_eq!(clear(4).name(), Some(String::from("Tetris")));
+        let t_spin_double = LineClear {
+            rows: 2,
+            spin: SpinKind::Full,
+            perfect_clear: false,
+        };
+        assert_eq!(t_spin_double.name(), Some(String::from("T-Spin Double")));
+        let t_spin_mini = LineClear {
+            rows: 0,
+            spin: SpinKind::Mini,
+            perfect_clear: false,
+        };
+        assert_eq!(t_spin_mini.name(), Some(String::from("T-Spin Mini")));
+    }
+    #[test]
+    fn test_immobile_spins_score_as_minis() {
+        let l_spin_single = LineClear {
+            rows: 1,
+            spin: SpinKind::Immobile(PieceShape::L),
+            perfect_clear: false,
+        };
+        assert_eq!(l_spin_single.name(), Some(String::from("L-Spin Single")));
+        assert!(l_spin_single.is_difficult());
+        assert_eq!(Score::new().award_line_clear(&l_spin_single), 200);
+    }
+    #[test]
+    fn test_consecutive_clears_build_a_combo() {
+        let mut score = Score::new();
+        assert_eq!(score.award_line_clear(&clear(1)), 100);
+        assert_eq!(score.combo, Some(0));
+        assert_eq!(score.award_line_clear(&clear(1)), 150);
+        assert_eq!(score.award_line_clear(&clear(2)), 400);
+        assert_eq!(score.combo, Some(2));
+        score.award_line_clear(&clear(0));
+        assert_eq!(score.combo, None);
+        assert_eq!(score.award_line_clear(&clear(1)), 100);
+    }
+    #[test]
+    fn test_back_to_back_difficult_clears_earn_bonus() {
+        let mut score = Score::new();
+        let t_spin_single = LineClear {
+            rows: 1,
+            spin: SpinKind::Full,
+            perfect_clear: false,
+        };
+        assert_eq!(score.award_line_clear(&clear(4)), 800);
+        assert_eq!(score.back_to_back, Some(0));
+        score.award_line_clear(&clear(0));
+        assert_eq!(score.award_line_clear(&t_spin_single), 1200);
+        assert_eq!(score.back_to_back, Some(1));
+        score.award_line_clear(&clear(0));
+        assert_eq!(score.award_line_clear(&clear(4)), 1200);
+        assert_eq!(score.back_to_back, Some(2));
+    }
+
+    #[test]
+    fn test_easy_clear_breaks_back_to_back() {
+        let mut score = Score::new();
+        score.award_line_clear(&clear(4));
+        score.award_line_clear(&clear(0));
+        score.award_line_clear(&clear(1));
+        assert_eq!(score.back_to_back, None);
+        score.award_line_clear(&clear(0));
+        assert_eq!(score.award_line_clear(&clear(4)), 800);
+    }
+    #[test]
+    fn test_perfect_clear_earns_bonus() {
+        let mut score = Score::new();
+        let perfect_tetris = LineClear {
+            perfect_clear: true,
+            ..clear(4)
+        };
+        assert_eq!(score.award_line_clear(&perfect_tetris), 2800);
+        score.award_line_clear(&clear(0));
+        assert_eq!(score.award_line_clear(&perfect_tetris), 1200 + 3200);
+        let perfect_single = LineClear {
+            perfect_clear: true,
+            ..clear(1)
+        };
+        score.award_line_clear(&clear(0));
+        assert_eq!(score.award_line_clear(&perfect_single), 900);
+    }
+    #[test]
+    fn test_score_table_values_are_used() {
+        let table = ScoreTable {
+            single: 40,
+            combo: 0,
+            ..ScoreTable::default()
+        };
+        let mut score = Score::with_rules(10, table);
+        assert_eq!(score.award_line_clear(&clear(1)), 40);
+        assert_eq!(score.award_line_clear(&clear(1)), 40);
+    }
+    #[test]
+    fn test_cascades_score_more_further_down_the_chain() {
+        let mut score = Score::new();
+        score.award_line_clear(&clear(1));
+        assert_eq!(score.award_cascade(1, 1), 200);
+        assert_eq!(score.award_cascade(2, 2), 900);
+        assert_eq!(score.lines, 4);
+    }
+
+    #[test]
+    fn test_zone_clears_grow_with_the_square_of_the_rows() {
+        let mut score = Score::new();
+        assert_eq!(score.award_zone_clear(4), 800);
+        assert_eq!(score.award_zone_clear(8), 3200);
+        assert_eq!(score.lines, 12);
+        assert_eq!(score.level, 2);
+    }
+
+    #[test]
+    fn test_levels_advance_from_the_start_level() {
+        let mut score = Score::new();
+        score.start_at_level(5);
+        assert_eq!(score.lines_to_next_level(), 10);
+        score.award_line_clear(&clear(4));
+        score.award_line_clear(&clear(4));
+        assert_eq!(score.level, 5);
+        score.award_line_clear(&clear(2));
+        assert_eq!(score.level, 6);
+        assert_eq!(score.lines_to_next_level(), 10);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::board::{ClearStyle, GarbageRules};
+use crate::config::{Config, ConfigError};
+use crate::mode::GameMode;
+use crate::piece::PieceSet;
+use crate::puzzle::Puzzle;
+use crate::randomizer::Randomizer;
+use crate::rotation::{KickTable, RotationSystem};
+use crate::score::ScoreTable;
+
+/// Options that change how a game plays, fixed when the game starts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameSettings {
+    pub mode: GameMode,
+    /// How many times faster than gravity the piece falls while soft
+    /// dropping.
+    pub soft_drop_factor: u64,
+    /// Delayed Auto Shift: milliseconds a move key is held before the piece
+    /// starts moving on its own, see `Engine`.
+    pub das: u64,
+    /// Auto Repeat Rate: milliseconds between moves once auto shift starts.
+    pub arr: u64,
+    /// Percentage to lengthen DAS and the gravity interval by, to make
+    /// one-handed play more comfortable.
+    pub slowdown: u64,
+    /// Milliseconds within which pressing soft drop twice hard drops, see
+    /// `DoubleTap`. Zero turns this off.
+    pub double_tap_drop: u64,
+    /// Longest wait for a key, in milliseconds, each time input is read.
+    /// Lower is more responsive but busier. Drawing happens as inputs
+    /// arrive, so this does not change how often the board is drawn.
+    pub input_poll: u64,
+    /// Whether to measure how long inputs take from being read to being
+    /// applied, and show it once the game ends.
+    pub show_latency: bool,
+    /// Whether to show the keys held and the last commands given beside the
+    /// board, for streams and tutorials.
+    pub input_display: bool,
+    /// Mouse control: the piece follows the mouse sideways, left and right
+    /// click rotate and middle click hard drops.
+    pub mouse: bool,
+    /// Whether to draw the board with half block characters, two rows of
+    /// cells to each line of the terminal, so that cells are square and the
+    /// board is half as tall.
+    pub half_blocks: bool,
+    /// Whether to draw with plain ASCII and no colour, for terminals that
+    /// cannot show Unicode or colour. This takes over from half blocks.
+    pub ascii: bool,
+    /// Whether to leave out effects that only move for show, such as the
+    /// trail a hard drop leaves.
+    pub reduced_motion: bool,
+    pub top_out: TopOutRules,
+    pub lock_delay: LockDelayRules,
+    pub garbage: GarbageRules,
+    /// Initial Hold System: holding the hold key as a piece spawns holds it
+    /// straight away.
+    pub initial_hold: bool,
+    /// Initial Rotation System: holding a rotate key as a piece spawns
+    /// spawns it already rotated.
+    pub initial_rotation: bool,
+    /// Entry delay (ARE) in milliseconds between a piece locking and the
+    /// next piece spawning. Zero spawns the next piece straight away.
+    pub entry_delay: u64,
+    pub rotation_system: RotationSystem,
+    /// Kicks that replace the rotation system's own, if any.
+    pub kick_table: Option<KickTable>,
+    pub randomizer: Randomizer,
+    /// Seed the randomizer deals from, so that games with the same seed
+    /// get the same pieces. Without one every game's pieces differ.
+    pub seed: Option<u64>,
+    pub piece_set: PieceSet,
+    /// Level the game starts at.
+    pub start_level: u64,
+    /// Lines to clear to advance each level, which speeds up gravity and
+    /// multiplies the points scored.
+    pub lines_per_level: u64,
+    pub score_table: ScoreTable,
+    pub clear_style: ClearStyle,
+    /// Rows of garbage the board starts with in cheese mode.
+    pub cheese_rows: u64,
+    /// Strict finesse practice: a piece placed with more key presses than it
+    /// needed goes back to its spawn position instead of locking.
+    pub strict_finesse: bool,
+    /// The puzzle to play in puzzle mode.
+    pub puzzle: Option<Puzzle>,
+    /// Whether line clears fill a zone meter that can be spent to stop
+    /// gravity and bank cleared rows, see `Zone`.
+    pub zone: bool,
+    /// Whether pieces other than the T can spin, by being rotated into a
+    /// spot they cannot move out of.
+    pub all_spin: bool,
+}
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::Endless,
+            soft_drop_factor: 20,
+            das: 167,
+            arr: 33,
+            slowdown: 0,
+            double_tap_drop: 0,
+            input_poll: 2,
+            show_latency: false,
+            input_display: false,
+            mouse: false,
+            half_blocks: false,
+            ascii: false,
+            reduced_motion: false,
+            top_out: TopOutRules::default(),
+            lock_delay: LockDelayRules::default(),
+            garbage: GarbageRules::default(),
+            initial_hold: true,
+            initial_rotation: true,
+            entry_delay: 0,
+            rotation_system: RotationSystem::Srs,
+            kick_table: None,
+            randomizer: Randomizer::SevenBag,
+            seed: None,
+            piece_set: PieceSet::Tetrominoes,
+            start_level: 1,
+            lines_per_level: 10,
+            score_table: ScoreTable::default(),
+            clear_style: ClearStyle::Naive,
+            cheese_rows: 10,
+            strict_finesse: false,
+            puzzle: None,
+            zone: false,
+            all_spin: false,
+        }
+    }
+}
+
+impl GameSettings {
+    /// A time in milliseconds lengthened by the slowdown.
+    pub fn slowed(&self, millis: u64) -> u64 {
+        millis * (100 + self.slowdown) / 100
+    }
+    /// Settings from the config file, with the default for anything it
+    /// leaves out.
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let mut settings = Self::default();
+        config.read_choice("game.mode", &GameMode::choices(), &mut settings.mode)?;
+        config.read("game.soft_drop_factor", &mut settings.soft_drop_factor)?;
+        config.read("game.das", &mut settings.das)?;
+        config.read("game.arr", &mut settings.arr)?;
+        config.read("game.slowdown", &mut settings.slowdown)?;
+        config.read("game.double_tap_drop", &mut settings.double_tap_drop)?;
+        config.read("game.input_poll", &mut settings.input_poll)?;
+        config.read("game.show_latency", &mut settings.show_latency)?;
+        config.read("game.input_display", &mut settings.input_display)?;
+        config.read("game.mouse", &mut settings.mouse)?;
+        config.read("game.half_blocks", &mut settings.half_blocks)?;
+        config.read("game.ascii", &mut settings.ascii)?;
+        config.read("game.reduced_motion", &mut settings.reduced_motion)?;
+        config.read("game.initial_hold", &mut settings.initial_hold)?;
+        config.read("game.initial_rotation", &mut settings.initial_rotation)?;
+        config.read("game.entry_delay", &mut settings.entry_delay)?;
+        config.read("game.start_level", &mut settings.start_level)?;
+        config.read("game.lines_per_level", &mut settings.lines_per_level)?;
+        config.read("game.cheese_rows", &mut settings.cheese_rows)?;
+        config.read("game.strict_finesse", &mut settings.strict_finesse)?;
+        config.read("game.zone", &mut settings.zone)?;
+        config.read("game.all_spin", &mut settings.all_spin)?;
+        config.read_choice(
+            "game.rotation_system",
+            &[
+                ("srs", RotationSystem::Srs),
+                ("nintendo", RotationSystem::Nintendo),
+            ],
+            &mut settings.rotation_system,
+        )?;
+        config.read_choice(
+            "game.randomizer",
+            &[
+                ("7-bag", Randomizer::SevenBag),
+                ("tgm", Randomizer::TgmHistory),
+                ("random", Randomizer::PureRandom),
+            ],
+            &mut settings.randomizer,
+        )?;
+        config.read_choice(
+            "game.clear_style",
+            &[
+                ("naive", ClearStyle::Naive),
+                ("cascade", ClearStyle::Cascade),
+            ],
+            &mut settings.clear_style,
+        )?;
+        config.read_choice(
+            "game.piece_set",
+            &PieceSet::choices(),
+            &mut settings.piece_set,
+        )?;
+        config.read("top_out.block_out", &mut settings.top_out.block_out)?;
+        config.read("top_out.lock_out", &mut settings.top_out.lock_out)?;
+        config.read("top_out.garbage_out", &mut settings.top_out.garbage_out)?;
+        config.read("garbage.messiness", &mut settings.garbage.messiness)?;
+        config.read("garbage.cancel", &mut settings.garbage.cancel)?;
+        config.read("garbage.entry_delay", &mut settings.garbage.entry_delay)?;
+        config.read("lock_delay.delay", &mut settings.lock_delay.delay)?;
+        config.read("lock_delay.max_resets", &mut settings.lock_delay.max_resets)?;
+        config.read_choice(
+            "lock_delay.reset",
+            &[
+                ("move", LockReset::MoveReset),
+                ("step", LockReset::StepReset),
+                ("classic", LockReset::Classic),
+            ],
+            &mut settings.lock_delay.reset,
+        )?;
+        settings.kick_table = KickTable::from_config(config)?;
+        settings.score_table.read_config(config)?;
+        Ok(settings)
+    }
+}
+
+/// Which of the guideline top out conditions end the game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TopOutRules {
+    /// A new piece spawns overlapping the stack. With this off the piece is
+    /// pushed up into the vanish zone instead, if there is room.
+    pub block_out: bool,
+    /// A piece locks entirely above the visible board.
+    pub lock_out: bool,
+    /// Incoming garbage pushes filled cells above the top of the board.
+    pub garbage_out: bool,
+}
+impl Default for TopOutRules {
+    fn default() -> Self {
+        Self {
+            block_out: true,
+            lock_out: true,
+            garbage_out: true,
+        }
+    }
+}
+
+/// How long a piece resting on the stack waits before it locks, and what
+/// restarts the wait.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LockDelayRules {
+    /// Milliseconds a piece rests on the stack before locking. Ignored by
+    /// classic locking.
+    pub delay: u64,
+    pub reset: LockReset,
+    /// Most times moving or rotating can restart the delay for a piece,
+    /// with move reset. Reaching a new lowest row allows as many again.
+    pub max_resets: u64,
+}
+impl Default for LockDelayRules {
+    fn default() -> Self {
+        Self {
+            delay: 500,
+            reset: LockReset::MoveReset,
+            max_resets: 15,
+        }
+    }
+}
+
+/// What restarts the lock delay of a piece resting on the stack.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LockReset {
+    /// Any move or rotation restarts the delay, up to the reset limit.
+    MoveReset,
+    /// Only falling to a lower row restarts the delay.
+    StepReset,
+    /// No lock delay: the piece locks when gravity next pulls on it.
+    Classic,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_from_config() {
+        let config = Config::parse(
+            "[game]\nrandomizer = \"tgm\"\ndas = 100\narr = 0\ninput_poll = 5\n[top_out]\nblock_out = false\n[lock_delay]\nreset = \"step\"\n[scoring]\ntetris = 1200",
+        )
+        .unwrap();
+        let settings = GameSettings::from_config(&config).unwrap();
+        assert_eq!(settings.randomizer, Randomizer::TgmHistory);
+        assert!(!settings.top_out.block_out);
+        assert_eq!(settings.lock_delay.reset, LockReset::StepReset);
+        assert_eq!(settings.lock_delay.max_resets, 15);
+        assert_eq!(settings.score_table.tetris, 1200);
+        assert_eq!(settings.soft_drop_factor, 20);
+        assert_eq!((settings.das, settings.arr), (100, 0));
+        assert_eq!(settings.input_poll, 5);
+    }
+}
@@ -0,0 +1,213 @@
+use crate::board::{PiecePositionValidity, TetrisBoard};
+use crate::models::Coord;
+use crate::piece::{Orientation, PieceShape, TetrisPiece};
+
+/// How a piece was spun into its final position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpinKind {
+    NoSpin,
+    Mini,
+    Full,
+    /// A piece other than the T rotated into a spot it cannot move out of,
+    /// which scores as a T-spin mini.
+    Immobile(PieceShape),
+}
+
+// The SRS kick that lets a T piece twist into a T-spin triple slot. Using it
+// always counts as a full T-spin, even when the 3-corner rule says mini.
+const TST_KICK_INDEX: usize = 4;
+
+/// Detects a spin as the piece locks. T pieces follow `detect_t_spin`. With
+/// `all_spin` on, any other piece whose last successful action was a
+/// rotation spins if it cannot move left, right, up or down.
+pub fn detect_spin(
+    piece: &TetrisPiece,
+    board: &TetrisBoard,
+    last_rotation_kick: Option<usize>,
+    all_spin: bool,
+) -> SpinKind {
+    if piece.piece_shape() == PieceShape::T {
+        return detect_t_spin(piece, board, last_rotation_kick);
+    }
+    if !all_spin || last_rotation_kick.is_none() || piece.scale() != 1 {
+        return SpinKind::NoSpin;
+    }
+    let is_immobile = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+        .iter()
+        .all(|(cols, rows)| {
+            board.check_is_valid_position(&piece.shifted(*cols, *rows).coordinates())
+                != PiecePositionValidity::Valid
+        });
+    if is_immobile {
+        return SpinKind::Immobile(piece.piece_shape());
+    }
+    return SpinKind::NoSpin;
+}
+
+/// Detects a T-spin using the 3-corner rule. The piece must be a T whose
+/// last successful action was a rotation (`last_rotation_kick` holds the
+/// index of the kick that rotation used) and at least three of the four
+/// cells diagonal to its centre must be filled, with walls and the floor
+/// counting as filled. It is a full T-spin when both corners on the side
+/// the T points towards are filled, and a mini otherwise. Big pieces never
+/// spin.
+pub fn detect_t_spin(
+    piece: &TetrisPiece,
+    board: &TetrisBoard,
+    last_rotation_kick: Option<usize>,
+) -> SpinKind {
+    let kick_index = match last_rotation_kick {
+        Some(kick_index) => kick_index,
+        None => return SpinKind::NoSpin,
+    };
+    if piece.piece_shape() != PieceShape::T || piece.scale() != 1 {
+        return SpinKind::NoSpin;
+    }
+    let position = piece.position();
+    let is_filled = |col: i16, row: i16| {
+        let coord = Coord {
+            col: position.col + col,
+            row: position.row + row,
+        };
+        !TetrisBoard::is_on_board(&coord) || board.is_occupied(&coord)
+    };
+    // Corners of the T's 3x3 box, ordered clockwise from the top left.
+    let corners = [
+        is_filled(0, 0),
+        is_filled(2, 0),
+        is_filled(2, 2),
+        is_filled(0, 2),
+    ];
+    if corners.iter().filter(|filled| **filled).count() < 3 {
+        return SpinKind::NoSpin;
+    }
+    let front_corners = match piece.orientation() {
+        Orientation::Spawn => [0, 1],
+        Orientation::Right => [1, 2],
+        Orientation::Reverse => [2, 3],
+        Orientation::Left => [3, 0],
+    };
+    if front_corners.iter().all(|corner| corners[*corner]) || kick_index == TST_KICK_INDEX {
+        return SpinKind::Full;
+    }
+    return SpinKind::Mini;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::Rotation;
+
+    // A T-spin double slot at the bottom of the board, with the T sitting in
+    // it upside down.
+    fn t_spin_double_setup() -> (TetrisBoard, TetrisPiece) {
+        let mut tetris_board = TetrisBoard::new();
+        for col in 0..TetrisBoard::NUM_COLS {
+            if col != 4 {
+                tetris_board.board[19][col] = true;
+            }
+            if !(3..=5).contains(&col) {
+                tetris_board.board[18][col] = true;
+            }
+        }
+        tetris_board.board[17][3] = true;
+        let tetris_piece = TetrisPiece::new(&PieceShape::T)
+            .rotated(Rotation::Clockwise)
+            .rotated(Rotation::Clockwise)
+            .shifted(0, 17);
+        (tetris_board, tetris_piece)
+    }
+
+    #[test]
+    fn test_t_spin_detected_with_three_corners() {
+        let (tetris_board, tetris_piece) = t_spin_double_setup();
+        assert_eq!(
+            detect_t_spin(&tetris_piece, &tetris_board, Some(0)),
+            SpinKind::Full
+        );
+    }
+
+    #[test]
+    fn test_no_spin_without_rotation() {
+        let (tetris_board, tetris_piece) = t_spin_double_setup();
+        assert_eq!(
+            detect_t_spin(&tetris_piece, &tetris_board, None),
+            SpinKind::NoSpin
+        );
+    }
+
+    #[test]
+    fn test_no_spin_with_two_corners() {
+        let (mut tetris_board, tetris_piece) = t_spin_double_setup();
+        tetris_board.board[17][3] = false;
+        assert_eq!(
+            detect_t_spin(&tetris_piece, &tetris_board, Some(0)),
+            SpinKind::NoSpin
+        );
+    }
+
+    #[test]
+    fn test_mini_when_front_corner_open() {
+        let mut tetris_board = TetrisBoard::new();
+        // T pointing up, resting on the floor in the bottom left corner, so
+        // the floor fills the two corners behind it.
+        let tetris_piece = TetrisPiece::new(&PieceShape::T).shifted(-3, 18);
+        assert_eq!(
+            detect_t_spin(&tetris_piece, &tetris_board, Some(1)),
+            SpinKind::NoSpin
+        );
+        tetris_board.board[18][0] = true;
+        assert_eq!(
+            detect_t_spin(&tetris_piece, &tetris_board, Some(1)),
+            SpinKind::Mini
+        );
+        assert_eq!(
+            detect_t_spin(&tetris_piece, &tetris_board, Some(TST_KICK_INDEX)),
+            SpinKind::Full
+        );
+        tetris_board.board[18][2] = true;
+        assert_eq!(
+            detect_t_spin(&tetris_piece, &tetris_board, Some(1)),
+            SpinKind::Full
+        );
+    }
+
+    #[test]
+    fn test_only_t_pieces_spin() {
+        let (tetris_board, _) = t_spin_double_setup();
+        let tetris_piece = TetrisPiece::new(&PieceShape::L).shifted(0, 17);
+        assert_eq!(
+            detect_t_spin(&tetris_piece, &tetris_board, Some(0)),
+            SpinKind::NoSpin
+        );
+    }
+
+    #[test]
+    fn test_all_spin_needs_an_immobile_piece() {
+        let tetris_piece = TetrisPiece::new(&PieceShape::L).shifted(0, 17);
+        let mut tetris_board = TetrisBoard::new();
+        assert_eq!(
+            detect_spin(&tetris_piece, &tetris_board, Some(0), true),
+            SpinKind::NoSpin
+        );
+        // Fill the bottom rows around the L, boxing it in.
+        for row in 16..TetrisBoard::NUM_ROWS {
+            tetris_board.board[row] = vec![true; TetrisBoard::NUM_COLS];
+        }
+        for coord in tetris_piece.coordinates() {
+            tetris_board.board[coord.row as usize][coord.col as usize] = false;
+        }
+        assert_eq!(
+            detect_spin(&tetris_piece, &tetris_board, Some(0), true),
+            SpinKind::Immobile(PieceShape::L)
+        );
+        assert_eq!(
+            detect_spin(&tetris_piece, &tetris_board, None, true),
+            SpinKind::NoSpin
+        );
+        assert_eq!(
+            detect_spin(&tetris_piece, &tetris_board, Some(0), false),
+            SpinKind::NoSpin
+        );
+    }
+}
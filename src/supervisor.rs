@@ -0,0 +1,122 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+
+/// A signal shared with supervised threads, telling them to finish up.
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+}
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+}
+
+/// Owns the threads the game spawns, so that none is left running or
+/// panics unnoticed once it is done with. Each thread is handed the
+/// supervisor's `Shutdown` signal to check, or may stop some other way,
+/// e.g. when a channel it waits on closes. Shutting down, or dropping the
+/// supervisor, signals every thread and waits for it to finish, logging any
+/// that panicked.
+#[derive(Debug, Default)]
+pub struct Supervisor {
+    shutdown: Shutdown,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+impl Supervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Spawns a thread named `name` running `work`, which is given the
+    /// shutdown signal.
+    ///
+    /// Panics if the thread cannot be created, as `thread::spawn` does.
+    pub fn spawn<F>(&mut self, name: &str, work: F)
+    where
+        F: FnOnce(Shutdown) + Send + 'static,
+    {
+        let shutdown = self.shutdown.clone();
+        let thread = thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || work(shutdown))
+            .expect("Failed to spawn thread.");
+        self.threads.push(thread);
+    }
+    /// Number of threads still to be joined.
+    pub fn len(&self) -> usize {
+        self.threads.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.threads.is_empty()
+    }
+    /// Signals every thread to shut down and waits for them all to finish,
+    /// returning the names of any that panicked.
+    pub fn shutdown(&mut self) -> Vec<String> {
+        self.shutdown.request();
+        let mut panicked = Vec::new();
+        for thread in self.threads.drain(..) {
+            let name = thread.thread().name().unwrap_or("unnamed").to_string();
+            if thread.join().is_err() {
+                log::error!("Thread {} panicked.", name);
+                panicked.push(name);
+            }
+        }
+        panicked
+    }
+}
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// The supervisor for threads that live as long as the program, such as the
+/// terminal event reader. It is shut down before the program exits.
+pub fn global() -> &'static Mutex<Supervisor> {
+    static SUPERVISOR: OnceLock<Mutex<Supervisor>> = OnceLock::new();
+    SUPERVISOR.get_or_init(|| Mutex::new(Supervisor::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_shutdown_stops_and_joins_every_thread() {
+        let mut supervisor = Supervisor::new();
+        let (sender, receiver) = mpsc::channel();
+        for index in 0..3 {
+            let sender = sender.clone();
+            supervisor.spawn(&format!("worker-{}", index), move |shutdown| {
+                while !shutdown.is_requested() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                sender.send(index).unwrap();
+            });
+        }
+        drop(sender);
+        assert_eq!(supervisor.len(), 3);
+        assert!(supervisor.shutdown().is_empty());
+        assert!(supervisor.is_empty());
+        let mut stopped: Vec<_> = receiver.iter().collect();
+        stopped.sort();
+        assert_eq!(stopped, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_shutdown_reports_threads_that_panicked() {
+        let mut supervisor = Supervisor::new();
+        supervisor.spawn("fine", |_| {});
+        supervisor.spawn("broken", |_| panic!("Broken on purpose."));
+        assert_eq!(supervisor.shutdown(), vec!["broken".to_string()]);
+    }
+}
@@ -0,0 +1,316 @@
+use crossterm::style::Color;
+
+use crate::config::{Config, ConfigError, ConfigValue};
+use crate::piece::{PieceSet, PieceShape};
+
+/// Colours the game is drawn in: one for each piece, and ones for the
+/// walls of the board, the ghost piece and text. The `[theme]` section of
+/// the config picks a palette by `name`, either built in (`classic`,
+/// `pastel` or `monochrome`) or defined in a `[themes.<name>]` section of
+/// its own, then recolours anything it sets. Pieces are keyed by their
+/// letter, e.g. `T = "dark_magenta"` or `I = "#00c0ff"`, and the rest by
+/// `border`, `ghost` and `text`. `ghost_style` picks how the ghost piece
+/// is drawn, see `GhostStyle`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    colors: Vec<(PieceShape, Color)>,
+    pub border: Color,
+    pub ghost: Color,
+    pub text: Color,
+    pub ghost_style: GhostStyle,
+}
+
+/// How the ghost piece, showing where the active piece would land, is
+/// drawn. Terminals differ a lot in how well a dim colour reads, so it can
+/// be drawn without relying on one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GhostStyle {
+    /// The outline of each cell, in the piece's colour.
+    Outline,
+    /// The piece's blocks in the theme's ghost colour.
+    Dim,
+    /// A glyph of its own that needs no colour to tell it apart.
+    Glyph,
+    /// No ghost piece.
+    Off,
+}
+impl GhostStyle {
+    pub fn choices() -> [(&'static str, Self); 4] {
+        [
+            ("outline", Self::Outline),
+            ("dim", Self::Dim),
+            ("glyph", Self::Glyph),
+            ("off", Self::Off),
+        ]
+    }
+}
+impl Default for Theme {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+impl Theme {
+    /// Names of the built in palettes.
+    pub const NAMES: [&'static str; 3] = ["classic", "pastel", "monochrome"];
+
+    /// Guideline piece colours, in the terminal's own colours.
+    pub fn classic() -> Self {
+        Self::with_pieces(guideline_color, Color::Reset, Color::DarkGrey, Color::Reset)
+    }
+    /// Soft colours, which need a terminal with true colour.
+    pub fn pastel() -> Self {
+        Self::with_pieces(
+            pastel_color,
+            Color::Rgb {
+                r: 0xb8,
+                g: 0xb8,
+                b: 0xd0,
+            },
+            Color::Rgb {
+                r: 0x70,
+                g: 0x70,
+                b: 0x80,
+            },
+            Color::Reset,
+        )
+    }
+    /// No colour at all, for terminals without it. The ghost piece is drawn
+    /// with a glyph of its own, as it cannot be dimmed.
+    pub fn monochrome() -> Self {
+        Self {
+            ghost_style: GhostStyle::Glyph,
+            ..Self::with_pieces(|_| Color::Reset, Color::Reset, Color::Reset, Color::Reset)
+        }
+    }
+    /// The built in palette called `name`.
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Self::classic()),
+            "pastel" => Some(Self::pastel()),
+            "monochrome" => Some(Self::monochrome()),
+            _other => None,
+        }
+    }
+    fn with_pieces(
+        piece_color: fn(PieceShape) -> Color,
+        border: Color,
+        ghost: Color,
+        text: Color,
+    ) -> Self {
+        let colors = PieceSet::ALL
+            .iter()
+            .flat_map(|piece_set| piece_set.shapes())
+            .map(|piece_shape| (*piece_shape, piece_color(*piece_shape)))
+            .collect();
+        Self {
+            colors,
+            border,
+            ghost,
+            text,
+            ghost_style: GhostStyle::Dim,
+        }
+    }
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let mut name = String::from("classic");
+        config.read("theme.name", &mut name)?;
+        let mut theme = match Self::named(&name) {
+            Some(theme) => theme,
+            None => Self::user_defined(config, &name)?,
+        };
+        theme.read_colors(config, "theme")?;
+        Ok(theme)
+    }
+    /// The palette defined in the `[themes.<name>]` section, starting from
+    /// the built in palette named by its `base`, or `classic`.
+    fn user_defined(config: &Config, name: &str) -> Result<Self, ConfigError> {
+        let section = format!("themes.{}", name);
+        if !config.has_section(&section) {
+            let names = Self::NAMES.join(", ");
+            return Err(config.error_at(
+                "theme.name",
+                &format!(
+                    "unknown theme `{}`: use one of {} or define it in [{}]",
+                    name, names, section
+                ),
+            ));
+        }
+        let base_key = format!("{}.base", section);
+        let mut base = String::from("classic");
+        config.read(&base_key, &mut base)?;
+        let mut theme = Self::named(&base).ok_or_else(|| {
+            config.error_at(
+                &base_key,
+                &format!("`{}` must be one of: {}", base_key, Self::NAMES.join(", ")),
+            )
+        })?;
+        theme.read_colors(config, &section)?;
+        Ok(theme)
+    }
+    /// Overwrites every colour set in `section` of the config, and the ghost
+    /// style if it is set.
+    fn read_colors(&mut self, config: &Config, section: &str) -> Result<(), ConfigError> {
+        for (piece_shape, color) in self.colors.iter_mut() {
+            config.read(&format!("{}.{}", section, piece_shape.name()), color)?;
+        }
+        config.read(&format!("{}.border", section), &mut self.border)?;
+        config.read(&format!("{}.ghost", section), &mut self.ghost)?;
+        config.read(&format!("{}.text", section), &mut self.text)?;
+        config.read_choice(
+            &format!("{}.ghost_style", section),
+            &GhostStyle::choices(),
+            &mut self.ghost_style,
+        )?;
+        Ok(())
+    }
+    pub fn color(&self, piece_shape: PieceShape) -> Color {
+        self.colors
+            .iter()
+            .find(|(shape, _)| *shape == piece_shape)
+            .map_or_else(|| guideline_color(piece_shape), |(_, color)| *color)
+    }
+}
+
+/// Orange from the 256 colour palette, as the basic colours have none.
+const ORANGE: Color = Color::AnsiValue(208);
+
+/// The guideline colour of each tetromino. Pentominoes share the colour of
+/// the tetromino with the same letter, and the rest take darker shades.
+fn guideline_color(piece_shape: PieceShape) -> Color {
+    match piece_shape {
+        PieceShape::I | PieceShape::I5 => Color::Cyan,
+        PieceShape::O => Color::Yellow,
+        PieceShape::T | PieceShape::T5 => Color::Magenta,
+        PieceShape::S => Color::Green,
+        PieceShape::Z | PieceShape::Z5 => Color::Red,
+        PieceShape::J => Color::Blue,
+        PieceShape::L | PieceShape::L5 => ORANGE,
+        PieceShape::F => Color::DarkGreen,
+        PieceShape::N => Color::DarkRed,
+        PieceShape::P => Color::DarkYellow,
+        PieceShape::U => Color::DarkCyan,
+        PieceShape::V => Color::DarkBlue,
+        PieceShape::W => Color::DarkMagenta,
+        PieceShape::X => Color::White,
+        PieceShape::Y => Color::Grey,
+    }
+}
+
+/// A pastel shade of each piece's guideline colour.
+fn pastel_color(piece_shape: PieceShape) -> Color {
+    let (r, g, b) = match piece_shape {
+        PieceShape::I | PieceShape::I5 => (0xa8, 0xe6, 0xf0),
+        PieceShape::O => (0xf8, 0xee, 0xa8),
+        PieceShape::T | PieceShape::T5 => (0xd8, 0xb4, 0xf0),
+        PieceShape::S => (0xb4, 0xe8, 0xb4),
+        PieceShape::Z | PieceShape::Z5 => (0xf4, 0xb0, 0xb4),
+        PieceShape::J => (0xb0, 0xc4, 0xf4),
+        PieceShape::L | PieceShape::L5 => (0xf8, 0xcc, 0xa0),
+        PieceShape::F => (0x98, 0xc8, 0x98),
+        PieceShape::N => (0xd8, 0x98, 0x9c),
+        PieceShape::P => (0xd8, 0xcc, 0x90),
+        PieceShape::U => (0x98, 0xcc, 0xd4),
+        PieceShape::V => (0x98, 0xa8, 0xd8),
+        PieceShape::W => (0xc4, 0xa0, 0xd8),
+        PieceShape::X => (0xf0, 0xf0, 0xf0),
+        PieceShape::Y => (0xc8, 0xc8, 0xc8),
+    };
+    Color::Rgb { r, g, b }
+}
+
+/// Colours are read as a terminal colour name, `orange`, `purple` or a
+/// `#rrggbb` hex code.
+impl ConfigValue for Color {
+    fn parse(raw: &str) -> Option<Self> {
+        let name = String::parse(raw)?;
+        if let Some(hex) = name.strip_prefix('#') {
+            if hex.len() != 6 {
+                return None;
+            }
+            let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+            return Some(Color::Rgb {
+                r: channel(0)?,
+                g: channel(2)?,
+                b: channel(4)?,
+            });
+        }
+        match name.as_str() {
+            "orange" => Some(ORANGE),
+            "purple" => Some(Color::Magenta),
+            other => Color::try_from(other).ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pieces_have_guideline_colors() {
+        let theme = Theme::default();
+        assert_eq!(theme.color(PieceShape::I), Color::Cyan);
+        assert_eq!(theme.color(PieceShape::O), Color::Yellow);
+        assert_eq!(theme.color(PieceShape::T), Color::Magenta);
+        assert_eq!(theme.color(PieceShape::L), ORANGE);
+    }
+
+    #[test]
+    fn test_theme_overrides_colors_from_config() {
+        let config =
+            Config::parse("[theme]\nT = \"purple\"\nI = \"#00c0ff\"\nS = \"dark_green\"").unwrap();
+        let theme = Theme::from_config(&config).unwrap();
+        assert_eq!(
+            theme.color(PieceShape::I),
+            Color::Rgb {
+                r: 0,
+                g: 0xc0,
+                b: 0xff
+            }
+        );
+        assert_eq!(theme.color(PieceShape::S), Color::DarkGreen);
+        assert_eq!(theme.color(PieceShape::T), Color::Magenta);
+        assert_eq!(theme.color(PieceShape::O), Color::Yellow);
+    }
+
+    #[test]
+    fn test_palettes_are_picked_by_name() {
+        let config = Config::parse("[theme]\nname = \"monochrome\"\nghost = \"grey\"").unwrap();
+        let theme = Theme::from_config(&config).unwrap();
+        assert_eq!(theme.color(PieceShape::T), Color::Reset);
+        assert_eq!(theme.ghost, Color::Grey);
+        assert_eq!(theme.ghost_style, GhostStyle::Glyph);
+        let config = Config::parse("[theme]\nghost_style = \"outline\"").unwrap();
+        let theme = Theme::from_config(&config).unwrap();
+        assert_eq!(theme.ghost_style, GhostStyle::Outline);
+        let config = Config::parse("[theme]\nghost_style = \"faint\"").unwrap();
+        assert!(Theme::from_config(&config).is_err());
+        let config = Config::parse("[theme]\nname = \"neon\"").unwrap();
+        assert!(Theme::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_user_defined_themes_start_from_their_base() {
+        let config = Config::parse(
+            "[theme]\nname = \"mine\"\n[themes.mine]\nbase = \"pastel\"\nborder = \"blue\"\nO = \"white\"",
+        )
+        .unwrap();
+        let theme = Theme::from_config(&config).unwrap();
+        assert_eq!(theme.border, Color::Blue);
+        assert_eq!(theme.color(PieceShape::O), Color::White);
+        assert_eq!(
+            theme.color(PieceShape::I),
+            Theme::pastel().color(PieceShape::I)
+        );
+        let config =
+            Config::parse("[theme]\nname = \"mine\"\n[themes.mine]\nbase = \"mine\"").unwrap();
+        assert!(Theme::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_unknown_colors_are_errors() {
+        let config = Config::parse("[theme]\nO = \"gold\"").unwrap();
+        assert!(Theme::from_config(&config).is_err());
+        let config = Config::parse("[theme]\nO = \"#fff\"").unwrap();
+        assert!(Theme::from_config(&config).is_err());
+    }
+}
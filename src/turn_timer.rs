@@ -1,2 +1,3 @@
 pub mod observer;
+pub mod stopwatch;
 pub mod turn_timer;
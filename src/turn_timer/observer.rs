@@ -1,38 +1,156 @@
-use std::sync::mpsc;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Weak};
 pub trait Notifier<T: std::clone::Clone> {
-    fn add_subscriber(&mut self, subscriber: &mut impl Subscriber<T>) {
+    /// Subscribes a subscriber, for as long as the returned subscription is
+    /// kept. Channels of dropped subscriptions are removed first.
+    fn add_subscriber(&mut self, subscriber: &mut impl Subscriber<T>) -> Subscription {
         let (notifier_sender, notifier_receiver) = mpsc::channel::<T>();
-        self.set_subscribers().push(notifier_sender);
+        let subscription = Arc::new(());
+        let subscribers = self.set_subscribers();
+        subscribers.retain(SubscriberChannel::is_subscribed);
+        subscribers.push(SubscriberChannel {
+            sender: notifier_sender,
+            subscription: Arc::downgrade(&subscription),
+        });
         subscriber.add_subscription(notifier_receiver);
+        Subscription {
+            _alive: subscription,
+        }
     }
-    fn set_subscribers(&mut self) -> &mut Vec<mpsc::Sender<T>>;
-    fn subscribers(&self) -> &Vec<mpsc::Sender<T>>;
+    fn set_subscribers(&mut self) -> &mut Vec<SubscriberChannel<T>>;
+    fn subscribers(&self) -> &Vec<SubscriberChannel<T>>;
     fn notify(&self, context: &T) {
         for subscriber in self.subscribers() {
-            if let Err(_) = subscriber.send(context.clone()) {
+            if !subscriber.is_subscribed() {
+                continue;
+            }
+            if subscriber.sender.send(context.clone()).is_err() {
                 log::warn!("Attempted to send message on a closed channel.")
             };
         }
     }
 }
 
+/// Keeps a subscriber subscribed to a notifier. Dropping it unsubscribes.
+#[must_use = "dropping a subscription unsubscribes it"]
+#[derive(Debug)]
+pub struct Subscription {
+    /// Only held: the notifier's channel is live while this is.
+    _alive: Arc<()>,
+}
+
+/// A notifier's channel to one of its subscribers, sent on while the
+/// subscription is kept.
+#[derive(Debug)]
+pub struct SubscriberChannel<T> {
+    sender: mpsc::Sender<T>,
+    subscription: Weak<()>,
+}
+impl<T> SubscriberChannel<T> {
+    pub fn is_subscribed(&self) -> bool {
+        self.subscription.strong_count() > 0
+    }
+}
+impl<T> Clone for SubscriberChannel<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            subscription: self.subscription.clone(),
+        }
+    }
+}
+
 pub trait Subscriber<T: std::clone::Clone> {
     fn update(&mut self);
     fn add_subscription(&mut self, reciever: mpsc::Receiver<T>);
 }
 
+/// Identifies a subscription to an `EventBus`, to unsubscribe with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// A subscription's sender, boxed from an `mpsc::Sender` of its event type.
+type BoxedSender = Box<dyn Any + Send>;
+
+/// Sends events of any type to whoever has subscribed to that type, so
+/// that score events, garbage events or sound cues can be sent the way the
+/// turn timer sends its status. Each subscription is a channel of its own.
+#[derive(Default)]
+pub struct EventBus {
+    next_id: u64,
+    /// The senders of each event type.
+    channels: HashMap<TypeId, Vec<(SubscriptionId, BoxedSender)>>,
+}
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Subscribes to events of type `E`, returning the subscription and
+    /// the receiver they arrive on.
+    pub fn subscribe<E: Clone + Send + 'static>(&mut self) -> (SubscriptionId, mpsc::Receiver<E>) {
+        let (sender, receiver) = mpsc::channel::<E>();
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        self.channels
+            .entry(TypeId::of::<E>())
+            .or_default()
+            .push((id, Box::new(sender)));
+        (id, receiver)
+    }
+    /// Subscribes a subscriber to events of its type.
+    pub fn add_subscriber<E: Clone + Send + 'static>(
+        &mut self,
+        subscriber: &mut impl Subscriber<E>,
+    ) -> SubscriptionId {
+        let (id, receiver) = self.subscribe::<E>();
+        subscriber.add_subscription(receiver);
+        id
+    }
+    /// Stops sending events to a subscription. Returns whether it was
+    /// subscribed.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        for senders in self.channels.values_mut() {
+            if let Some(index) = senders.iter().position(|(sub_id, _)| *sub_id == id) {
+                senders.remove(index);
+                return true;
+            }
+        }
+        false
+    }
+    /// Sends an event to every subscriber to its type. Subscriptions whose
+    /// receiver has been dropped are unsubscribed.
+    pub fn publish<E: Clone + Send + 'static>(&mut self, event: &E) {
+        let Some(senders) = self.channels.get_mut(&TypeId::of::<E>()) else {
+            return;
+        };
+        senders.retain(
+            |(_, sender)| match sender.downcast_ref::<mpsc::Sender<E>>() {
+                Some(sender) => sender.send(event.clone()).is_ok(),
+                None => false,
+            },
+        );
+    }
+    /// How many subscriptions there are to events of type `E`.
+    pub fn subscriber_count<E: 'static>(&self) -> usize {
+        self.channels
+            .get(&TypeId::of::<E>())
+            .map_or(0, |senders| senders.len())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     struct TestNotifier<T> {
-        subscribers: Vec<mpsc::Sender<T>>,
+        subscribers: Vec<SubscriberChannel<T>>,
     }
     impl<T: std::clone::Clone> Notifier<T> for TestNotifier<T> {
-        fn set_subscribers(&mut self) -> &mut Vec<mpsc::Sender<T>> {
+        fn set_subscribers(&mut self) -> &mut Vec<SubscriberChannel<T>> {
             &mut self.subscribers
         }
-        fn subscribers(&self) -> &Vec<mpsc::Sender<T>> {
+        fn subscribers(&self) -> &Vec<SubscriberChannel<T>> {
             &self.subscribers
         }
     }
@@ -63,7 +181,7 @@ mod tests {
             value: "".to_string(),
             subscription: None,
         };
-        notifier.add_subscriber(&mut subscriber);
+        let _subscription = notifier.add_subscriber(&mut subscriber);
 
         assert_eq!(notifier.subscribers.len(), 1);
         assert!(subscriber.subscription.is_some());
@@ -80,4 +198,56 @@ mod tests {
         };
         notifier.notify(&"notified!".to_string());
     }
+
+    #[test]
+    fn test_dropping_a_subscription_unsubscribes() {
+        let mut notifier: TestNotifier<String> = TestNotifier {
+            subscribers: Vec::new(),
+        };
+        let mut subscriber = TestSubscriber {
+            value: "".to_string(),
+            subscription: None,
+        };
+        let subscription = notifier.add_subscriber(&mut subscriber);
+        drop(subscription);
+        notifier.notify(&"notified!".to_string());
+        subscriber.update();
+        assert_eq!(subscriber.value, "");
+
+        let _subscription = notifier.add_subscriber(&mut subscriber);
+        assert_eq!(notifier.subscribers.len(), 1);
+    }
+
+    #[test]
+    fn test_event_bus_sends_events_by_type() {
+        let mut bus = EventBus::new();
+        let (_, words) = bus.subscribe::<String>();
+        let (_, numbers) = bus.subscribe::<u64>();
+        let mut subscriber = TestSubscriber {
+            value: "".to_string(),
+            subscription: None,
+        };
+        bus.add_subscriber(&mut subscriber);
+        bus.publish(&"published!".to_string());
+        bus.publish(&7_u64);
+        subscriber.update();
+        assert_eq!(subscriber.value, "published!");
+        assert_eq!(words.try_iter().collect::<Vec<_>>(), vec!["published!"]);
+        assert_eq!(numbers.try_iter().collect::<Vec<_>>(), vec![7]);
+    }
+
+    #[test]
+    fn test_event_bus_unsubscribes() {
+        let mut bus = EventBus::new();
+        let (id, numbers) = bus.subscribe::<u64>();
+        let (_, dropped) = bus.subscribe::<u64>();
+        drop(dropped);
+        assert_eq!(bus.subscriber_count::<u64>(), 2);
+        bus.publish(&1_u64);
+        assert_eq!(bus.subscriber_count::<u64>(), 1);
+        assert!(bus.unsubscribe(id));
+        assert!(!bus.unsubscribe(id));
+        bus.publish(&2_u64);
+        assert_eq!(numbers.try_iter().collect::<Vec<_>>(), vec![1]);
+    }
 }
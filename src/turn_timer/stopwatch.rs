@@ -0,0 +1,79 @@
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, SystemClock};
+
+/// Measures how long a game has been played, leaving out time spent
+/// paused, for the HUD clock and results screens. Runs from when it is
+/// made, taking the time from a `Clock`.
+#[derive(Debug, Clone)]
+pub struct Stopwatch<C: Clock = SystemClock> {
+    clock: C,
+    started: Instant,
+    paused_for: Duration,
+    paused_at: Option<Instant>,
+}
+impl Stopwatch {
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock)
+    }
+}
+impl Default for Stopwatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<C: Clock> Stopwatch<C> {
+    pub fn with_clock(clock: C) -> Self {
+        Self {
+            started: clock.now(),
+            clock,
+            paused_for: Duration::ZERO,
+            paused_at: None,
+        }
+    }
+    /// Time played, not counting time paused.
+    pub fn elapsed(&self) -> Duration {
+        let now = self.paused_at.unwrap_or_else(|| self.clock.now());
+        now.duration_since(self.started)
+            .saturating_sub(self.paused_for)
+    }
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+    pub fn pause(&mut self) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(self.clock.now());
+        }
+    }
+    /// Carries on timing, returning how long it was paused for.
+    pub fn resume(&mut self) -> Duration {
+        let Some(paused_at) = self.paused_at.take() else {
+            return Duration::ZERO;
+        };
+        let paused = self.clock.now().duration_since(paused_at);
+        self.paused_for += paused;
+        paused
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_stopwatch_leaves_out_time_paused() {
+        let clock = MockClock::new();
+        let mut stopwatch = Stopwatch::with_clock(clock.clone());
+        clock.advance(Duration::from_secs(3));
+        assert_eq!(stopwatch.elapsed(), Duration::from_secs(3));
+        stopwatch.pause();
+        clock.advance(Duration::from_secs(10));
+        assert!(stopwatch.is_paused());
+        assert_eq!(stopwatch.elapsed(), Duration::from_secs(3));
+        assert_eq!(stopwatch.resume(), Duration::from_secs(10));
+        assert_eq!(stopwatch.resume(), Duration::ZERO);
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(stopwatch.elapsed(), Duration::from_secs(5));
+    }
+}
@@ -1,42 +1,310 @@
-pub use crate::turn_timer::observer::{Notifier, Subscriber};
+use crate::clock::{Clock, SystemClock};
+use crate::supervisor::Supervisor;
+pub use crate::turn_timer::observer::{Notifier, Subscriber, SubscriberChannel, Subscription};
 use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 #[derive(Clone, PartialEq, Debug)]
 pub enum TimerStatus {
     TimerNotComplete,
+    /// Sent every tick interval while the timer counts down, with the time
+    /// left, e.g. for a countdown bar.
+    TimerTick(Duration),
+    /// Sent once a run is nearly over, with the time left, e.g. to flash
+    /// the border or play a warning cue.
+    TimerWarning(Duration),
     TimerComplete,
 }
 
-pub struct TurnTimer {
-    timer_duration: u64,
-    subscribers: Vec<mpsc::Sender<TimerStatus>>,
+/// Tells its subscribers once a turn's time is up. One timer counts down
+/// every turn: each run restarts it, on the same thread and with the same
+/// subscribers. A run can be cancelled, and dropping the timer stops its
+/// thread, so that no notification arrives after a turn is abandoned. The
+/// time is taken from a `Clock`, the system clock unless given another.
+pub struct TurnTimer<C: Clock = SystemClock> {
+    clock: C,
+    timer_duration: Duration,
+    /// How often subscribers are told the time left, if at all.
+    tick_interval: Option<Duration>,
+    /// The fraction of a run left when subscribers are warned, if at all.
+    warning: Option<f64>,
+    subscribers: Vec<SubscriberChannel<TimerStatus>>,
+    /// Controls the countdown thread, once the timer has first been run.
+    control: Option<mpsc::Sender<TimerControl>>,
+    /// Owns the countdown thread, joining it when the timer is dropped.
+    threads: Supervisor,
 }
 impl TurnTimer {
-    pub fn new(timer_duration: u64) -> TurnTimer {
+    pub fn new(timer_duration: Duration) -> TurnTimer {
+        Self::with_clock(timer_duration, SystemClock)
+    }
+}
+impl<C: Clock> TurnTimer<C> {
+    pub fn with_clock(timer_duration: Duration, clock: C) -> Self {
         Self {
-            timer_duration: timer_duration,
+            clock,
+            timer_duration,
+            tick_interval: None,
+            warning: None,
             subscribers: Vec::new(),
+            control: None,
+            threads: Supervisor::new(),
         }
     }
-    pub fn run_timer(self) {
-        // set up timer to accept input for
-        thread::spawn(move || {
-            thread::sleep(Duration::from_millis(self.timer_duration));
-            self.notify(&TimerStatus::TimerComplete);
+    /// Sets how long a run lasts, e.g. as the level rises or while soft
+    /// dropping. A run counting down is changed too, and completes straight
+    /// away if it has already run for longer.
+    pub fn set_duration(&mut self, timer_duration: Duration) {
+        self.timer_duration = timer_duration;
+        self.send(TimerControl::SetDuration(timer_duration));
+    }
+    /// Tells subscribers the time left every `tick_interval` from the next
+    /// run, or stops telling them with `None`.
+    pub fn set_tick_interval(&mut self, tick_interval: Option<Duration>) {
+        self.tick_interval = tick_interval.filter(|interval| !interval.is_zero());
+    }
+    /// Warns subscribers once `fraction` of a run is left, from the next
+    /// run, e.g. 0.25 for the last 3 seconds of a 12 second run. `None` or
+    /// zero stops warning them.
+    pub fn set_warning(&mut self, fraction: Option<f64>) {
+        self.warning = fraction
+            .map(|fraction| fraction.min(1.0))
+            .filter(|fraction| *fraction > 0.0);
+    }
+    /// Starts counting down, or starts again if already counting down, on
+    /// the timer's own thread. Subscribers are told the timer is not
+    /// complete before this returns, so they never see the previous run
+    /// completing as this one.
+    pub fn run_timer(&mut self) {
+        let control = self.control.get_or_insert_with(|| {
+            let (control_sender, control_receiver) = mpsc::channel();
+            let clock = self.clock.clone();
+            // The countdown stops when the control channel closes rather
+            // than on the shutdown signal, as it may be waiting on it.
+            self.threads.spawn("turn-timer", move |_shutdown| {
+                run_countdowns(control_receiver, clock)
+            });
+            control_sender
         });
+        self.subscribers.retain(SubscriberChannel::is_subscribed);
+        let (started_sender, started_receiver) = mpsc::channel();
+        let start = TimerControl::Start {
+            at: self.clock.now(),
+            duration: self.timer_duration,
+            tick_interval: self.tick_interval,
+            warning: self.warning,
+            subscribers: self.subscribers.clone(),
+            started: started_sender,
+        };
+        if control.send(start).is_err() || started_receiver.recv().is_err() {
+            log::warn!("Turn timer thread stopped.");
+        }
+    }
+    /// Stops the countdown until it is resumed.
+    pub fn pause(&self) {
+        self.send(TimerControl::Pause(self.clock.now()));
+    }
+    /// Carries on the countdown from where it was paused.
+    pub fn resume(&self) {
+        self.send(TimerControl::Resume(self.clock.now()));
+    }
+    /// Stops the countdown without telling subscribers it completed. They
+    /// keep the last status they were sent until the timer is run again.
+    pub fn cancel(&self) {
+        self.send(TimerControl::Cancel);
+    }
+    fn send(&self, message: TimerControl) {
+        if let Some(control) = &self.control {
+            if control.send(message).is_err() {
+                log::warn!("Turn timer thread stopped.");
+            }
+        }
+    }
+}
+
+impl<C: Clock> Drop for TurnTimer<C> {
+    fn drop(&mut self) {
+        // Closing the control channel wakes the thread and stops it, before
+        // the supervisor joins it.
+        self.control = None;
+        self.threads.shutdown();
     }
 }
 
-impl Notifier<TimerStatus> for TurnTimer {
-    fn subscribers(&self) -> &Vec<mpsc::Sender<TimerStatus>> {
+impl<C: Clock> Notifier<TimerStatus> for TurnTimer<C> {
+    fn subscribers(&self) -> &Vec<SubscriberChannel<TimerStatus>> {
         return &self.subscribers;
     }
-    fn set_subscribers(&mut self) -> &mut Vec<mpsc::Sender<TimerStatus>> {
+    fn set_subscribers(&mut self) -> &mut Vec<SubscriberChannel<TimerStatus>> {
         return &mut self.subscribers;
     }
 }
+
+/// Changes to the countdown, with the time they were made at where that
+/// matters, so that the thread taking a moment to get them does not change
+/// the timing.
+enum TimerControl {
+    Start {
+        at: Instant,
+        duration: Duration,
+        tick_interval: Option<Duration>,
+        warning: Option<f64>,
+        subscribers: Vec<SubscriberChannel<TimerStatus>>,
+        /// Told once the subscribers have been told the run has started.
+        started: mpsc::Sender<()>,
+    },
+    Pause(Instant),
+    Resume(Instant),
+    Cancel,
+    SetDuration(Duration),
+}
+
+/// A run of the timer on its thread. It is timed from absolute instants
+/// rather than by adding up waits, so that it does not drift however long
+/// it runs or however often it ticks.
+struct Countdown {
+    started: Instant,
+    duration: Duration,
+    /// Time spent paused, which the end and the ticks are put back by.
+    paused_for: Duration,
+    paused_at: Option<Instant>,
+    tick_interval: Option<Duration>,
+    ticks: u32,
+    warning: Option<f64>,
+    warned: bool,
+    subscribers: Vec<SubscriberChannel<TimerStatus>>,
+}
+impl Countdown {
+    fn ends_at(&self) -> Instant {
+        self.started + self.paused_for + self.duration
+    }
+    fn next_tick_at(&self) -> Option<Instant> {
+        let interval = self.tick_interval?;
+        Some(self.started + self.paused_for + interval * (self.ticks + 1))
+    }
+    fn warns_at(&self) -> Option<Instant> {
+        let fraction = self.warning.filter(|_| !self.warned)?;
+        Some(self.started + self.paused_for + self.duration.mul_f64(1.0 - fraction))
+    }
+    /// When to wake for the next tick, the warning or the end of the
+    /// countdown.
+    fn wakes_at(&self) -> Instant {
+        [self.next_tick_at(), self.warns_at()]
+            .into_iter()
+            .flatten()
+            .fold(self.ends_at(), Instant::min)
+    }
+    fn remaining(&self, now: Instant) -> Duration {
+        self.ends_at()
+            .saturating_duration_since(self.paused_at.unwrap_or(now))
+    }
+    fn pause(&mut self, now: Instant) {
+        self.paused_at.get_or_insert(now);
+    }
+    fn resume(&mut self, now: Instant) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_for += now.duration_since(paused_at);
+        }
+    }
+    /// Ticks if a tick is due at `now`, skipping any that were missed.
+    fn tick(&mut self, now: Instant) {
+        let Some(interval) = self.tick_interval else {
+            return;
+        };
+        if self.next_tick_at().is_some_and(|tick_at| tick_at <= now) {
+            let run_for = now.duration_since(self.started + self.paused_for);
+            self.ticks = (run_for.as_nanos() / interval.as_nanos()) as u32;
+            self.notify(&TimerStatus::TimerTick(self.remaining(now)));
+        }
+    }
+    /// Warns that the countdown is nearly over if that is due at `now`.
+    fn warn(&mut self, now: Instant) {
+        if self.warns_at().is_some_and(|warn_at| warn_at <= now) {
+            self.warned = true;
+            self.notify(&TimerStatus::TimerWarning(self.remaining(now)));
+        }
+    }
+}
+impl Notifier<TimerStatus> for Countdown {
+    fn subscribers(&self) -> &Vec<SubscriberChannel<TimerStatus>> {
+        return &self.subscribers;
+    }
+    fn set_subscribers(&mut self) -> &mut Vec<SubscriberChannel<TimerStatus>> {
+        return &mut self.subscribers;
+    }
+}
+
+/// Runs the timer's countdowns as they are started, until the timer is
+/// dropped. Waiting is on the control channel, so that pausing or starting
+/// again interrupts the wait.
+fn run_countdowns<C: Clock>(control: mpsc::Receiver<TimerControl>, clock: C) {
+    let mut countdown: Option<Countdown> = None;
+    loop {
+        let received = match &countdown {
+            Some(running) if running.paused_at.is_none() => {
+                clock.recv_until(&control, running.wakes_at())
+            }
+            _other => control
+                .recv()
+                .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(TimerControl::Start {
+                at,
+                duration,
+                tick_interval,
+                warning,
+                subscribers,
+                started,
+            }) => {
+                let running = Countdown {
+                    started: at,
+                    duration,
+                    paused_for: Duration::ZERO,
+                    paused_at: None,
+                    tick_interval,
+                    ticks: 0,
+                    warning,
+                    warned: false,
+                    subscribers,
+                };
+                running.notify(&TimerStatus::TimerNotComplete);
+                // The caller waits for this, so it cannot have gone.
+                let _ = started.send(());
+                countdown = Some(running);
+            }
+            Ok(TimerControl::Pause(at)) => {
+                if let Some(running) = &mut countdown {
+                    running.pause(at);
+                }
+            }
+            Ok(TimerControl::Resume(at)) => {
+                if let Some(running) = &mut countdown {
+                    running.resume(at);
+                }
+            }
+            Ok(TimerControl::Cancel) => countdown = None,
+            Ok(TimerControl::SetDuration(duration)) => {
+                if let Some(running) = &mut countdown {
+                    running.duration = duration;
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => match countdown.take() {
+                Some(finished) if finished.ends_at() <= clock.now() => {
+                    finished.notify(&TimerStatus::TimerComplete);
+                }
+                Some(mut running) => {
+                    running.tick(clock.now());
+                    running.warn(clock.now());
+                    countdown = Some(running);
+                }
+                None => {}
+            },
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
 pub trait TurnTimerSubscriberTrait {
     fn get_timer_status(&mut self) -> TimerStatus;
 }
@@ -44,6 +312,11 @@ pub struct TurnTimerSubscriber {
     timer_status: TimerStatus,
     subscription: Option<mpsc::Receiver<TimerStatus>>,
 }
+impl Default for TurnTimerSubscriber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl TurnTimerSubscriber {
     pub fn new() -> TurnTimerSubscriber {
         Self {
@@ -51,24 +324,33 @@ impl TurnTimerSubscriber {
             subscription: None,
         }
     }
+    /// The time left in the current run as of the timer's last tick or
+    /// warning, e.g. for a countdown bar. Zero once the run is complete, and
+    /// `None` before its first tick or if the timer does not tick.
+    pub fn remaining(&mut self) -> Option<Duration> {
+        match self.get_timer_status() {
+            TimerStatus::TimerNotComplete => None,
+            TimerStatus::TimerTick(remaining) | TimerStatus::TimerWarning(remaining) => {
+                Some(remaining)
+            }
+            TimerStatus::TimerComplete => Some(Duration::ZERO),
+        }
+    }
 }
 impl TurnTimerSubscriberTrait for TurnTimerSubscriber {
     fn get_timer_status(&mut self) -> TimerStatus {
-        match self.timer_status {
-            TimerStatus::TimerComplete => return TimerStatus::TimerComplete,
-            TimerStatus::TimerNotComplete => {
-                self.update();
-                return self.timer_status.clone();
-            }
-        }
+        self.update();
+        return self.timer_status.clone();
     }
 }
 impl Subscriber<TimerStatus> for TurnTimerSubscriber {
     fn update(&mut self) {
         match &self.subscription {
             Some(subscription) => {
-                if let Ok(TimerStatus::TimerComplete) = subscription.try_recv() {
-                    self.timer_status = TimerStatus::TimerComplete;
+                // The timer is restarted each turn, so the latest status
+                // is the one that counts.
+                for timer_status in subscription.try_iter() {
+                    self.timer_status = timer_status;
                 }
             }
             None => panic!("No subscriber set!"),
@@ -82,6 +364,8 @@ impl Subscriber<TimerStatus> for TurnTimerSubscriber {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::MockClock;
+    use std::thread;
 
     use crate::turn_timer::turn_timer::{TimerStatus, TurnTimerSubscriber};
 
@@ -89,8 +373,8 @@ mod tests {
 
     #[test]
     fn test_timer_setup_works() {
-        let timer = TurnTimer::new(1);
-        assert_eq!(timer.timer_duration, 1);
+        let timer = TurnTimer::new(Duration::from_micros(1500));
+        assert_eq!(timer.timer_duration, Duration::from_micros(1500));
         let listener = TurnTimerSubscriber::new();
         assert_eq!(listener.timer_status, TimerStatus::TimerNotComplete);
     }
@@ -102,11 +386,50 @@ mod tests {
         listener.get_timer_status();
     }
 
+    /// A timer on a mock clock, and a subscriber to it.
+    fn mock_timer(
+        duration: u64,
+    ) -> (
+        TurnTimer<MockClock>,
+        TurnTimerSubscriber,
+        MockClock,
+        Subscription,
+    ) {
+        let clock = MockClock::new();
+        let mut timer = TurnTimer::with_clock(Duration::from_millis(duration), clock.clone());
+        let mut listener = TurnTimerSubscriber::new();
+        let subscription = timer.add_subscriber(&mut listener);
+        (timer, listener, clock, subscription)
+    }
+
+    /// Waits for the timer's thread to catch up with the mock clock.
+    fn wait_for(listener: &mut TurnTimerSubscriber, status: TimerStatus) {
+        let give_up = Instant::now() + Duration::from_secs(5);
+        while listener.get_timer_status() != status {
+            assert!(Instant::now() < give_up, "timer never reached {:?}", status);
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn test_paused_timer_does_not_complete_until_resumed() {
+        let (mut timer, mut listener, clock, _subscription) = mock_timer(20);
+        timer.run_timer();
+        timer.pause();
+        clock.advance(Duration::from_millis(40));
+        assert_eq!(listener.get_timer_status(), TimerStatus::TimerNotComplete);
+        timer.resume();
+        clock.advance(Duration::from_millis(19));
+        assert_eq!(listener.get_timer_status(), TimerStatus::TimerNotComplete);
+        clock.advance(Duration::from_millis(1));
+        wait_for(&mut listener, TimerStatus::TimerComplete);
+    }
+
     #[test]
     fn test_timer_works() {
-        let mut timer = TurnTimer::new(10);
+        let mut timer = TurnTimer::new(Duration::from_millis(10));
         let mut listener = TurnTimerSubscriber::new();
-        timer.add_subscriber(&mut listener);
+        let _subscription = timer.add_subscriber(&mut listener);
         assert_eq!(listener.get_timer_status(), TimerStatus::TimerNotComplete);
         timer.run_timer();
         assert_eq!(listener.get_timer_status(), TimerStatus::TimerNotComplete);
@@ -114,4 +437,104 @@ mod tests {
         assert_eq!(listener.get_timer_status(), TimerStatus::TimerComplete);
         assert_eq!(listener.get_timer_status(), TimerStatus::TimerComplete);
     }
+
+    #[test]
+    fn test_timer_completes_on_time_by_the_clock() {
+        let (mut timer, mut listener, clock, _subscription) = mock_timer(10);
+        timer.run_timer();
+        clock.advance(Duration::from_millis(9));
+        assert_eq!(listener.get_timer_status(), TimerStatus::TimerNotComplete);
+        clock.advance(Duration::from_millis(1));
+        wait_for(&mut listener, TimerStatus::TimerComplete);
+    }
+
+    #[test]
+    fn test_timer_restarts_with_the_same_subscribers() {
+        let (mut timer, mut listener, clock, _subscription) = mock_timer(10);
+        timer.run_timer();
+        clock.advance(Duration::from_millis(10));
+        wait_for(&mut listener, TimerStatus::TimerComplete);
+        timer.set_duration(Duration::from_millis(30));
+        timer.run_timer();
+        assert_eq!(listener.get_timer_status(), TimerStatus::TimerNotComplete);
+        clock.advance(Duration::from_millis(30));
+        wait_for(&mut listener, TimerStatus::TimerComplete);
+    }
+
+    #[test]
+    fn test_restarting_a_running_timer_starts_its_count_again() {
+        let (mut timer, mut listener, clock, _subscription) = mock_timer(40);
+        timer.run_timer();
+        clock.advance(Duration::from_millis(25));
+        timer.run_timer();
+        clock.advance(Duration::from_millis(25));
+        assert_eq!(listener.get_timer_status(), TimerStatus::TimerNotComplete);
+        clock.advance(Duration::from_millis(15));
+        wait_for(&mut listener, TimerStatus::TimerComplete);
+    }
+
+    #[test]
+    fn test_timer_ticks_with_the_time_left() {
+        let (mut timer, mut listener, clock, _subscription) = mock_timer(200);
+        timer.set_tick_interval(Some(Duration::from_millis(20)));
+        timer.run_timer();
+        assert_eq!(listener.remaining(), None);
+        clock.advance(Duration::from_millis(30));
+        wait_for(
+            &mut listener,
+            TimerStatus::TimerTick(Duration::from_millis(170)),
+        );
+        assert_eq!(listener.remaining(), Some(Duration::from_millis(170)));
+        clock.advance(Duration::from_millis(170));
+        wait_for(&mut listener, TimerStatus::TimerComplete);
+        assert_eq!(listener.remaining(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_cancelled_timer_never_completes() {
+        let (mut timer, mut listener, clock, _subscription) = mock_timer(10);
+        timer.run_timer();
+        timer.cancel();
+        clock.advance(Duration::from_millis(30));
+        assert_eq!(listener.get_timer_status(), TimerStatus::TimerNotComplete);
+        timer.run_timer();
+        clock.advance(Duration::from_millis(10));
+        wait_for(&mut listener, TimerStatus::TimerComplete);
+    }
+
+    #[test]
+    fn test_dropped_timer_stops_without_completing() {
+        let (mut timer, mut listener, clock, _subscription) = mock_timer(10);
+        timer.run_timer();
+        drop(timer);
+        clock.advance(Duration::from_millis(30));
+        assert_eq!(listener.get_timer_status(), TimerStatus::TimerNotComplete);
+    }
+
+    #[test]
+    fn test_duration_changes_while_counting_down() {
+        let (mut timer, mut listener, clock, _subscription) = mock_timer(1000);
+        timer.run_timer();
+        clock.advance(Duration::from_millis(20));
+        timer.set_duration(Duration::from_millis(40));
+        assert_eq!(listener.get_timer_status(), TimerStatus::TimerNotComplete);
+        clock.advance(Duration::from_millis(20));
+        wait_for(&mut listener, TimerStatus::TimerComplete);
+    }
+
+    #[test]
+    fn test_timer_warns_when_nearly_over() {
+        let (mut timer, mut listener, clock, _subscription) = mock_timer(12000);
+        timer.set_warning(Some(0.25));
+        timer.run_timer();
+        clock.advance(Duration::from_millis(8999));
+        assert_eq!(listener.get_timer_status(), TimerStatus::TimerNotComplete);
+        clock.advance(Duration::from_millis(1));
+        wait_for(
+            &mut listener,
+            TimerStatus::TimerWarning(Duration::from_secs(3)),
+        );
+        clock.advance(Duration::from_secs(3));
+        wait_for(&mut listener, TimerStatus::TimerComplete);
+    }
 }
@@ -1,126 +1,579 @@
-use crossterm::event::{poll, read, Event, KeyCode};
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
-use std::sync::mpsc;
-use std::thread::Scope;
-use std::time::Duration;
-
-use crate::models::{Command, TurnEvent};
-use crate::turn_timer::turn_timer::{TimerStatus, TurnTimerSubscriberTrait};
+use crossterm::event::{
+    poll, read, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+    KeyModifiers, KeyboardEnhancementFlags, MouseButton, MouseEventKind,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::execute;
+use crossterm::terminal::{self, disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::double_tap::DoubleTap;
+use crate::key_repeat::KeyRepeats;
+use crate::keymap::Keymap;
+use crate::layout::Layout;
+use crate::models::{Command, KeyInput, KeyState};
+use crate::recording::{RecordedInput, Recorder, Recording};
+use crate::settings::GameSettings;
+use crate::supervisor;
+/// Returns whether the terminal can report key releases. The terminal is
+/// only asked once, as it can take a while to answer.
+fn reports_key_releases() -> bool {
+    static REPORTS_KEY_RELEASES: OnceLock<bool> = OnceLock::new();
+    *REPORTS_KEY_RELEASES.get_or_init(|| supports_keyboard_enhancement().unwrap_or(false))
+}
+
+/// Set when the process is sent SIGINT, until the terminal event reader
+/// passes it on.
+fn interrupt_flag() -> &'static Arc<AtomicBool> {
+    static INTERRUPTED: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+    INTERRUPTED.get_or_init(|| Arc::new(AtomicBool::new(false)))
+}
+
+/// Catches SIGINT, e.g. from `kill -INT` or Ctrl-C outside raw mode, so that
+/// rather than the process being killed with the terminal left in the
+/// alternate screen, it is read as Ctrl-C and quits as normal.
+pub fn catch_interrupts() -> io::Result<()> {
+    signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(interrupt_flag()))?;
+    Ok(())
+}
+
+/// The key event SIGINT is read as.
+fn ctrl_c() -> KeyEvent {
+    KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)
+}
+
+/// How often the terminal event reader checks whether to shut down.
+const EVENT_READER_POLL: Duration = Duration::from_millis(50);
+
+/// Terminal events, read on a thread of their own as soon as they arrive
+/// and handed over through a channel, so that waiting for input wakes the
+/// moment a key is pressed. The thread belongs to the global supervisor and
+/// stops when it shuts down.
+fn events() -> &'static Mutex<mpsc::Receiver<io::Result<Event>>> {
+    static EVENTS: OnceLock<Mutex<mpsc::Receiver<io::Result<Event>>>> = OnceLock::new();
+    EVENTS.get_or_init(|| {
+        // The reader holds crossterm's event lock while it waits, which
+        // would block asking the terminal about key releases, so ask first.
+        reports_key_releases();
+        let (sender, receiver) = mpsc::channel();
+        let mut supervisor = supervisor::global().lock().expect("Supervisor poisoned.");
+        supervisor.spawn("terminal-events", move |shutdown| {
+            // Waiting is broken up so that a shutdown or SIGINT is noticed,
+            // while an event still arrives the moment it is ready.
+            while !shutdown.is_requested() {
+                if interrupt_flag().swap(false, Ordering::SeqCst) {
+                    if sender.send(Ok(Event::Key(ctrl_c()))).is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                let event = match poll(EVENT_READER_POLL) {
+                    Ok(false) => continue,
+                    Ok(true) => read(),
+                    Err(error) => Err(error),
+                };
+                let failed = event.is_err();
+                if sender.send(event).is_err() || failed {
+                    return;
+                }
+            }
+        });
+        Mutex::new(receiver)
+    })
+}
+
+/// Waits up to `timeout` for the next terminal event.
+fn next_event(timeout: Duration) -> io::Result<Option<Event>> {
+    let receiver = events().lock().expect("Terminal event channel poisoned.");
+    match receiver.recv_timeout(timeout) {
+        Ok(event) => event.map(Some),
+        Err(mpsc::RecvTimeoutError::Timeout) => Ok(None),
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err(reader_stopped()),
+    }
+}
+
+/// Waits for the next terminal event.
+fn wait_event() -> io::Result<Event> {
+    let receiver = events().lock().expect("Terminal event channel poisoned.");
+    receiver.recv().map_err(|_| reader_stopped())?
+}
+
+fn reader_stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "terminal event reader stopped")
+}
+
+/// Returns whether a key event is Ctrl-C. Raw mode stops the terminal
+/// turning Ctrl-C into an interrupt, so it arrives as a key press instead
+/// and is treated as quitting, leaving the terminal to be restored as
+/// normal.
+fn is_interrupt(key_event: &KeyEvent) -> bool {
+    key_event.code == KeyCode::Char('c') && key_event.modifiers.contains(KeyModifiers::CONTROL)
+}
+
 // Struct that runs enable_raw_mode on start and disables when it is
-// dropped so that it is only active in the scope of the instantiation
-struct ScopedRawMode;
+// dropped so that it is only active in the scope of the instantiation.
+// Key release reporting is turned on alongside it where supported.
+pub struct ScopedRawMode;
 
 impl ScopedRawMode {
-    fn new() -> ScopedRawMode {
+    pub fn new() -> ScopedRawMode {
         enable_raw_mode().expect("Failed to enable raw mode required to display correctly.");
+        if reports_key_releases() {
+            execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )
+            .expect("Failed to enable key release reporting.");
+        }
         ScopedRawMode
     }
 }
 
+impl Default for ScopedRawMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Drop for ScopedRawMode {
     fn drop(&mut self) {
+        if reports_key_releases() {
+            execute!(io::stdout(), PopKeyboardEnhancementFlags)
+                .expect("Failed to disable key release reporting.");
+        }
         disable_raw_mode()
             .expect("Failed to disable raw mode. Restart terminal to resume normal behaviour.");
     }
 }
-// TODO: Move the run_user_input_loop fn into a class that implements an interface so
-// we don't have to pass in all of these dependencies to this fn.
-pub fn timed_user_input<'a, T: CommandCollector, U: TurnTimerSubscriberTrait + Send + 'a>(
-    mut turn_timer_subscriber: U,
-    command_dispatcher: mpsc::Sender<Command>,
-    turn_event_reciever: mpsc::Receiver<TurnEvent>,
-    s: &'a Scope<'a, '_>,
-) {
-    // set up thread for getting cli input
-
-    s.spawn(move || {
-        let _guard = ScopedRawMode::new();
-        let command_collector = T::new();
-        run_user_input_loop::<T, U>(
-            &mut turn_timer_subscriber,
-            command_dispatcher,
-            command_collector,
-            turn_event_reciever,
-        )
-    });
-}
-
-/// Runs a loop to collect commands from the user while the turn timer
-/// is not yet complete. This has been implemented with dependency injection
-/// through the use of generics in order to make testing easier.
-///
-/// Args:
-/// turn_timer_subscriber: a mutable reference to an object that
-/// implements the TurnTimerSubscriberTrait and the deived trait Send
-/// (so that it can be sent into a thread).
-/// command_dispatcher: an mpsc::Sender of type Command, which
-/// is used to send the read commands back to the main thread.
-/// command_collector: an object that implements the CommandCollector trait. This
-/// reference is mutable to make testing easier.
-///
-/// Edge cases:
-/// - Timer never completes: unhandled - user must interrupt program
-/// - Get command fails when reading from input
-/// - Command is not recognised
-/// - Send to main fails
-fn run_user_input_loop<'a, T: CommandCollector, U: TurnTimerSubscriberTrait + Send + 'a>(
-    turn_timer_subscriber: &mut U,
-    command_dispatcher: mpsc::Sender<Command>,
-    mut command_collector: T,
-    turn_event_reciever: mpsc::Receiver<TurnEvent>,
-) {
+/// Reports mouse movement and clicks to the game for as long as it is held,
+/// for mouse control.
+pub struct MouseCapture;
+
+impl MouseCapture {
+    pub fn enable() -> MouseCapture {
+        execute!(io::stdout(), EnableMouseCapture).expect("Failed to enable mouse capture.");
+        MouseCapture
+    }
+}
+
+impl Drop for MouseCapture {
+    fn drop(&mut self) {
+        execute!(io::stdout(), DisableMouseCapture).expect("Failed to disable mouse capture.");
+    }
+}
+
+/// What was read during a frame.
+#[derive(Debug, Default)]
+pub struct FrameInputs {
+    /// Inputs, with the times they were read.
+    pub inputs: Vec<(KeyInput, Instant)>,
+    /// The columns and rows the terminal was last resized to, if it was.
+    pub resized: Option<(u16, u16)>,
+}
+
+/// Reads inputs until `deadline`, for the engine to apply together in the
+/// next frame, with the times they were read, and notes the terminal being
+/// resized. Raw mode must be on. Unrecognised keys are skipped. A quit
+/// request is read as a quit, and it or a failed read stops reading early.
+pub fn read_frame_inputs<T: CommandCollector>(
+    command_collector: &mut T,
+    deadline: Instant,
+) -> FrameInputs {
+    let mut frame_inputs = FrameInputs::default();
+    while Instant::now() < deadline {
+        match command_collector.get_command() {
+            Ok(Some(Collected::Key(input))) => frame_inputs.inputs.push((input, Instant::now())),
+            Ok(Some(Collected::Resized(cols, rows))) => frame_inputs.resized = Some((cols, rows)),
+            Ok(None) | Err(InputError::UnrecognisedKey) => {}
+            Err(InputError::QuitRequested) => {
+                let quit = KeyInput {
+                    command: Command::Quit,
+                    state: KeyState::Tapped,
+                };
+                frame_inputs.inputs.push((quit, Instant::now()));
+                break;
+            }
+            Err(InputError::ReadFailed(e)) => {
+                log::warn!("Error encountered reading command {:?}", e);
+                break;
+            }
+        }
+    }
+    frame_inputs
+}
+
+/// Waits for the next key press that means something in a menu, and
+/// returns it as a menu command: `MenuUp`, `MenuDown`, `Select`, `Back`, or
+/// `Quit` for Ctrl-C to leave the game from any menu.
+pub fn read_menu_input() -> std::io::Result<Command> {
+    let _guard = ScopedRawMode::new();
     loop {
-        if let Ok(TurnEvent::EndTurn) = turn_event_reciever.try_recv() {
-            return;
-        }
-        match turn_timer_subscriber.get_timer_status() {
-            TimerStatus::TimerComplete => {
-                return;
-            }
-            TimerStatus::TimerNotComplete => match command_collector.get_command() {
-                Ok(val) => match val {
-                    Some(command) => {
-                        if let Err(error) = command_dispatcher.send(command) {
-                            log::warn!("{:?}", error.to_string());
-                            return;
-                        }
-                    }
-                    None => (),
-                },
-                Err(e) => {
-                    log::warn!("Error encountered reading command {:?}", e);
-                    return;
+        if let Event::Key(key_event) = wait_event()? {
+            if key_event.kind == KeyEventKind::Release {
+                continue;
+            }
+            if is_interrupt(&key_event) {
+                return Ok(Command::Quit);
+            }
+            match key_event.code {
+                KeyCode::Up => return Ok(Command::MenuUp),
+                KeyCode::Down => return Ok(Command::MenuDown),
+                KeyCode::Enter | KeyCode::Char(' ') => return Ok(Command::Select),
+                KeyCode::Esc => return Ok(Command::Back),
+                _other => {}
+            }
+        }
+    }
+}
+
+/// Waits for the next key press and returns the key, or `None` for Esc or
+/// Ctrl-C.
+pub fn read_key() -> std::io::Result<Option<KeyCode>> {
+    let _guard = ScopedRawMode::new();
+    loop {
+        if let Event::Key(key_event) = wait_event()? {
+            if key_event.kind != KeyEventKind::Press {
+                continue;
+            }
+            if key_event.code == KeyCode::Esc || is_interrupt(&key_event) {
+                return Ok(None);
+            }
+            return Ok(Some(key_event.code));
+        }
+    }
+}
+
+/// A key press in the practice mode board editor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditorInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    /// Fill or empty the cell under the cursor.
+    Toggle,
+    /// Choose the piece with this letter to come next.
+    Piece(char),
+    /// Take back the last piece chosen.
+    Unchoose,
+    Resume,
+}
+
+/// Waits for the next key press that means something in the board editor.
+pub fn read_editor_input() -> std::io::Result<EditorInput> {
+    let _guard = ScopedRawMode::new();
+    loop {
+        if let Event::Key(key_event) = wait_event()? {
+            if key_event.kind == KeyEventKind::Release {
+                continue;
+            }
+            if is_interrupt(&key_event) {
+                return Ok(EditorInput::Resume);
+            }
+            match key_event.code {
+                KeyCode::Up => return Ok(EditorInput::Up),
+                KeyCode::Down => return Ok(EditorInput::Down),
+                KeyCode::Left => return Ok(EditorInput::Left),
+                KeyCode::Right => return Ok(EditorInput::Right),
+                KeyCode::Char(' ') => return Ok(EditorInput::Toggle),
+                KeyCode::Char(letter) if letter.is_ascii_alphabetic() => {
+                    return Ok(EditorInput::Piece(letter.to_ascii_uppercase()))
+                }
+                KeyCode::Backspace => return Ok(EditorInput::Unchoose),
+                KeyCode::Enter | KeyCode::Esc => return Ok(EditorInput::Resume),
+                _other => {}
+            }
+        }
+    }
+}
+
+/// Waits for the terminal to be resized, returning its new columns and
+/// rows, or `None` if q, Esc or Ctrl-C is pressed first.
+pub fn wait_for_resize() -> std::io::Result<Option<(u16, u16)>> {
+    let _guard = ScopedRawMode::new();
+    loop {
+        match wait_event()? {
+            Event::Resize(cols, rows) => return Ok(Some((cols, rows))),
+            Event::Key(key_event) => {
+                let stops = matches!(key_event.code, KeyCode::Esc | KeyCode::Char('q'))
+                    || is_interrupt(&key_event);
+                if stops && key_event.kind != KeyEventKind::Release {
+                    return Ok(None);
                 }
-            },
+            }
+            _other => {}
         }
     }
 }
 
+/// Waits for `duration`, throwing away any keys pressed in the meantime so
+/// that they are not applied once play starts. Returns early with `true`
+/// if Esc or Ctrl-C is pressed.
+pub fn wait_discarding_input(duration: Duration) -> std::io::Result<bool> {
+    let _guard = ScopedRawMode::new();
+    let deadline = Instant::now() + duration;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        let Some(event) = next_event(remaining)? else {
+            break;
+        };
+        if let Event::Key(key_event) = event {
+            let stops = key_event.code == KeyCode::Esc || is_interrupt(&key_event);
+            if stops && key_event.kind != KeyEventKind::Release {
+                return Ok(true);
+            }
+        }
+    }
+    return Ok(false);
+}
+
+/// Coalesces inputs that piled up while the game was busy, so that they do
+/// not all fire at once. Presses of a key already held, which are the
+/// terminal repeating it, are dropped. Taps and auto shift repeats are each
+/// moves of their own, so everything else is kept in order. Inputs come with
+/// the times they were read.
+pub fn coalesce_inputs(
+    inputs: impl IntoIterator<Item = (KeyInput, Instant)>,
+) -> Vec<(KeyInput, Instant)> {
+    let mut coalesced: Vec<(KeyInput, Instant)> = Vec::new();
+    let mut held: Vec<Command> = Vec::new();
+    for (input, read_at) in inputs {
+        match input.state {
+            KeyState::Pressed if held.contains(&input.command) => continue,
+            KeyState::Pressed => held.push(input.command),
+            KeyState::Released => held.retain(|command| *command != input.command),
+            KeyState::Tapped | KeyState::Repeated => {}
+        }
+        coalesced.push((input, read_at));
+    }
+    coalesced
+}
+
 pub trait CommandCollector {
     fn new() -> Self;
-    fn get_command(&mut self) -> std::io::Result<Option<Command>>;
+    fn get_command(&mut self) -> Result<Option<Collected>, InputError>;
+}
+
+/// What a command collector read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collected {
+    /// An input for the game.
+    Key(KeyInput),
+    /// The terminal was resized to the columns and rows given, so the
+    /// screen needs laying out again.
+    Resized(u16, u16),
+}
+
+/// Why a command collector gave no input.
+#[derive(Debug)]
+pub enum InputError {
+    /// A key with no command was pressed, which is ignored.
+    UnrecognisedKey,
+    /// Reading the terminal failed, so no more input can be read this turn.
+    ReadFailed(io::Error),
+    /// The player asked to quit, e.g. with Ctrl-C.
+    QuitRequested,
+}
+impl From<io::Error> for InputError {
+    fn from(error: io::Error) -> Self {
+        Self::ReadFailed(error)
+    }
+}
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnrecognisedKey => write!(f, "Unrecognised key"),
+            Self::ReadFailed(error) => write!(f, "Failed to read input: {}", error),
+            Self::QuitRequested => write!(f, "Quit requested"),
+        }
+    }
 }
+impl std::error::Error for InputError {}
 
-pub struct CliCommandCollector {}
+/// Reads key presses from the terminal, following a keymap, leaving out
+/// the terminal's repeats of held move keys, see `KeyRepeats`. Double
+/// tapping soft drop can hard drop, see `DoubleTap`. Presses are sent as
+/// taps on terminals that cannot report key releases. Inputs can be
+/// recorded as they are read.
+pub struct CliCommandCollector {
+    keymap: Keymap,
+    key_repeats: KeyRepeats,
+    double_tap: DoubleTap,
+    /// Longest wait for a key each time input is read.
+    poll_interval: Duration,
+    recorder: Option<Recorder>,
+    /// Where the board is on screen, to follow the mouse across it.
+    layout: Layout,
+    /// Whether the input display is shown, which moves the board over.
+    input_display: bool,
+}
+impl CliCommandCollector {
+    /// Reads keys following `keymap`, with the handling and poll interval
+    /// from `settings`.
+    pub fn with_settings(keymap: Keymap, settings: &GameSettings) -> Self {
+        Self {
+            keymap,
+            key_repeats: KeyRepeats::new(),
+            double_tap: DoubleTap::new(settings.double_tap_drop),
+            poll_interval: Duration::from_millis(settings.input_poll.max(1)),
+            recorder: None,
+            layout: terminal::size()
+                .map(|(cols, rows)| Layout::for_size(cols, rows, settings.input_display))
+                .unwrap_or_default(),
+            input_display: settings.input_display,
+        }
+    }
+    /// The keymap, to rebind keys in game.
+    pub fn keymap_mut(&mut self) -> &mut Keymap {
+        &mut self.keymap
+    }
+    /// Records every input read from now on, until the recorder is taken
+    /// back.
+    pub fn record(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+    pub fn recorder_mut(&mut self) -> Option<&mut Recorder> {
+        self.recorder.as_mut()
+    }
+    pub fn take_recorder(&mut self) -> Option<Recorder> {
+        self.recorder.take()
+    }
+    fn read_input(&mut self) -> Result<Option<Collected>, InputError> {
+        if let Some(input) = self.key_repeats.let_go(Instant::now()) {
+            return Ok(Some(Collected::Key(input)));
+        }
+        if let Some(event) = next_event(self.poll_interval)? {
+            return match event {
+                Event::Key(key_event) => {
+                    // Keys held with Ctrl or Alt are not the bound keys, apart
+                    // from Ctrl-C which always quits.
+                    let with_modifier = key_event
+                        .modifiers
+                        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+                    if is_interrupt(&key_event) && key_event.kind != KeyEventKind::Release {
+                        return Err(InputError::QuitRequested);
+                    }
+                    let command = match self.keymap.command(key_event.code) {
+                        Some(command) if !with_modifier => command,
+                        _other => return Err(InputError::UnrecognisedKey),
+                    };
+                    let state = match key_event.kind {
+                        KeyEventKind::Release => KeyState::Released,
+                        _other if !reports_key_releases() => KeyState::Tapped,
+                        _other => KeyState::Pressed,
+                    };
+                    let is_repeat = key_event.kind == KeyEventKind::Repeat;
+                    let input = self.double_tap.observe(
+                        KeyInput { command, state },
+                        is_repeat,
+                        Instant::now(),
+                    );
+                    let input = self.key_repeats.observe(input, is_repeat, Instant::now());
+                    Ok(input.map(Collected::Key))
+                }
+                Event::Mouse(mouse_event) => {
+                    let command = match mouse_event.kind {
+                        // The board's cells start after a column of wall.
+                        MouseEventKind::Moved | MouseEventKind::Drag(_) => {
+                            let board_col = self.layout.board_col() as i16;
+                            Command::MoveToColumn(mouse_event.column as i16 - board_col - 1)
+                        }
+                        MouseEventKind::Down(MouseButton::Left) => Command::RotateClockwise,
+                        MouseEventKind::Down(MouseButton::Right) => Command::RotateAnticlockwise,
+                        MouseEventKind::Down(MouseButton::Middle) => Command::HardDrop,
+                        _other => return Ok(None),
+                    };
+                    Ok(Some(Collected::Key(KeyInput {
+                        command,
+                        state: KeyState::Tapped,
+                    })))
+                }
+                Event::Resize(cols, rows) => {
+                    self.layout = Layout::for_size(cols, rows, self.input_display);
+                    Ok(Some(Collected::Resized(cols, rows)))
+                }
+                _other => Ok(None),
+            };
+        }
+        return Ok(None);
+    }
+}
 impl CommandCollector for CliCommandCollector {
     fn new() -> Self {
-        Self {}
-    }
-    fn get_command(&mut self) -> std::io::Result<Option<Command>> {
-        if poll(Duration::from_millis(2)).expect("Poll of CLI buffer failed.") {
-            return match read()? {
-                Event::Key(key_event) => match key_event.code {
-                    KeyCode::Down => Ok(Some(Command::MoveDown)),
-                    KeyCode::Left => Ok(Some(Command::MoveLeft)),
-                    KeyCode::Right => Ok(Some(Command::MoveRight)),
-                    KeyCode::Char('z') => Ok(Some(Command::RotateAnticlockwise)),
-                    KeyCode::Char('x') => Ok(Some(Command::RotateClockwise)),
-                    KeyCode::Esc => Ok(Some(Command::EndGame)),
-
-                    _other => Ok(None),
-                },
-                _other => panic!("Unrecognised command!"),
+        Self::with_settings(Keymap::default(), &GameSettings::default())
+    }
+    fn get_command(&mut self) -> Result<Option<Collected>, InputError> {
+        let read = self.read_input();
+        if let Some(recorder) = self.recorder.as_mut() {
+            let recorded = match &read {
+                Ok(Some(Collected::Key(input))) => Some(*input),
+                Err(InputError::QuitRequested) => Some(KeyInput {
+                    command: Command::Quit,
+                    state: KeyState::Tapped,
+                }),
+                _other => None,
             };
+            if let Some(input) = recorded {
+                recorder.record(input, Instant::now());
+            }
+        }
+        read
+    }
+}
+/// Replays the inputs of a recording at the ticks they were recorded on,
+/// timed from when it is created, so that a game can be watched again.
+/// Pausing is left out, as the recording skips time spent paused. Pressing
+/// q, Esc or Ctrl-C stops the replay.
+pub struct PlaybackCommandCollector {
+    inputs: VecDeque<RecordedInput>,
+    start: Instant,
+}
+impl PlaybackCommandCollector {
+    pub fn from_recording(recording: &Recording) -> Self {
+        Self {
+            inputs: recording
+                .inputs
+                .iter()
+                .filter(|recorded| recorded.input.command != Command::Pause)
+                .copied()
+                .collect(),
+            start: Instant::now(),
+        }
+    }
+    /// Returns the next input if it is due at `now`.
+    fn next_due(&mut self, now: Instant) -> Option<KeyInput> {
+        let tick = now.duration_since(self.start).as_millis() as u64;
+        if self.inputs.front()?.tick > tick {
+            return None;
+        }
+        self.inputs.pop_front().map(|recorded| recorded.input)
+    }
+}
+impl CommandCollector for PlaybackCommandCollector {
+    fn new() -> Self {
+        Self {
+            inputs: VecDeque::new(),
+            start: Instant::now(),
+        }
+    }
+    fn get_command(&mut self) -> Result<Option<Collected>, InputError> {
+        if let Some(input) = self.next_due(Instant::now()) {
+            return Ok(Some(Collected::Key(input)));
+        }
+        match next_event(Duration::from_millis(2))? {
+            Some(Event::Key(key_event)) => {
+                let stops = matches!(key_event.code, KeyCode::Esc | KeyCode::Char('q'))
+                    || is_interrupt(&key_event);
+                if stops && key_event.kind != KeyEventKind::Release {
+                    return Err(InputError::QuitRequested);
+                }
+            }
+            Some(Event::Resize(cols, rows)) => return Ok(Some(Collected::Resized(cols, rows))),
+            _other => {}
         }
         return Ok(None);
     }
@@ -128,6 +581,7 @@ impl CommandCollector for CliCommandCollector {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::Command;
     use crossterm::terminal::is_raw_mode_enabled;
 
     #[test]
@@ -140,23 +594,69 @@ mod tests {
         }
         assert!(!is_raw_mode_enabled().unwrap());
     }
-    struct TestTurnTimerSubscriber {
-        outputs: Vec<TimerStatus>,
-    }
-    impl TurnTimerSubscriberTrait for TestTurnTimerSubscriber {
-        fn get_timer_status(&mut self) -> TimerStatus {
-            self.outputs.pop().unwrap_or(TimerStatus::TimerComplete)
+    #[test]
+    fn test_playback_replays_inputs_when_they_are_due() {
+        let mut recording = Recording::new(1, crate::mode::GameMode::Sprint);
+        for (tick, command) in [
+            (0, Command::Pause),
+            (10, Command::MoveLeft),
+            (50, Command::HardDrop),
+        ] {
+            recording.inputs.push(RecordedInput {
+                tick,
+                input: KeyInput {
+                    command,
+                    state: KeyState::Tapped,
+                },
+            });
         }
+        let mut playback = PlaybackCommandCollector::from_recording(&recording);
+        let start = playback.start;
+        let after = |millis: u64| start + Duration::from_millis(millis);
+        assert_eq!(playback.next_due(after(5)), None);
+        assert_eq!(
+            playback.next_due(after(30)).map(|input| input.command),
+            Some(Command::MoveLeft)
+        );
+        assert_eq!(playback.next_due(after(30)), None);
+        assert_eq!(
+            playback.next_due(after(60)).map(|input| input.command),
+            Some(Command::HardDrop)
+        );
+        assert_eq!(playback.next_due(after(100)), None);
+    }
+    #[test]
+    fn test_only_terminal_repeats_are_coalesced() {
+        let input = |command, state| KeyInput { command, state };
+        let tap = input(Command::MoveLeft, KeyState::Tapped);
+        let repeat = input(Command::MoveRight, KeyState::Repeated);
+        let down = input(Command::MoveDown, KeyState::Pressed);
+        let released = input(Command::MoveDown, KeyState::Released);
+        let now = Instant::now();
+        let inputs = [tap, tap, down, down, repeat, repeat, down, released, down];
+        let coalesced: Vec<KeyInput> = coalesce_inputs(inputs.map(|input| (input, now)))
+            .into_iter()
+            .map(|(input, _)| input)
+            .collect();
+        assert_eq!(
+            coalesced,
+            vec![tap, tap, down, repeat, repeat, released, down]
+        );
+    }
+    #[test]
+    fn test_ctrl_c_is_an_interrupt_but_c_is_not() {
+        assert!(is_interrupt(&ctrl_c()));
+        let c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::NONE);
+        assert!(!is_interrupt(&c));
     }
-
     struct TestCommandCollector {
-        outputs: Vec<std::io::Result<Option<Command>>>,
+        outputs: Vec<Result<Option<Collected>, InputError>>,
     }
     impl CommandCollector for TestCommandCollector {
         fn new() -> Self {
             Self { outputs: vec![] }
         }
-        fn get_command(&mut self) -> std::io::Result<Option<Command>> {
+        fn get_command(&mut self) -> Result<Option<Collected>, InputError> {
             match self.outputs.pop() {
                 Some(val) => val,
                 None => Ok(None),
@@ -164,81 +664,27 @@ mod tests {
         }
     }
     #[test]
-    fn test_loop_does_exit_on_invalid_input() {
-        let mut test_turn_timer = TestTurnTimerSubscriber {
-            outputs: vec![
-                TimerStatus::TimerNotComplete,
-                TimerStatus::TimerNotComplete,
-                TimerStatus::TimerNotComplete,
-            ],
-        };
-        let (command_dispatcher, _command_reciever) = mpsc::channel();
-        let (_turn_event_sender, turn_event_reciever) = mpsc::channel::<TurnEvent>();
-        let mut command_collector = TestCommandCollector::new();
-        command_collector.outputs.push(Ok(Some(Command::MoveDown)));
-        command_collector
-            .outputs
-            .push(Err(std::io::Error::new(std::io::ErrorKind::NotFound, "")));
-
-        run_user_input_loop::<TestCommandCollector, TestTurnTimerSubscriber>(
-            &mut test_turn_timer,
-            command_dispatcher,
-            command_collector,
-            turn_event_reciever,
-        );
-        assert_eq!(
-            test_turn_timer.get_timer_status(),
-            TimerStatus::TimerNotComplete
-        );
-    }
-    #[test]
-    fn test_loop_exits_on_end_turn_event() {
-        let mut test_turn_timer = TestTurnTimerSubscriber {
-            outputs: vec![
-                TimerStatus::TimerComplete,
-                TimerStatus::TimerComplete,
-                TimerStatus::TimerNotComplete,
-                TimerStatus::TimerNotComplete,
-                TimerStatus::TimerNotComplete,
-            ],
-        };
-        let (command_dispatcher, _command_reciever) = mpsc::channel();
-        let (turn_event_sender, turn_event_reciever) = mpsc::channel::<TurnEvent>();
+    fn test_frame_inputs_note_the_last_resize() {
         let mut command_collector = TestCommandCollector::new();
-        command_collector.outputs.push(Ok(Some(Command::MoveDown)));
-        turn_event_sender
-            .send(TurnEvent::EndTurn)
-            .expect("Sent end turn event to closed channel.");
-        run_user_input_loop::<TestCommandCollector, TestTurnTimerSubscriber>(
-            &mut test_turn_timer,
-            command_dispatcher,
-            command_collector,
-            turn_event_reciever,
-        );
-        assert_eq!(test_turn_timer.outputs.len(), 5);
-    }
-    #[test]
-    fn test_loop_does_not_exit_on_valid_input() {
-        let mut test_turn_timer = TestTurnTimerSubscriber {
-            outputs: vec![
-                TimerStatus::TimerComplete,
-                TimerStatus::TimerComplete,
-                TimerStatus::TimerNotComplete,
-                TimerStatus::TimerNotComplete,
-                TimerStatus::TimerNotComplete,
-            ],
+        let down = KeyInput {
+            command: Command::MoveDown,
+            state: KeyState::Tapped,
         };
-        let (command_dispatcher, _command_reciever) = mpsc::channel();
-        let (_turn_event_sender, turn_event_reciever) = mpsc::channel::<TurnEvent>();
-        let mut command_collector = TestCommandCollector::new();
-        command_collector.outputs.push(Ok(Some(Command::MoveDown)));
-
-        run_user_input_loop::<TestCommandCollector, TestTurnTimerSubscriber>(
-            &mut test_turn_timer,
-            command_dispatcher,
-            command_collector,
-            turn_event_reciever,
-        );
-        assert_eq!(test_turn_timer.outputs.len(), 1);
+        // Outputs are popped from the back.
+        command_collector.outputs = vec![
+            Err(InputError::QuitRequested),
+            Ok(Some(Collected::Resized(60, 30))),
+            Ok(Some(Collected::Key(down))),
+            Ok(Some(Collected::Resized(40, 20))),
+        ];
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let frame_inputs = read_frame_inputs(&mut command_collector, deadline);
+        let commands: Vec<Command> = frame_inputs
+            .inputs
+            .iter()
+            .map(|(input, _)| input.command)
+            .collect();
+        assert_eq!(commands, vec![Command::MoveDown, Command::Quit]);
+        assert_eq!(frame_inputs.resized, Some((60, 30)));
     }
 }
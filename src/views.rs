@@ -1,64 +1,724 @@
-use crate::models::{Coord, TetrisBoard, TetrisPiece};
+use crate::board::TetrisBoard;
+use crate::finesse::FinesseTracker;
+use crate::game::HardDrop;
+use crate::items::Item;
+use crate::keymap::Keymap;
+use crate::layout::Layout;
+use crate::leaderboard::format_time;
+use crate::models::{Command, Coord, KeyInput, KeyState};
+use crate::piece::{PieceSet, PieceShape, TetrisPiece};
+use crate::puzzle::Puzzle;
+use crate::score::{LineClear, Score};
+use crate::theme::{GhostStyle, Theme};
+use crate::zone::Zone;
 use crossterm::terminal;
 use crossterm::{cursor, execute, queue, style};
+use std::collections::{HashMap, VecDeque};
 use std::io;
 use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// Tracks when each cell of the stack was last seen, so that in invisible
+/// mode locked cells can fade from view. The whole stack flashes back into
+/// view whenever lines are cleared.
+struct StackFade {
+    seen_at: Vec<Vec<Option<Instant>>>,
+    filled_cells: usize,
+}
+impl StackFade {
+    /// How long a locked cell stays visible. It is dimmed for the second
+    /// half of this time.
+    const FADE: Duration = Duration::from_millis(1500);
+
+    fn new() -> Self {
+        Self {
+            seen_at: vec![vec![None; TetrisBoard::NUM_COLS]; TetrisBoard::NUM_ROWS],
+            filled_cells: 0,
+        }
+    }
+    fn update(&mut self, tetris_board: &TetrisBoard, now: Instant) {
+        let filled_cells = tetris_board.board.iter().flatten().filter(|x| **x).count();
+        let flash = filled_cells < self.filled_cells;
+        self.filled_cells = filled_cells;
+        for (row, line) in tetris_board.board.iter().enumerate() {
+            for (col, filled) in line.iter().enumerate() {
+                let seen_at = &mut self.seen_at[row][col];
+                *seen_at = match (filled, *seen_at) {
+                    (false, _) => None,
+                    (true, Some(seen_at)) if !flash => Some(seen_at),
+                    (true, _) => Some(now),
+                };
+            }
+        }
+    }
+    /// Replaces the cells of a board view with how visible they are now.
+    fn apply(&self, view_lines: &mut [String], now: Instant) {
+        for (row, line) in view_lines.iter_mut().enumerate() {
+            let mut line_chars = line.clone().into_bytes();
+            for (col, seen_at) in self.seen_at[row].iter().enumerate() {
+                if let Some(seen_at) = seen_at {
+                    let age = now.duration_since(*seen_at);
+                    line_chars[col + 1] = if age < Self::FADE / 2 {
+                        b'o'
+                    } else if age < Self::FADE {
+                        b'.'
+                    } else {
+                        b' '
+                    };
+                }
+            }
+            *line = String::from_utf8(line_chars).expect("Error converting to string.");
+        }
+    }
+}
+
+/// The trail left above a hard dropped piece, in each of its columns from
+/// where it started to where it landed, which fades over a few frames.
+struct DropTrail {
+    /// The piece number of the last drop seen, so that each drop only
+    /// leaves one trail.
+    piece_number: Option<usize>,
+    trail: Vec<Coord>,
+    /// Colour of the piece that left the trail.
+    color: style::Color,
+    left_at: Option<Instant>,
+}
+impl DropTrail {
+    /// How long a trail stays visible. It is dimmed for the second half of
+    /// this time.
+    const FADE: Duration = Duration::from_millis(150);
+
+    fn new() -> Self {
+        Self {
+            piece_number: None,
+            trail: Vec::new(),
+            color: style::Color::Reset,
+            left_at: None,
+        }
+    }
+    /// Drops the trail being shown, still remembering which drop left it so
+    /// that it is not shown again.
+    fn erase(&mut self) {
+        self.trail.clear();
+        self.left_at = None;
+    }
+    /// Starts a trail for `hard_drop` if it is one not seen before.
+    fn update(&mut self, hard_drop: Option<&HardDrop>, theme: &Theme, now: Instant) {
+        let Some(hard_drop) = hard_drop else {
+            return;
+        };
+        if self.piece_number == Some(hard_drop.piece_number) {
+            return;
+        }
+        self.piece_number = Some(hard_drop.piece_number);
+        let coords = hard_drop.piece.coordinates();
+        self.trail = coords
+            .iter()
+            .filter(|coord| {
+                !coords
+                    .iter()
+                    .any(|other| other.col == coord.col && other.row < coord.row)
+            })
+            .flat_map(|top| {
+                (top.row - hard_drop.distance..top.row).map(|row| Coord { col: top.col, row })
+            })
+            .filter(TetrisBoard::is_on_board)
+            .collect();
+        self.color = theme.color(hard_drop.piece.piece_shape());
+        self.left_at = Some(now);
+    }
+    fn is_fading(&self, now: Instant) -> bool {
+        self.left_at
+            .is_some_and(|left_at| now.duration_since(left_at) < Self::FADE)
+    }
+    /// Puts the trail into the empty cells of the frame, in the piece's
+    /// colour and then the theme's ghost colour as it fades.
+    fn apply(&self, frame: &mut [Vec<Cell>], theme: &Theme, now: Instant) {
+        let Some(left_at) = self.left_at else {
+            return;
+        };
+        let color = match now.duration_since(left_at) {
+            age if age < Self::FADE / 2 => self.color,
+            age if age < Self::FADE => theme.ghost,
+            _faded => return,
+        };
+        for coord in &self.trail {
+            let cell = &mut frame[coord.row as usize][(coord.col + 1) as usize];
+            if cell.symbol == ' ' {
+                *cell = Cell {
+                    symbol: '│',
+                    style: CellStyle {
+                        color,
+                        ..cell.style
+                    },
+                };
+            }
+        }
+    }
+}
+
+/// The keys held and the last commands given, shown beside the board like
+/// a fighting game's input viewer.
+struct InputDisplay {
+    held: Vec<Command>,
+    /// The most recent command first.
+    recent: VecDeque<Command>,
+}
+impl InputDisplay {
+    /// How many of the last commands are listed.
+    const HISTORY: usize = 8;
+
+    fn new() -> Self {
+        Self {
+            held: Vec::new(),
+            recent: VecDeque::with_capacity(Self::HISTORY),
+        }
+    }
+    fn observe(&mut self, input: KeyInput) {
+        match input.state {
+            KeyState::Released => {
+                self.held.retain(|command| *command != input.command);
+                return;
+            }
+            KeyState::Pressed if self.held.contains(&input.command) => return,
+            KeyState::Pressed => self.held.push(input.command),
+            KeyState::Tapped => {}
+            // The key is already listed from when it was pressed.
+            KeyState::Repeated => return,
+        }
+        if self.recent.len() == Self::HISTORY {
+            self.recent.pop_back();
+        }
+        self.recent.push_front(input.command);
+    }
+    /// A short name for a command, to fit the panel.
+    fn label(command: Command) -> String {
+        let label = match command {
+            Command::MoveLeft => "Left",
+            Command::MoveRight => "Right",
+            Command::MoveDown => "Down",
+            Command::HardDrop => "Drop",
+            Command::SonicDrop => "Sonic",
+            Command::RotateClockwise => "CW",
+            Command::RotateAnticlockwise => "CCW",
+            Command::Rotate180 => "180",
+            Command::Hold => "Hold",
+            Command::ActivateZone => "Zone",
+            Command::MoveToColumn(col) => return format!("Col {}", col),
+            Command::Pause => "Pause",
+            Command::Quit => "Quit",
+            Command::Restart => "Restart",
+            Command::MenuUp | Command::MenuDown | Command::Select | Command::Back => "",
+        };
+        label.to_string()
+    }
+    fn lines(&self) -> Vec<String> {
+        let held: Vec<String> = self
+            .held
+            .iter()
+            .map(|command| Self::label(*command))
+            .collect();
+        let mut lines = vec![String::from("Input:"), format!("Held: {}", held.join(" "))];
+        lines.extend((0..Self::HISTORY).map(|i| {
+            self.recent.get(i).map_or(String::new(), |command| {
+                format!("  {}", Self::label(*command))
+            })
+        }));
+        lines
+    }
+}
+
+/// How a cell of the board is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CellStyle {
+    color: style::Color,
+    background: style::Color,
+    underlined: bool,
+    reversed: bool,
+}
+
+impl CellStyle {
+    fn in_color(color: style::Color) -> Self {
+        Self {
+            color,
+            background: style::Color::Reset,
+            underlined: false,
+            reversed: false,
+        }
+    }
+}
+
+/// A character cell of the board as drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cell {
+    symbol: char,
+    style: CellStyle,
+}
+impl Cell {
+    /// The colour of the block in the cell, if it holds one: a piece, the
+    /// stack, or a fading block of the stack in invisible mode. Empty cells,
+    /// walls and text hold none.
+    fn block_color(&self) -> Option<style::Color> {
+        match self.symbol {
+            'x' | 'o' => Some(self.style.color),
+            '.' => Some(style::Color::DarkGrey),
+            _other => None,
+        }
+    }
+    fn is_block(&self) -> bool {
+        self.symbol == ' ' || self.block_color().is_some()
+    }
+    /// The cell showing `upper` above `lower` in half blocks. Cells that are
+    /// not blocks, such as the walls or text across the board, are drawn as
+    /// they are instead, preferring the upper one. The default colour of the
+    /// terminal is unknown, so it is only ever used as the foreground.
+    fn half_blocks(upper: &Cell, lower: &Cell) -> Cell {
+        let style = CellStyle {
+            underlined: lower.style.underlined,
+            reversed: upper.style.reversed || lower.style.reversed,
+            ..CellStyle::in_color(upper.style.color)
+        };
+        if !upper.is_block() {
+            return Cell { style, ..*upper };
+        }
+        if !lower.is_block() {
+            return Cell {
+                style: CellStyle {
+                    color: lower.style.color,
+                    ..style
+                },
+                ..*lower
+            };
+        }
+        let (symbol, color, background) = match (upper.block_color(), lower.block_color()) {
+            (None, None) => (' ', style.color, style::Color::Reset),
+            (Some(upper), None) => ('▀', upper, style::Color::Reset),
+            (None, Some(lower)) => ('▄', lower, style::Color::Reset),
+            (Some(upper), Some(lower)) if upper == lower => ('█', upper, style::Color::Reset),
+            (Some(upper), Some(style::Color::Reset)) => ('▄', style::Color::Reset, upper),
+            (Some(upper), Some(lower)) => ('▀', upper, lower),
+        };
+        Cell {
+            symbol,
+            style: CellStyle {
+                color,
+                background,
+                ..style
+            },
+        }
+    }
+}
+
+/// The board as it was last drawn, kept as a back buffer so that each frame
+/// only the cells that changed are drawn again. The screen is not redrawn
+/// every frame, so it does not flicker, even over a slow connection.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct BoardBuffer {
+    drawn: Vec<Vec<Cell>>,
+}
+impl BoardBuffer {
+    /// Forgets what was drawn, e.g. once the screen is cleared, so that the
+    /// next frame is drawn in full.
+    fn invalidate(&mut self) {
+        self.drawn.clear();
+    }
+    /// Takes the frame about to be drawn in place of the last, returning the
+    /// column, row and cell of every cell that differs from it.
+    fn swap(&mut self, frame: Vec<Vec<Cell>>) -> Vec<(u16, u16, Cell)> {
+        let drawn = &self.drawn;
+        let changes = frame
+            .iter()
+            .enumerate()
+            .flat_map(|(row, cells)| {
+                cells
+                    .iter()
+                    .enumerate()
+                    .filter(move |(col, cell)| {
+                        drawn.get(row).and_then(|drawn_row| drawn_row.get(*col)) != Some(cell)
+                    })
+                    .map(move |(col, cell)| (col as u16, row as u16, *cell))
+            })
+            .collect();
+        self.drawn = frame;
+        changes
+    }
+}
+
+/// Whether the terminal may not show Unicode or colour, going by the
+/// environment variables looked up with `var`: `NO_COLOR` is set, or `TERM`
+/// is unset or `dumb`, or the locale is not UTF-8. Windows terminals set
+/// neither `TERM` nor a locale, so only `NO_COLOR` counts there.
+pub fn needs_ascii(var: impl Fn(&str) -> Option<String>) -> bool {
+    if var("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return true;
+    }
+    if cfg!(windows) {
+        return false;
+    }
+    if var("TERM").is_none_or(|term| term.is_empty() || term == "dumb") {
+        return true;
+    }
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|name| var(name).filter(|value| !value.is_empty()))
+        .unwrap_or_default()
+        .to_lowercase();
+    !(locale.contains("utf-8") || locale.contains("utf8"))
+}
 
 pub struct CliView<W: Write> {
     writer: W,
+    board: BoardBuffer,
+    /// Lines of text beside the board as last drawn, by column and row, so
+    /// that text drawn every frame is only written when it changes.
+    lines: HashMap<(u16, u16), String>,
+    /// The pieces to come as last drawn, to draw them only when they change.
+    next_drawn: Option<Vec<PieceShape>>,
+    /// The held piece as last drawn, and whether it could be held.
+    hold_drawn: Option<(Option<PieceShape>, bool)>,
+    /// Set when the board is drawn two rows to a line in half blocks.
+    half_blocks: bool,
+    /// Set when only ASCII is drawn, with no colour.
+    ascii: bool,
+    /// Set in invisible mode, where the stack fades from view.
+    stack_fade: Option<StackFade>,
+    /// Unset with reduced motion, so that hard drops leave no trail.
+    drop_trail: Option<DropTrail>,
+    /// Set when the inputs are shown beside the board.
+    input_display: Option<InputDisplay>,
+    theme: Theme,
+    /// The keys in play, for the intro to list.
+    keymap: Keymap,
+    /// Size of the terminal, to lay the screen out for.
+    size: (u16, u16),
+    /// Where each part of the screen is drawn.
+    layout: Layout,
 }
 impl CliView<io::Stdout> {
+    /// Takes over the terminal, laid out for its size.
     pub fn new() -> Self {
         let mut writer = io::stdout();
-        execute!(writer, terminal::EnterAlternateScreen).unwrap();
-        Self { writer }
+        execute!(writer, terminal::EnterAlternateScreen, cursor::Hide).unwrap();
+        let mut view = Self::with_writer(writer);
+        if let Ok((cols, rows)) = terminal::size() {
+            view.size = (cols, rows);
+            view.layout = Layout::for_size(cols, rows, false);
+        }
+        view
+    }
+}
+impl Default for CliView<io::Stdout> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 impl<W: Write> Drop for CliView<W> {
     fn drop(&mut self) {
-        execute!(self.writer, terminal::LeaveAlternateScreen).expect(
-            "Failed to exit alternate screen. Restart terminal to resume normal behaviour.",
-        );
+        execute!(
+            self.writer,
+            style::ResetColor,
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        )
+        .expect("Failed to exit alternate screen. Restart terminal to resume normal behaviour.");
     }
 }
 impl<W: Write> CliView<W> {
+    fn with_writer(writer: W) -> Self {
+        Self {
+            writer,
+            board: BoardBuffer::default(),
+            lines: HashMap::new(),
+            next_drawn: None,
+            hold_drawn: None,
+            half_blocks: false,
+            ascii: false,
+            stack_fade: None,
+            drop_trail: Some(DropTrail::new()),
+            input_display: None,
+            theme: Theme::default(),
+            keymap: Keymap::default(),
+            size: (Layout::WIDTH, Layout::HEIGHT),
+            layout: Layout::default(),
+        }
+    }
+    /// Sets the colours pieces are drawn in, unless drawing only ASCII.
+    pub fn set_theme(&mut self, theme: Theme) {
+        if !self.ascii {
+            self.theme = theme;
+        }
+    }
+    /// Draws only ASCII, with no colour and no half blocks: the stack as
+    /// `#` on a board of `.`, for terminals that cannot show Unicode or
+    /// colour.
+    pub fn set_ascii(&mut self, ascii: bool) {
+        self.ascii = ascii;
+        if ascii {
+            self.theme = Theme::monochrome();
+        }
+        self.board.invalidate();
+    }
+    /// Makes locked pieces fade from view, for invisible mode.
+    pub fn set_invisible_stack(&mut self, invisible: bool) {
+        self.stack_fade = match invisible {
+            true => Some(StackFade::new()),
+            false => None,
+        };
+    }
+    /// Lays the screen out for a terminal `cols` wide and `rows` tall,
+    /// clearing it if anything moves so that it is drawn afresh.
+    pub fn set_size(&mut self, cols: u16, rows: u16) -> std::io::Result<()> {
+        self.size = (cols, rows);
+        self.lay_out()
+    }
+    /// Lays the screen out for the size of the terminal and whether the
+    /// input display is shown, clearing it if anything moves.
+    fn lay_out(&mut self) -> std::io::Result<()> {
+        let (cols, rows) = self.size;
+        let layout = Layout::for_size(cols, rows, self.input_display.is_some());
+        if layout != self.layout {
+            self.layout = layout;
+            self.clear()?;
+        }
+        return Ok(());
+    }
+    /// Leaves out effects that only move for show, such as the trail a hard
+    /// drop leaves.
+    pub fn set_reduced_motion(&mut self, reduced_motion: bool) {
+        self.drop_trail = match reduced_motion {
+            true => None,
+            false => Some(DropTrail::new()),
+        };
+    }
+    /// Whether the trail of a hard drop is still fading, so that the board
+    /// needs drawing again.
+    pub fn is_animating(&self) -> bool {
+        self.drop_trail
+            .as_ref()
+            .is_some_and(|drop_trail| drop_trail.is_fading(Instant::now()))
+    }
+    /// Whether the game, with the input display if it is shown, fits in a
+    /// terminal `cols` wide and `rows` tall.
+    pub fn fits(&self, cols: u16, rows: u16) -> bool {
+        Layout::fits(cols, rows, self.input_display.is_some())
+    }
+    /// Shows the keys held and the last commands given beside the board,
+    /// making room for them right of the side panel.
+    pub fn set_input_display(&mut self, shown: bool) -> std::io::Result<()> {
+        self.input_display = shown.then(InputDisplay::new);
+        self.lay_out()
+    }
+    /// Lists the keys of `keymap` in the intro.
+    pub fn set_keymap(&mut self, keymap: &Keymap) {
+        self.keymap = keymap.clone();
+    }
+    /// Draws the board with square cells of half blocks, two rows to a line.
+    pub fn set_half_blocks(&mut self, half_blocks: bool) {
+        self.half_blocks = half_blocks;
+        self.board.invalidate();
+    }
+    /// Notes an input for the input display, if it is shown.
+    pub fn observe_input(&mut self, input: KeyInput) {
+        if let Some(input_display) = &mut self.input_display {
+            input_display.observe(input);
+        }
+    }
+    pub fn draw_input_display(&mut self) -> std::io::Result<()> {
+        let Some(input_display) = &self.input_display else {
+            return Ok(());
+        };
+        for (i, line) in input_display.lines().iter().enumerate() {
+            queue!(
+                self.writer,
+                self.layout.input_display(9 + i as u16),
+                style::Print(format!(
+                    "{:<width$}",
+                    line,
+                    width = Layout::INPUT_DISPLAY_WIDTH as usize
+                )),
+            )?;
+        }
+        self.writer.flush()?;
+        return Ok(());
+    }
     fn generate_board_string_view(tetris_board: &TetrisBoard) -> Vec<String> {
         let mut view_lines: Vec<String> = Vec::with_capacity(tetris_board.board.len());
         for line in &tetris_board.board {
-            let mut line_chars: Vec<u8> = vec!['|' as u8];
+            let mut line_chars: Vec<u8> = vec![b'|'];
             line_chars.extend(line.iter().map(|x| match x {
-                true => 'o' as u8,
-                false => ' ' as u8,
+                true => b'o',
+                false => b' ',
             }));
-            line_chars.push('|' as u8);
+            line_chars.push(b'|');
             view_lines.push(String::from_utf8(line_chars).expect("Error converting to string."));
         }
         return view_lines;
     }
-    fn draw_board(&mut self, board_string: Vec<String>) -> std::io::Result<()> {
-        queue!(self.writer, cursor::MoveTo(0, 0),)?;
-        for line in &board_string[0..board_string.len() - 1] {
-            queue!(self.writer, style::Print(line), cursor::MoveToNextLine(1),)?;
+    /// The cells of the board drawn from its lines, with the bottom row
+    /// underlined as the floor. The walls and floor are drawn in the theme's
+    /// border colour and the stack in its text colour.
+    fn board_cells(board_string: &[String], theme: &Theme) -> Vec<Vec<Cell>> {
+        let floor = board_string.len().saturating_sub(1);
+        board_string
+            .iter()
+            .enumerate()
+            .map(|(row, line)| {
+                let last = line.chars().count().saturating_sub(1);
+                line.chars()
+                    .enumerate()
+                    .map(|(col, symbol)| {
+                        let is_border = col == 0 || col == last || (row == floor && symbol == ' ');
+                        let color = match is_border {
+                            true => theme.border,
+                            false => theme.text,
+                        };
+                        let style = CellStyle {
+                            underlined: row == floor,
+                            ..CellStyle::in_color(color)
+                        };
+                        Cell { symbol, style }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    /// Puts the cells of the piece that are on the board into the frame in
+    /// the given colour. Cells in the vanish zone above the board are
+    /// hidden.
+    fn place_piece(frame: &mut [Vec<Cell>], piece_coordinates: Vec<Coord>, color: style::Color) {
+        for coord in piece_coordinates
+            .iter()
+            .filter(|c| TetrisBoard::is_on_board(c))
+        {
+            frame[coord.row as usize][(coord.col + 1) as usize] = Cell {
+                symbol: 'x',
+                style: CellStyle::in_color(color),
+            };
         }
-        queue!(
-            self.writer,
-            style::SetAttribute(style::Attribute::Underlined),
-            style::Print(&board_string[board_string.len() - 1]),
-            style::SetAttribute(style::Attribute::NoUnderline),
-        )?;
-
-        self.writer.flush()?;
-
-        return Ok(());
     }
-    fn draw_piece(&mut self, piece_coordinates: Vec<Coord>) -> std::io::Result<()> {
-        for coord in piece_coordinates {
+    /// Puts the ghost piece into the frame, drawn in the theme's ghost style
+    /// for a piece of the given colour. Cells in the vanish zone above the
+    /// board are hidden.
+    fn place_ghost(
+        frame: &mut [Vec<Cell>],
+        ghost_coordinates: Vec<Coord>,
+        color: style::Color,
+        theme: &Theme,
+    ) {
+        let cell = match theme.ghost_style {
+            GhostStyle::Outline => Cell {
+                symbol: '□',
+                style: CellStyle::in_color(color),
+            },
+            GhostStyle::Dim => Cell {
+                symbol: 'x',
+                style: CellStyle::in_color(theme.ghost),
+            },
+            GhostStyle::Glyph => Cell {
+                symbol: '*',
+                style: CellStyle::in_color(theme.text),
+            },
+            GhostStyle::Off => return,
+        };
+        for coord in ghost_coordinates
+            .iter()
+            .filter(|c| TetrisBoard::is_on_board(c))
+        {
+            frame[coord.row as usize][(coord.col + 1) as usize] = cell;
+        }
+    }
+    /// The frame with blocks and empty cells drawn in plain ASCII. The
+    /// fading stack of invisible mode is drawn as `:`, an outlined ghost
+    /// piece as `+` and the trail of a hard drop as `|`.
+    fn ascii_cells(mut frame: Vec<Vec<Cell>>) -> Vec<Vec<Cell>> {
+        for cell in frame.iter_mut().flatten() {
+            cell.symbol = match cell.symbol {
+                'o' => '#',
+                '.' => ':',
+                '□' => '+',
+                '│' => '|',
+                ' ' => '.',
+                other => other,
+            };
+        }
+        frame
+    }
+    /// The frame with each pair of rows merged into one line of half blocks.
+    fn half_block_cells(frame: &[Vec<Cell>]) -> Vec<Vec<Cell>> {
+        frame
+            .chunks(2)
+            .map(|rows| match rows {
+                [upper, lower] => upper
+                    .iter()
+                    .zip(lower)
+                    .map(|(upper, lower)| Cell::half_blocks(upper, lower))
+                    .collect(),
+                rows => rows[0].clone(),
+            })
+            .collect()
+    }
+    /// Draws a frame of the board, writing only the cells that changed since
+    /// the last frame. The cursor is only moved to skip over cells that
+    /// stayed the same, and the style only set when it changes. The text
+    /// colour is left set for whatever is written next.
+    fn draw_cells(&mut self, frame: Vec<Vec<Cell>>) -> std::io::Result<()> {
+        let frame = match (self.ascii, self.half_blocks) {
+            (true, _) => Self::ascii_cells(frame),
+            (false, true) => Self::half_block_cells(&frame),
+            (false, false) => frame,
+        };
+        let mut cursor_at = None;
+        let plain = CellStyle::in_color(self.theme.text);
+        let mut current = plain;
+        for (col, row, cell) in self.board.swap(frame) {
+            if cursor_at != Some((col, row)) {
+                queue!(self.writer, self.layout.board(col, row))?;
+            }
+            if cell.style.color != current.color {
+                queue!(self.writer, style::SetForegroundColor(cell.style.color))?;
+            }
+            if cell.style.background != current.background {
+                queue!(
+                    self.writer,
+                    style::SetBackgroundColor(cell.style.background)
+                )?;
+            }
+            if cell.style.underlined != current.underlined {
+                let attribute = match cell.style.underlined {
+                    true => style::Attribute::Underlined,
+                    false => style::Attribute::NoUnderline,
+                };
+                queue!(self.writer, style::SetAttribute(attribute))?;
+            }
+            if cell.style.reversed != current.reversed {
+                let attribute = match cell.style.reversed {
+                    true => style::Attribute::Reverse,
+                    false => style::Attribute::NoReverse,
+                };
+                queue!(self.writer, style::SetAttribute(attribute))?;
+            }
+            queue!(self.writer, style::Print(cell.symbol))?;
+            current = cell.style;
+            cursor_at = Some((col + 1, row));
+        }
+        if current.underlined {
             queue!(
                 self.writer,
-                cursor::MoveTo((coord.col + 1) as u16, coord.row as u16),
-                style::Print("x")
+                style::SetAttribute(style::Attribute::NoUnderline)
             )?;
         }
+        if current.reversed {
+            queue!(
+                self.writer,
+                style::SetAttribute(style::Attribute::NoReverse)
+            )?;
+        }
+        if current.background != plain.background {
+            queue!(self.writer, style::SetBackgroundColor(plain.background))?;
+        }
+        if current.color != plain.color {
+            queue!(self.writer, style::SetForegroundColor(plain.color))?;
+        }
         self.writer.flush()?;
         return Ok(());
     }
@@ -66,60 +726,633 @@ impl<W: Write> CliView<W> {
         &mut self,
         piece: &TetrisPiece,
         board: &TetrisBoard,
+        hard_drop: Option<&HardDrop>,
+    ) -> std::io::Result<()> {
+        let now = Instant::now();
+        let mut board_string = Self::generate_board_string_view(board);
+        if let Some(stack_fade) = &mut self.stack_fade {
+            stack_fade.update(board, now);
+            stack_fade.apply(&mut board_string, now);
+        }
+        let mut frame = Self::board_cells(&board_string, &self.theme);
+        if let Some(drop_trail) = &mut self.drop_trail {
+            drop_trail.update(hard_drop, &self.theme, now);
+            drop_trail.apply(&mut frame, &self.theme, now);
+        }
+        let color = self.theme.color(piece.piece_shape());
+        let ghost = piece.shifted(0, piece.drop_distance(board));
+        Self::place_ghost(&mut frame, ghost.coordinates(), color, &self.theme);
+        Self::place_piece(&mut frame, piece.coordinates(), color);
+        self.draw_cells(frame)
+    }
+    /// Draws the board with no active piece and `text` written across its
+    /// middle, for the countdown before a game starts and while paused.
+    pub fn draw_board_text(&mut self, board: &TetrisBoard, text: &str) -> std::io::Result<()> {
+        let mut board_string = Self::generate_board_string_view(board);
+        let middle = &mut board_string[TetrisBoard::NUM_ROWS / 2 - 1];
+        let start = 1 + TetrisBoard::NUM_COLS.saturating_sub(text.len()) / 2;
+        let end = (start + text.len()).min(1 + TetrisBoard::NUM_COLS);
+        middle.replace_range(start..end, &text[..end - start]);
+        self.draw_cells(Self::board_cells(&board_string, &self.theme))
+    }
+    /// Draws the held piece in a panel big enough for any piece of the set,
+    /// with the piece shifted to the top left of the panel.
+    fn generate_hold_string_view(
+        held_piece: Option<PieceShape>,
+        piece_set: PieceSet,
+    ) -> Vec<String> {
+        let (cols, rows) = piece_set.preview_size();
+        let mut view_lines = vec![vec![b' '; cols]; rows];
+        if let Some(piece_shape) = held_piece {
+            let shape = piece_shape.shape();
+            let min_col = shape.iter().map(|coord| coord.col).min().unwrap_or(0);
+            let min_row = shape.iter().map(|coord| coord.row).min().unwrap_or(0);
+            for coord in shape {
+                view_lines[(coord.row - min_row) as usize][(coord.col - min_col) as usize] = b'x';
+            }
+        }
+        return view_lines
+            .into_iter()
+            .map(|line| String::from_utf8(line).expect("Error converting to string."))
+            .collect();
+    }
+    /// Draws the held piece in a box to the left of the board, greyed out
+    /// while it cannot be held again for the current piece. It is only
+    /// drawn when it changes.
+    pub fn draw_hold(
+        &mut self,
+        held_piece: Option<PieceShape>,
+        piece_set: PieceSet,
+        can_hold: bool,
+    ) -> std::io::Result<()> {
+        if self.hold_drawn == Some((held_piece, can_hold)) {
+            return Ok(());
+        }
+        let held: Vec<PieceShape> = held_piece.into_iter().collect();
+        let mut lines = Self::piece_box_lines("Hold", &held, piece_set, self.ascii);
+        if !can_hold && self.ascii {
+            for (line, _) in lines.iter_mut().skip(1) {
+                *line = line.replace('x', ".");
+            }
+        }
+        let greyed = (!can_hold).then_some(style::Color::DarkGrey);
+        self.draw_piece_box(self.layout.hold(0, 0), &lines, greyed)?;
+        self.hold_drawn = Some((held_piece, can_hold));
+        return Ok(());
+    }
+    /// The lines of a box of pieces titled `title`, each with the piece
+    /// drawn on it if there is one. A box of no pieces is left empty. The
+    /// borders are ASCII when `ascii` is set.
+    fn piece_box_lines(
+        title: &str,
+        pieces: &[PieceShape],
+        piece_set: PieceSet,
+        ascii: bool,
+    ) -> Vec<(String, Option<PieceShape>)> {
+        let (cols, _) = piece_set.preview_size();
+        let (horizontal, vertical, corners) = match ascii {
+            true => ("-", '|', ['+', '+', '+', '+']),
+            false => ("─", '│', ['┌', '┐', '└', '┘']),
+        };
+        let mut lines = vec![(
+            format!(
+                "{}{}{}{}",
+                corners[0],
+                title,
+                horizontal.repeat(cols.saturating_sub(title.len())),
+                corners[1]
+            ),
+            None,
+        )];
+        if pieces.is_empty() {
+            for line in Self::generate_hold_string_view(None, piece_set) {
+                lines.push((format!("{}{}{}", vertical, line, vertical), None));
+            }
+        }
+        for (i, piece_shape) in pieces.iter().enumerate() {
+            if i > 0 {
+                lines.push((
+                    format!("{}{}{}", vertical, " ".repeat(cols), vertical),
+                    None,
+                ));
+            }
+            for line in Self::generate_hold_string_view(Some(*piece_shape), piece_set) {
+                lines.push((
+                    format!("{}{}{}", vertical, line, vertical),
+                    Some(*piece_shape),
+                ));
+            }
+        }
+        lines.push((
+            format!(
+                "{}{}{}",
+                corners[2],
+                horizontal.repeat(cols.max(title.len())),
+                corners[3]
+            ),
+            None,
+        ));
+        lines
+    }
+    /// Draws a box of pieces from `piece_box_lines` with its top left corner
+    /// at `corner`, the pieces in their colours unless `greyed` gives
+    /// another.
+    fn draw_piece_box(
+        &mut self,
+        corner: cursor::MoveTo,
+        lines: &[(String, Option<PieceShape>)],
+        greyed: Option<style::Color>,
+    ) -> std::io::Result<()> {
+        for (i, (line, piece_shape)) in lines.iter().enumerate() {
+            queue!(
+                self.writer,
+                cursor::MoveTo(corner.0, corner.1 + i as u16),
+                style::SetForegroundColor(self.theme.border),
+            )?;
+            let Some(piece_shape) = piece_shape else {
+                queue!(self.writer, style::Print(line))?;
+                continue;
+            };
+            let color = greyed.unwrap_or_else(|| self.theme.color(*piece_shape));
+            let mut chars = line.chars();
+            let (left, right) = (chars.next(), chars.next_back());
+            queue!(
+                self.writer,
+                style::Print(left.unwrap_or(' ')),
+                style::SetForegroundColor(color),
+                style::Print(chars.as_str()),
+                style::SetForegroundColor(self.theme.border),
+                style::Print(right.unwrap_or(' ')),
+            )?;
+        }
+        queue!(self.writer, style::SetForegroundColor(self.theme.text))?;
+        self.writer.flush()?;
+        return Ok(());
+    }
+    /// Draws the pieces to come in a box to the right of the board, next at
+    /// the top, each in its colour. They are only drawn when they change.
+    pub fn draw_next(
+        &mut self,
+        next_pieces: &[PieceShape],
+        piece_set: PieceSet,
+    ) -> std::io::Result<()> {
+        if self.next_drawn.as_deref() == Some(next_pieces) {
+            return Ok(());
+        }
+        let lines = Self::piece_box_lines("Next", next_pieces, piece_set, self.ascii);
+        self.draw_piece_box(self.layout.next(0, 0), &lines, None)?;
+        self.next_drawn = Some(next_pieces.to_vec());
+        return Ok(());
+    }
+    /// Lists the items waiting to trigger in party mode, oldest first.
+    fn items_string(items: &[Item]) -> String {
+        if items.is_empty() {
+            return String::new();
+        }
+        let names: Vec<&str> = items.iter().map(|item| item.name()).collect();
+        format!("Items: {}", names.join(", "))
+    }
+    fn chosen_pieces_string(chosen_pieces: &[PieceShape]) -> String {
+        if chosen_pieces.is_empty() {
+            return String::from("Chosen: -");
+        }
+        let names: Vec<&str> = chosen_pieces.iter().map(|shape| shape.name()).collect();
+        format!("Chosen: {}", names.join(" "))
+    }
+    pub fn draw_chosen_pieces(&mut self, chosen_pieces: &[PieceShape]) -> std::io::Result<()> {
+        queue!(
+            self.writer,
+            self.layout.side(0, 20),
+            terminal::Clear(terminal::ClearType::UntilNewLine),
+            style::Print(Self::chosen_pieces_string(chosen_pieces)),
+        )?;
+        self.writer.flush()?;
+        return Ok(());
+    }
+    /// Draws the practice editor: the board with no active piece, the cell
+    /// under the cursor highlighted, the pieces chosen so far and the keys.
+    pub fn draw_editor(
+        &mut self,
+        board: &TetrisBoard,
+        cursor: &Coord,
+        chosen_pieces: &[PieceShape],
     ) -> std::io::Result<()> {
         let board_string = Self::generate_board_string_view(board);
-        Self::draw_board(self, board_string)?;
-        Self::draw_piece(self, piece.coordinates())?;
-        Ok(())
+        let mut frame = Self::board_cells(&board_string, &self.theme);
+        let cell = &mut frame[cursor.row as usize][(cursor.col + 1) as usize];
+        cell.style.reversed = true;
+        self.draw_cells(frame)?;
+        queue!(
+            self.writer,
+            self.layout.side(0, 23),
+            style::Print(
+                "Paused. Arrows move, Space fills a cell, letters choose pieces, Backspace takes one back, Enter resumes."
+            ),
+        )?;
+        self.draw_chosen_pieces(chosen_pieces)
+    }
+    /// Removes the practice editor's key help once play resumes.
+    pub fn clear_editor(&mut self) -> std::io::Result<()> {
+        queue!(
+            self.writer,
+            self.layout.side(0, 23),
+            terminal::Clear(terminal::ClearType::UntilNewLine),
+        )?;
+        self.writer.flush()?;
+        return Ok(());
+    }
+    pub fn draw_items(&mut self, items: &[Item]) -> std::io::Result<()> {
+        queue!(
+            self.writer,
+            self.layout.side(0, 20),
+            terminal::Clear(terminal::ClearType::UntilNewLine),
+            style::Print(Self::items_string(items)),
+        )?;
+        self.writer.flush()?;
+        return Ok(());
+    }
+    /// Shows the name and goal of the puzzle being played.
+    pub fn draw_puzzle(&mut self, puzzle: &Puzzle) -> std::io::Result<()> {
+        queue!(
+            self.writer,
+            self.layout.side(0, 20),
+            style::Print(format!("{}: {}", puzzle.name, puzzle.description())),
+        )?;
+        self.writer.flush()?;
+        return Ok(());
+    }
+    /// Shows how much garbage is left to dig out in cheese mode, and the
+    /// time taken so far.
+    pub fn draw_dig_progress(
+        &mut self,
+        garbage_rows: usize,
+        elapsed: Duration,
+    ) -> std::io::Result<()> {
+        queue!(
+            self.writer,
+            self.layout.side(0, 20),
+            style::Print(format!(
+                "{:<24}",
+                format!("Garbage: {}  Time: {}", garbage_rows, format_time(elapsed))
+            )),
+        )?;
+        self.writer.flush()?;
+        return Ok(());
+    }
+    /// Shows the lines left to clear in sprint mode, and the time taken so
+    /// far.
+    pub fn draw_sprint_progress(
+        &mut self,
+        lines_left: u64,
+        elapsed: Duration,
+    ) -> std::io::Result<()> {
+        queue!(
+            self.writer,
+            self.layout.side(0, 20),
+            style::Print(format!(
+                "{:<24}",
+                format!("Lines left: {}  Time: {}", lines_left, format_time(elapsed))
+            )),
+        )?;
+        self.writer.flush()?;
+        return Ok(());
+    }
+    /// Shows the time until the next garbage row rises, and the time
+    /// survived.
+    pub fn draw_survival(
+        &mut self,
+        until_next: Duration,
+        elapsed: Duration,
+    ) -> std::io::Result<()> {
+        queue!(
+            self.writer,
+            self.layout.side(0, 20),
+            style::Print(format!(
+                "{:<24}",
+                format!(
+                    "Rise: {:.1}s  Time: {}",
+                    until_next.as_secs_f64(),
+                    format_time(elapsed)
+                )
+            )),
+        )?;
+        self.writer.flush()?;
+        return Ok(());
     }
+    pub fn draw_dig_race(
+        &mut self,
+        garbage_cleared: usize,
+        time_left: Duration,
+    ) -> std::io::Result<()> {
+        queue!(
+            self.writer,
+            self.layout.side(0, 20),
+            style::Print(format!(
+                "{:<24}",
+                format!("Dug: {}  Left: {}", garbage_cleared, format_time(time_left))
+            )),
+        )?;
+        self.writer.flush()?;
+        return Ok(());
+    }
+    /// Shows how many placements were finesse errors.
+    pub fn draw_finesse(&mut self, finesse: &FinesseTracker) -> std::io::Result<()> {
+        queue!(
+            self.writer,
+            self.layout.side(0, 21),
+            style::Print(format!(
+                "{:<24}",
+                format!("Finesse errors: {}/{}", finesse.errors, finesse.placements)
+            )),
+        )?;
+        self.writer.flush()?;
+        return Ok(());
+    }
+    /// Describes the zone meter, or the time left and rows banked while the
+    /// zone is active.
+    fn zone_string(zone: &Zone, banked_rows: usize) -> String {
+        match zone.remaining() {
+            Some(remaining) => format!(
+                "ZONE {:.1}s  Banked: {}",
+                remaining.as_secs_f64(),
+                banked_rows
+            ),
+            None if zone.can_activate() => format!("Zone: {}/{} ready", zone.meter(), Zone::FULL),
+            None => format!("Zone: {}/{}", zone.meter(), Zone::FULL),
+        }
+    }
+    pub fn draw_zone(&mut self, zone: &Zone, banked_rows: usize) -> std::io::Result<()> {
+        queue!(
+            self.writer,
+            self.layout.side(0, 22),
+            style::Print(format!("{:<24}", Self::zone_string(zone, banked_rows))),
+        )?;
+        self.writer.flush()?;
+        return Ok(());
+    }
+    fn combo_string(combo: Option<u64>) -> String {
+        match combo {
+            Some(combo) if combo > 0 => format!("Combo x{}", combo),
+            _other => String::new(),
+        }
+    }
+    fn back_to_back_string(back_to_back: Option<u64>) -> String {
+        match back_to_back {
+            Some(chain) if chain > 0 => format!("Back-to-Back x{}", chain),
+            _other => String::new(),
+        }
+    }
+    /// Writes a line of text at `at`, unless it is already there.
+    fn print_line(&mut self, at: cursor::MoveTo, text: String) -> std::io::Result<()> {
+        if self.lines.get(&(at.0, at.1)) == Some(&text) {
+            return Ok(());
+        }
+        queue!(self.writer, at, style::Print(&text))?;
+        self.lines.insert((at.0, at.1), text);
+        return Ok(());
+    }
+    /// Draws the panel beside the board of the score, level, lines cleared
+    /// and time played, `elapsed`. This is drawn every frame, and only the
+    /// lines that changed are written.
     pub fn draw_score(
         &mut self,
-        score: u64,
-        level: u64,
+        score: &Score,
         time_per_turn: u64,
+        elapsed: Duration,
     ) -> std::io::Result<()> {
+        let lines = [
+            format!("Score: {}", score.points),
+            format!("Level: {} ({} ms per turn)", score.level, time_per_turn),
+            format!(
+                "Lines: {} (next level in {})",
+                score.lines,
+                score.lines_to_next_level()
+            ),
+            format!("Time: {}", format_time(elapsed)),
+            Self::combo_string(score.combo),
+        ];
+        for (i, line) in lines.into_iter().enumerate() {
+            self.print_line(self.layout.side(0, 13 + i as u16), format!("{:<26}", line))?;
+        }
+        let back_to_back = Self::back_to_back_string(score.back_to_back);
+        self.print_line(self.layout.side(0, 19), format!("{:<26}", back_to_back))?;
+        self.writer.flush()?;
+        return Ok(());
+    }
+    /// Announces the last scoring clear, e.g. "T-Spin Double", below the
+    /// score, with a highlighted banner above it for a perfect clear.
+    pub fn draw_clear_announcement(
+        &mut self,
+        last_clear: Option<LineClear>,
+    ) -> std::io::Result<()> {
+        let announcement = last_clear
+            .and_then(|line_clear| line_clear.name())
+            .unwrap_or_default();
         queue!(
             self.writer,
-            cursor::MoveTo(20, 13),
-            style::Print(format!("Score: {}", score)),
-            cursor::MoveTo(20, 14),
-            style::Print(format!("Current Level: {}", level)),
-            cursor::MoveTo(20, 15),
-            style::Print(format!("Time per turn: {} ms", time_per_turn)),
+            self.layout.side(0, 18),
+            style::Print(format!("{:<24}", announcement)),
+            self.layout.side(0, 12),
         )?;
+        if last_clear.is_some_and(|line_clear| line_clear.perfect_clear) {
+            queue!(
+                self.writer,
+                style::SetAttribute(style::Attribute::Reverse),
+                style::Print(" PERFECT CLEAR! "),
+                style::SetAttribute(style::Attribute::NoReverse),
+            )?;
+        } else {
+            queue!(self.writer, style::Print(format!("{:<16}", "")))?;
+        }
+        self.writer.flush()?;
+        return Ok(());
+    }
+    /// Draws a list of options below the intro, highlighting the selected
+    /// one and showing its description underneath.
+    pub fn draw_menu(
+        &mut self,
+        title: &str,
+        options: &[(&str, &str)],
+        selected: usize,
+    ) -> std::io::Result<()> {
+        queue!(self.writer, self.layout.side(0, 8), style::Print(title))?;
+        for (i, (name, _)) in options.iter().enumerate() {
+            queue!(self.writer, self.layout.side(2, 10 + i as u16))?;
+            if i == selected {
+                queue!(
+                    self.writer,
+                    style::SetAttribute(style::Attribute::Reverse),
+                    style::Print(format!(" {} ", name)),
+                    style::SetAttribute(style::Attribute::NoReverse),
+                )?;
+            } else {
+                queue!(self.writer, style::Print(format!(" {} ", name)))?;
+            }
+        }
+        let description = options
+            .get(selected)
+            .map_or("", |(_, description)| description);
+        queue!(
+            self.writer,
+            self.layout.side(0, 11 + options.len() as u16),
+            terminal::Clear(terminal::ClearType::UntilNewLine),
+            style::Print(description),
+        )?;
+        self.writer.flush()?;
+        return Ok(());
+    }
+    /// Shows a results screen of labelled values.
+    pub fn draw_results(&mut self, title: &str, results: &[(&str, String)]) -> std::io::Result<()> {
+        queue!(self.writer, self.layout.side(0, 8), style::Print(title))?;
+        for (i, (label, value)) in results.iter().enumerate() {
+            queue!(
+                self.writer,
+                self.layout.side(2, 10 + i as u16),
+                style::Print(format!("{:<16}{}", label, value)),
+            )?;
+        }
+        queue!(
+            self.writer,
+            self.layout.side(0, 11 + results.len() as u16),
+            style::Print("Press Enter to continue."),
+        )?;
+        self.writer.flush()?;
+        return Ok(());
+    }
+    /// Clears the screen, leaving text to be written in the theme's colour.
+    pub fn clear(&mut self) -> std::io::Result<()> {
+        self.board.invalidate();
+        self.lines.clear();
+        self.next_drawn = None;
+        self.hold_drawn = None;
+        if let Some(drop_trail) = &mut self.drop_trail {
+            drop_trail.erase();
+        }
+        execute!(
+            self.writer,
+            style::SetForegroundColor(self.theme.text),
+            terminal::Clear(terminal::ClearType::All)
+        )
+    }
+    fn daily_best_string(date: &str, best: Option<u64>) -> String {
+        match best {
+            Some(best) => format!("Daily {} best: {}", date, best),
+            None => format!("Daily {}: not played yet", date),
+        }
+    }
+    /// Shows the best score in today's daily challenge under the title.
+    pub fn draw_daily_best(&mut self, date: &str, best: Option<u64>) -> std::io::Result<()> {
+        queue!(
+            self.writer,
+            self.layout.side(0, 5),
+            terminal::Clear(terminal::ClearType::UntilNewLine),
+            style::Print(Self::daily_best_string(date, best)),
+        )?;
+        self.writer.flush()?;
+        return Ok(());
+    }
+    /// The message shown when the terminal, `cols` wide and `rows` tall, is
+    /// too small for the game, which needs `width` columns, with where each
+    /// line starts to centre it. It is split over two lines if it does not
+    /// fit on one.
+    fn too_small_lines(cols: u16, rows: u16, width: u16, ascii: bool) -> Vec<(u16, u16, String)> {
+        let by = if ascii { "x" } else { "×" };
+        let title = String::from("terminal too small");
+        let sizes = format!(
+            "(need {}{}{}, have {}{}{})",
+            width,
+            by,
+            Layout::HEIGHT,
+            cols,
+            by,
+            rows
+        );
+        let message = format!("{} {}", title, sizes);
+        let lines = match message.chars().count() <= cols as usize {
+            true => vec![message],
+            false => vec![title, sizes],
+        };
+        let top = (rows / 2).saturating_sub(lines.len() as u16 / 2);
+        lines
+            .into_iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let col = cols.saturating_sub(line.chars().count() as u16) / 2;
+                (col, top + i as u16, line)
+            })
+            .collect()
+    }
+    /// Clears the screen for a message, centred in the terminal `cols` wide
+    /// and `rows` tall, saying it is too small for the game and how big it
+    /// needs to be, in place of a board that would not fit.
+    pub fn draw_too_small(&mut self, cols: u16, rows: u16) -> std::io::Result<()> {
+        self.clear()?;
+        let width = Layout::width(self.input_display.is_some());
+        for (col, row, line) in Self::too_small_lines(cols, rows, width, self.ascii) {
+            queue!(self.writer, cursor::MoveTo(col, row), style::Print(line))?;
+        }
         self.writer.flush()?;
         return Ok(());
     }
     pub fn draw_intro(&mut self) -> std::io::Result<()> {
-        let tetris_art = vec![
+        let tetris_art = [
             String::from("##### ##### ##### ###   #####   ### "),
             String::from("  #   #       #   #  #    #    # "),
             String::from("  #   ####    #   ##      #     ##"),
             String::from("  #   #       #   # #     #       #"),
             String::from("  #   ####    #   #  #  #####   ##"),
         ];
-        for i in 0..tetris_art.len() {
+        for (i, line) in tetris_art.iter().enumerate() {
             queue!(
                 self.writer,
-                cursor::MoveTo(20, i as u16),
-                style::Print(&tetris_art[i]),
+                self.layout.side(0, i as u16),
+                style::Print(line),
             )?;
         }
         queue!(
             self.writer,
-            cursor::MoveTo(20, 6),
-            style::Print(
-                "Use the arrows to move, 'x' to rotate clockwise and 'z' to rotate anticlockise. Hit Esc to quit."
-            ),
+            self.layout.side(0, 6),
+            style::Print(controls_help(&self.keymap)),
         )?;
         self.writer.flush()?;
         return Ok(());
     }
 }
 
+/// How to play with the keys of `keymap`, for the intro.
+fn controls_help(keymap: &Keymap) -> String {
+    let keys = |command| {
+        let names: Vec<String> = keymap
+            .key_names(command)
+            .split_whitespace()
+            .map(|name| format!("'{}'", name))
+            .collect();
+        match names.is_empty() {
+            true => String::from("no key"),
+            false => names.join(" or "),
+        }
+    };
+    format!(
+        "Use {} and {} to move, {} to rotate clockwise and {} to rotate anticlockwise, {} to rotate 180. {} hard drops, {} drops without locking, {} holds, {} enters the zone, {} pauses, {} quits and {} restarts.",
+        keys(Command::MoveLeft),
+        keys(Command::MoveRight),
+        keys(Command::RotateClockwise),
+        keys(Command::RotateAnticlockwise),
+        keys(Command::Rotate180),
+        keys(Command::HardDrop),
+        keys(Command::SonicDrop),
+        keys(Command::Hold),
+        keys(Command::ActivateZone),
+        keys(Command::Pause),
+        keys(Command::Quit),
+        keys(Command::Restart),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::keymap::KeymapPreset;
     struct TestWriter {
         buffer: Vec<u8>,
     }
@@ -134,27 +1367,198 @@ mod tests {
     }
     #[test]
     fn test_cli_view_generates_board() {
-        let expected_string = vec![String::from("|          |"); 16];
+        let expected_string = vec![String::from("|          |"); 20];
         let tetris_board = TetrisBoard::new();
         let cli_string = CliView::<TestWriter>::generate_board_string_view(&tetris_board);
         assert_eq!(cli_string, expected_string);
     }
 
+    #[test]
+    fn test_cli_view_generates_hold() {
+        let empty_hold =
+            CliView::<TestWriter>::generate_hold_string_view(None, PieceSet::Tetrominoes);
+        assert_eq!(empty_hold, vec![String::from("    "); 2]);
+        let held_i = CliView::<TestWriter>::generate_hold_string_view(
+            Some(PieceShape::I),
+            PieceSet::Tetrominoes,
+        );
+        assert_eq!(held_i, vec![String::from("xxxx"), String::from("    ")]);
+        let held_t = CliView::<TestWriter>::generate_hold_string_view(
+            Some(PieceShape::T),
+            PieceSet::Tetrominoes,
+        );
+        assert_eq!(held_t, vec![String::from(" x  "), String::from("xxx ")]);
+        let held_f = CliView::<TestWriter>::generate_hold_string_view(
+            Some(PieceShape::F),
+            PieceSet::Pentominoes,
+        );
+        assert_eq!(
+            held_f,
+            vec![
+                String::from(" xx  "),
+                String::from("xx   "),
+                String::from(" x   ")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_input_display_lists_held_keys_and_last_commands() {
+        let mut input_display = InputDisplay::new();
+        let input = |command, state| KeyInput { command, state };
+        input_display.observe(input(Command::MoveLeft, KeyState::Pressed));
+        input_display.observe(input(Command::MoveLeft, KeyState::Pressed));
+        input_display.observe(input(Command::HardDrop, KeyState::Tapped));
+        let lines = input_display.lines();
+        assert_eq!(lines[1], "Held: Left");
+        assert_eq!(
+            lines[2..4],
+            [String::from("  Drop"), String::from("  Left")]
+        );
+        input_display.observe(input(Command::MoveLeft, KeyState::Released));
+        for _ in 0..InputDisplay::HISTORY {
+            input_display.observe(input(Command::Rotate180, KeyState::Tapped));
+        }
+        let lines = input_display.lines();
+        assert_eq!(lines[1], "Held: ");
+        assert!(lines[2..].iter().all(|line| line == "  180"));
+    }
+
+    #[test]
+    fn test_text_is_written_across_the_board() {
+        let mut test_viewer = CliView::with_writer(TestWriter { buffer: Vec::new() });
+        test_viewer
+            .draw_board_text(&TetrisBoard::new(), "GO")
+            .expect("Writing to test writer failed.");
+        let written = String::from_utf8_lossy(&test_viewer.writer.buffer).into_owned();
+        assert!(written.contains("|    GO    |"));
+    }
+
+    #[test]
+    fn test_next_pieces_drawn_in_a_box() {
+        let lines = CliView::<TestWriter>::piece_box_lines(
+            "Next",
+            &[PieceShape::T, PieceShape::I],
+            PieceSet::Tetrominoes,
+            false,
+        );
+        let text: Vec<&str> = lines.iter().map(|(line, _)| line.as_str()).collect();
+        assert_eq!(
+            text,
+            vec![
+                "┌Next┐",
+                "│ x  │",
+                "│xxx │",
+                "│    │",
+                "│xxxx│",
+                "│    │",
+                "└────┘"
+            ]
+        );
+        assert_eq!(lines[1].1, Some(PieceShape::T));
+        assert_eq!(lines[3].1, None);
+        let ascii =
+            CliView::<TestWriter>::piece_box_lines("Hold", &[], PieceSet::Pentominoes, true);
+        let text: Vec<&str> = ascii.iter().map(|(line, _)| line.as_str()).collect();
+        assert_eq!(
+            text,
+            vec!["+Hold-+", "|     |", "|     |", "|     |", "+-----+"]
+        );
+    }
+
+    #[test]
+    fn test_too_small_message_centred_in_the_terminal() {
+        assert_eq!(
+            CliView::<TestWriter>::too_small_lines(80, 20, Layout::WIDTH, false),
+            vec![(
+                18,
+                10,
+                String::from("terminal too small (need 55×24, have 80×20)")
+            )]
+        );
+        assert_eq!(
+            CliView::<TestWriter>::too_small_lines(40, 20, Layout::width(true), true),
+            vec![
+                (11, 9, String::from("terminal too small")),
+                (8, 10, String::from("(need 75x24, have 40x20)")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chosen_pieces_listed_in_order() {
+        assert_eq!(
+            CliView::<TestWriter>::chosen_pieces_string(&[]),
+            "Chosen: -"
+        );
+        assert_eq!(
+            CliView::<TestWriter>::chosen_pieces_string(&[PieceShape::T, PieceShape::I5]),
+            "Chosen: T I5"
+        );
+    }
+
+    #[test]
+    fn test_daily_best_shown_once_played() {
+        assert_eq!(
+            CliView::<TestWriter>::daily_best_string("2025-10-15", None),
+            "Daily 2025-10-15: not played yet"
+        );
+        assert_eq!(
+            CliView::<TestWriter>::daily_best_string("2025-10-15", Some(4200)),
+            "Daily 2025-10-15 best: 4200"
+        );
+    }
+
+    #[test]
+    fn test_items_listed_oldest_first() {
+        assert_eq!(CliView::<TestWriter>::items_string(&[]), "");
+        assert_eq!(
+            CliView::<TestWriter>::items_string(&[Item::SlowGravity, Item::ClearBottomRow]),
+            "Items: Slow Down, Clear Row"
+        );
+    }
+
+    #[test]
+    fn test_zone_shows_meter_then_time_left() {
+        let mut zone = Zone::new();
+        zone.fill(3);
+        assert_eq!(CliView::<TestWriter>::zone_string(&zone, 0), "Zone: 3/20");
+        zone.fill(Zone::MIN_LINES);
+        assert_eq!(
+            CliView::<TestWriter>::zone_string(&zone, 0),
+            "Zone: 8/20 ready"
+        );
+        zone.activate();
+        assert_eq!(
+            CliView::<TestWriter>::zone_string(&zone, 2),
+            "ZONE 8.0s  Banked: 2"
+        );
+    }
+
+    #[test]
+    fn test_combo_only_shown_after_second_clear() {
+        assert_eq!(CliView::<TestWriter>::combo_string(None), "");
+        assert_eq!(CliView::<TestWriter>::combo_string(Some(0)), "");
+        assert_eq!(CliView::<TestWriter>::combo_string(Some(3)), "Combo x3");
+    }
+
+    #[test]
+    fn test_back_to_back_only_shown_for_chains() {
+        assert_eq!(CliView::<TestWriter>::back_to_back_string(Some(0)), "");
+        assert_eq!(
+            CliView::<TestWriter>::back_to_back_string(Some(2)),
+            "Back-to-Back x2"
+        );
+    }
+
     struct CommandMapping {}
     impl CommandMapping {
-        const MOVE_TO_START: [u8; 6] = [27, 91, 49, 59, 49, 72];
-        const MOVE_TO_NEXT_LINE: [u8; 4] = [27, 91, 49, 69];
         const SET_UNDERLINED: [u8; 4] = [27, 91, 52, 109];
         const SET_NOT_UNDERLINED: [u8; 5] = [27, 91, 50, 52, 109];
-        fn move_to(col: u8, row: u8) -> [u8; 6] {
-            return [
-                Self::MOVE_TO_START[0],
-                Self::MOVE_TO_START[1],
-                Self::MOVE_TO_START[2] + row,
-                Self::MOVE_TO_START[3],
-                Self::MOVE_TO_START[4] + col,
-                Self::MOVE_TO_START[5],
-            ];
+        const SET_FOREGROUND_MAGENTA: &'static [u8] = b"\x1b[38;5;13m";
+        const SET_FOREGROUND_RESET: &'static [u8] = b"\x1b[39m";
+        fn move_to(col: u16, row: u16) -> Vec<u8> {
+            format!("\x1b[{};{}H", row + 1, col + 1).into_bytes()
         }
     }
 
@@ -165,52 +1569,286 @@ mod tests {
         let board_row_bytes: [u8; 12] = board_row.as_bytes().try_into().unwrap();
 
         // Construct expected buffer from commands
-        let expected_buffer: Vec<u8> = CommandMapping::MOVE_TO_START
+        let board_col = Layout::default().board_col();
+        let expected_buffer: Vec<u8> = CommandMapping::move_to(board_col, 0)
             .into_iter()
             .chain(board_row_bytes)
-            .chain(CommandMapping::MOVE_TO_NEXT_LINE)
+            .chain(CommandMapping::move_to(board_col, 1))
             .chain(CommandMapping::SET_UNDERLINED)
             .chain(board_row_bytes)
             .chain(CommandMapping::SET_NOT_UNDERLINED)
             .collect();
 
         let cli_string = vec![String::from(board_row); 2];
-        let mut test_viewer = CliView {
-            writer: TestWriter { buffer: Vec::new() },
-        };
+        let mut test_viewer = CliView::with_writer(TestWriter { buffer: Vec::new() });
         test_viewer
-            .draw_board(cli_string)
+            .draw_cells(CliView::<TestWriter>::board_cells(
+                &cli_string,
+                &Theme::default(),
+            ))
             .expect("Writing to test writer failed.");
         assert_eq!(test_viewer.writer.buffer, expected_buffer);
     }
 
     #[cfg(unix)]
     #[test]
-    fn test_cli_view_writes_piece() {
+    fn test_cli_view_only_writes_changed_cells() {
         // Construct expected buffer from commands
-        let expected_buffer: Vec<u8> = CommandMapping::move_to(2, 1)
+        let board_col = Layout::default().board_col();
+        let expected_buffer: Vec<u8> = CommandMapping::move_to(board_col + 2, 1)
             .into_iter()
-            .chain(['x' as u8])
-            .chain(CommandMapping::move_to(3, 1))
-            .chain(['x' as u8])
-            .chain(CommandMapping::move_to(3, 2))
-            .chain(['x' as u8])
-            .chain(CommandMapping::move_to(4, 2))
-            .chain(['x' as u8])
+            .chain(CommandMapping::SET_FOREGROUND_MAGENTA.iter().copied())
+            .chain([b'x', b'x'])
+            .chain(CommandMapping::move_to(board_col + 3, 2))
+            .chain([b'x', b'x'])
+            .chain(CommandMapping::SET_FOREGROUND_RESET.iter().copied())
             .collect();
 
-        let mut test_viewer = CliView {
-            writer: TestWriter { buffer: Vec::new() },
-        };
+        let mut test_viewer = CliView::with_writer(TestWriter { buffer: Vec::new() });
+        let cli_string = vec![String::from("|          |"); 4];
+        let board = CliView::<TestWriter>::board_cells(&cli_string, &Theme::default());
+        test_viewer
+            .draw_cells(board.clone())
+            .expect("Writing to test writer failed.");
+        test_viewer.writer.buffer.clear();
+        test_viewer
+            .draw_cells(board.clone())
+            .expect("Writing to test writer failed.");
+        assert!(test_viewer.writer.buffer.is_empty());
+
         let piece_coords = vec![
             Coord { col: 1, row: 1 },
             Coord { col: 2, row: 1 },
             Coord { col: 2, row: 2 },
             Coord { col: 3, row: 2 },
         ];
+        let mut frame = board;
+        CliView::<TestWriter>::place_piece(&mut frame, piece_coords, style::Color::Magenta);
         test_viewer
-            .draw_piece(piece_coords)
+            .draw_cells(frame)
             .expect("Writing to test writer failed.");
         assert_eq!(test_viewer.writer.buffer, expected_buffer);
     }
+
+    #[test]
+    fn test_ghost_piece_drawn_in_the_theme_style() {
+        let cli_string = vec![String::from("|    |"); 2];
+        let coords = vec![Coord { col: 1, row: 1 }, Coord { col: 2, row: 1 }];
+        let ghost_line = |ghost_style| {
+            let mut theme = Theme::default();
+            theme.ghost_style = ghost_style;
+            let mut frame = CliView::<TestWriter>::board_cells(&cli_string, &theme);
+            let color = style::Color::Magenta;
+            CliView::<TestWriter>::place_ghost(&mut frame, coords.clone(), color, &theme);
+            frame[1].clone()
+        };
+        let symbols = |cells: &[Cell]| cells.iter().map(|cell| cell.symbol).collect::<String>();
+        let outline = ghost_line(GhostStyle::Outline);
+        assert_eq!(symbols(&outline), "| □□ |");
+        assert_eq!(outline[2].style.color, style::Color::Magenta);
+        let dim = ghost_line(GhostStyle::Dim);
+        assert_eq!(symbols(&dim), "| xx |");
+        assert_eq!(dim[2].style.color, Theme::default().ghost);
+        assert_eq!(symbols(&ghost_line(GhostStyle::Glyph)), "| ** |");
+        assert_eq!(symbols(&ghost_line(GhostStyle::Off)), "|    |");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hold_greyed_out_once_used() {
+        let grey = b"\x1b[38;5;8m";
+        let mut test_viewer = CliView::with_writer(TestWriter { buffer: Vec::new() });
+        let contains_grey = |buffer: &[u8]| buffer.windows(grey.len()).any(|bytes| bytes == grey);
+        test_viewer
+            .draw_hold(Some(PieceShape::T), PieceSet::Tetrominoes, true)
+            .expect("Writing to test writer failed.");
+        assert!(!contains_grey(&test_viewer.writer.buffer));
+        test_viewer.writer.buffer.clear();
+        test_viewer
+            .draw_hold(Some(PieceShape::T), PieceSet::Tetrominoes, true)
+            .expect("Writing to test writer failed.");
+        assert!(test_viewer.writer.buffer.is_empty());
+        test_viewer
+            .draw_hold(Some(PieceShape::T), PieceSet::Tetrominoes, false)
+            .expect("Writing to test writer failed.");
+        assert!(contains_grey(&test_viewer.writer.buffer));
+    }
+
+    #[test]
+    fn test_half_blocks_draw_two_rows_to_a_line() {
+        let cli_string = vec![
+            String::from("|    |"),
+            String::from("|    |"),
+            String::from("|  o |"),
+            String::from("|oo o|"),
+        ];
+        let mut frame = CliView::<TestWriter>::board_cells(&cli_string, &Theme::default());
+        let piece_coords = vec![
+            Coord { col: 0, row: 0 },
+            Coord { col: 0, row: 1 },
+            Coord { col: 1, row: 1 },
+            Coord { col: 1, row: 2 },
+        ];
+        CliView::<TestWriter>::place_piece(&mut frame, piece_coords, style::Color::Magenta);
+        let half_blocks = CliView::<TestWriter>::half_block_cells(&frame);
+        let lines: Vec<String> = half_blocks
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.symbol).collect())
+            .collect();
+        assert_eq!(lines, vec!["|█▄  |", "|▄▄▀▄|"]);
+        // The stack below the piece is drawn in front of it, as the
+        // terminal's own colour cannot be a background.
+        assert_eq!(half_blocks[1][2].style.color, style::Color::Reset);
+        assert_eq!(half_blocks[1][2].style.background, style::Color::Magenta);
+        assert!(half_blocks[1][0].style.underlined);
+    }
+
+    #[test]
+    fn test_ascii_needed_without_unicode_or_colour() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert!(!needs_ascii(env(&[
+            ("TERM", "xterm-256color"),
+            ("LANG", "en_GB.UTF-8")
+        ])));
+        assert!(needs_ascii(env(&[
+            ("TERM", "xterm-256color"),
+            ("LANG", "en_GB.UTF-8"),
+            ("NO_COLOR", "1")
+        ])));
+        if cfg!(windows) {
+            return;
+        }
+        assert!(needs_ascii(env(&[("TERM", "dumb"), ("LANG", "C.UTF-8")])));
+        assert!(needs_ascii(env(&[("TERM", "xterm"), ("LANG", "C")])));
+        assert!(!needs_ascii(env(&[
+            ("TERM", "xterm"),
+            ("LC_ALL", "de_DE.utf8"),
+            ("LANG", "C")
+        ])));
+    }
+
+    #[test]
+    fn test_ascii_cells_draw_the_stack_on_dots() {
+        let cli_string = vec![String::from("| o. |"), String::from("|oo o|")];
+        let mut frame = CliView::<TestWriter>::board_cells(&cli_string, &Theme::monochrome());
+        CliView::<TestWriter>::place_piece(
+            &mut frame,
+            vec![Coord { col: 3, row: 0 }],
+            style::Color::Reset,
+        );
+        let lines: Vec<String> = CliView::<TestWriter>::ascii_cells(frame)
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.symbol).collect())
+            .collect();
+        assert_eq!(lines, vec!["|.#:x|", "|##.#|"]);
+    }
+
+    #[test]
+    fn test_score_panel_only_writes_lines_that_changed() {
+        let mut test_viewer = CliView::with_writer(TestWriter { buffer: Vec::new() });
+        let score = Score::new();
+        test_viewer
+            .draw_score(&score, 1000, Duration::from_secs(1))
+            .expect("Writing to test writer failed.");
+        let written = String::from_utf8_lossy(&test_viewer.writer.buffer).into_owned();
+        assert!(written.contains("Score: 0"));
+        assert!(written.contains("Time: 0:01.000"));
+        test_viewer.writer.buffer.clear();
+        test_viewer
+            .draw_score(&score, 1000, Duration::from_secs(2))
+            .expect("Writing to test writer failed.");
+        let written = String::from_utf8_lossy(&test_viewer.writer.buffer).into_owned();
+        assert!(written.contains("Time: 0:02.000"));
+        assert!(!written.contains("Score"));
+    }
+
+    #[test]
+    fn test_invisible_stack_fades_and_flashes_on_clear() {
+        let mut tetris_board = TetrisBoard::new();
+        tetris_board.board[19] = vec![true; TetrisBoard::NUM_COLS];
+        tetris_board.board[19][0] = false;
+        tetris_board.board[18][5] = true;
+        let start = Instant::now();
+        let mut stack_fade = StackFade::new();
+        let view = |stack_fade: &StackFade, tetris_board: &TetrisBoard, now: Instant| {
+            let mut view_lines = CliView::<TestWriter>::generate_board_string_view(tetris_board);
+            stack_fade.apply(&mut view_lines, now);
+            view_lines
+        };
+        stack_fade.update(&tetris_board, start);
+        assert_eq!(view(&stack_fade, &tetris_board, start)[19], "| ooooooooo|");
+        let dimmed = start + StackFade::FADE / 2;
+        assert_eq!(view(&stack_fade, &tetris_board, dimmed)[19], "| .........|");
+        let hidden = start + StackFade::FADE;
+        assert_eq!(view(&stack_fade, &tetris_board, hidden)[18], "|          |");
+        // Clearing the bottom row brings the rest of the stack back.
+        tetris_board.board[19][0] = true;
+        tetris_board.clear_rows();
+        stack_fade.update(&tetris_board, hidden);
+        assert_eq!(view(&stack_fade, &tetris_board, hidden)[19], "|     o    |");
+    }
+
+    #[test]
+    fn test_hard_drop_leaves_a_fading_trail() {
+        let tetris_board = TetrisBoard::new();
+        let piece = TetrisPiece::new(&PieceShape::O);
+        let distance = piece.drop_distance(&tetris_board);
+        let hard_drop = HardDrop {
+            piece: piece.shifted(0, distance),
+            distance,
+            piece_number: 0,
+        };
+        let theme = Theme::default();
+        let start = Instant::now();
+        let mut drop_trail = DropTrail::new();
+        let view = |drop_trail: &DropTrail, now: Instant| {
+            let board_string = CliView::<TestWriter>::generate_board_string_view(&tetris_board);
+            let mut frame = CliView::<TestWriter>::board_cells(&board_string, &theme);
+            drop_trail.apply(&mut frame, &theme, now);
+            frame
+        };
+        drop_trail.update(Some(&hard_drop), &theme, start);
+        assert!(drop_trail.is_fading(start));
+        let frame = view(&drop_trail, start);
+        let trail_rows = frame
+            .iter()
+            .filter(|cells| cells.iter().any(|cell| cell.symbol == '│'))
+            .count();
+        assert_eq!(
+            trail_rows as i16,
+            distance.min(TetrisBoard::NUM_ROWS as i16 - 2)
+        );
+        let trail_cell = frame[17].iter().find(|cell| cell.symbol == '│').unwrap();
+        assert_eq!(trail_cell.style.color, theme.color(PieceShape::O));
+        let dimmed = view(&drop_trail, start + DropTrail::FADE / 2);
+        let trail_cell = dimmed[17].iter().find(|cell| cell.symbol == '│').unwrap();
+        assert_eq!(trail_cell.style.color, theme.ghost);
+        let faded = start + DropTrail::FADE;
+        assert!(!drop_trail.is_fading(faded));
+        assert!(view(&drop_trail, faded)
+            .iter()
+            .flatten()
+            .all(|cell| cell.symbol != '│'));
+        // The same drop seen again does not leave another trail, even once
+        // the screen has been cleared.
+        drop_trail.update(Some(&hard_drop), &theme, faded);
+        assert!(!drop_trail.is_fading(faded));
+        drop_trail.erase();
+        drop_trail.update(Some(&hard_drop), &theme, faded);
+        assert!(!drop_trail.is_fading(faded));
+    }
+    #[test]
+    fn test_controls_help_lists_the_keys_in_play() {
+        let help = controls_help(&Keymap::default());
+        assert!(help.starts_with("Use 'left' and 'right' to move, 'x' to rotate clockwise"));
+        assert!(help.contains("'p' or 'esc' pauses"));
+        let help = controls_help(&Keymap::preset(KeymapPreset::VIM));
+        assert!(help.starts_with("Use 'h' and 'l' to move, 'k' to rotate clockwise"));
+    }
 }
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+/// The zone meter, which fills as lines are cleared. Once it holds enough
+/// lines the zone can be entered, stopping gravity for a time that grows
+/// with the meter. Rows filled in the zone are banked at the bottom of the
+/// board rather than cleared, and all clear together when the zone ends.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Zone {
+    /// Lines cleared towards the zone, up to `FULL`.
+    meter: u64,
+    /// Time left before the zone ends, while it is active.
+    remaining: Option<Duration>,
+}
+impl Zone {
+    /// Lines that fill the meter.
+    pub const FULL: u64 = 20;
+    /// Fewest lines in the meter that the zone can be entered with.
+    pub const MIN_LINES: u64 = 5;
+    /// Time the zone lasts for each line in the meter.
+    const TIME_PER_LINE: Duration = Duration::from_secs(1);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn meter(&self) -> u64 {
+        self.meter
+    }
+    /// Adds cleared lines to the meter. The meter does not fill while the
+    /// zone is active.
+    pub fn fill(&mut self, lines: u64) {
+        if !self.is_active() {
+            self.meter = (self.meter + lines).min(Self::FULL);
+        }
+    }
+    pub fn can_activate(&self) -> bool {
+        !self.is_active() && self.meter >= Self::MIN_LINES
+    }
+    /// Enters the zone, emptying the meter into its timer. Returns whether
+    /// the zone was entered.
+    pub fn activate(&mut self) -> bool {
+        if !self.can_activate() {
+            return false;
+        }
+        self.remaining = Some(Self::TIME_PER_LINE * self.meter as u32);
+        self.meter = 0;
+        return true;
+    }
+    pub fn is_active(&self) -> bool {
+        self.remaining.is_some()
+    }
+    /// Time left before the zone ends, if it is active.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.remaining
+    }
+    /// Runs down the zone's timer. Returns whether the zone ended.
+    pub fn tick(&mut self, elapsed: Duration) -> bool {
+        let remaining = match self.remaining {
+            Some(remaining) => remaining.saturating_sub(elapsed),
+            None => return false,
+        };
+        if remaining.is_zero() {
+            self.remaining = None;
+            return true;
+        }
+        self.remaining = Some(remaining);
+        return false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone_needs_enough_lines_to_activate() {
+        let mut zone = Zone::new();
+        zone.fill(Zone::MIN_LINES - 1);
+        assert!(!zone.activate());
+        zone.fill(Zone::FULL);
+        assert_eq!(zone.meter(), Zone::FULL);
+        assert!(zone.activate());
+        assert_eq!(zone.meter(), 0);
+        assert_eq!(
+            zone.remaining(),
+            Some(Zone::TIME_PER_LINE * Zone::FULL as u32)
+        );
+        zone.fill(4);
+        assert_eq!(zone.meter(), 0);
+    }
+
+    #[test]
+    fn test_zone_ends_when_its_timer_runs_out() {
+        let mut zone = Zone::new();
+        zone.fill(Zone::MIN_LINES);
+        zone.activate();
+        let duration = zone.remaining().unwrap();
+        assert!(!zone.tick(duration / 2));
+        assert!(zone.is_active());
+        assert!(zone.tick(duration));
+        assert!(!zone.is_active());
+        assert!(!zone.tick(duration));
+    }
+}